@@ -0,0 +1,330 @@
+//! Decoding of the QR printed on AFIP (now ARCA) electronic invoices.
+//!
+//! The QR holds a URL such as `https://www.afip.gob.ar/fe/qr/?p=<payload>`,
+//! where the payload is base64-encoded JSON:
+//!
+//! ```json
+//! {"ver":1,"fecha":"2026-05-03","cuit":30712345679,"ptoVta":3,"tipoCmp":6,
+//!  "nroCmp":12345,"importe":15000.5,"moneda":"PES","ctz":1,
+//!  "tipoDocRec":80,"nroDocRec":20123456786,"tipoCodAut":"E","codAut":70417054367476}
+//! ```
+//!
+//! Only what a transaction needs is read; the receiver and authorization
+//! code are ignored.
+
+use crate::money::{cents_from_decimal, Currency, Rate};
+use base64::engine::general_purpose::STANDARD_NO_PAD;
+use base64::Engine;
+use chrono::NaiveDate;
+use serde_json::{Map, Value};
+
+const QR_HOSTS: [&str; 2] = ["afip.gob.ar", "arca.gob.ar"];
+
+/// The parts of an invoice QR a transaction uses.
+#[derive(Debug)]
+pub struct InvoiceQr {
+    pub date: NaiveDate,
+    /// Issuer CUIT formatted as `30-71234567-9`
+    pub issuer_cuit: String,
+    /// Comprobante type code (1 = Factura A, 6 = Factura B, ...)
+    pub kind: i64,
+    pub point_of_sale: i64,
+    pub number: i64,
+    /// Total in centavos, always positive
+    pub amount_cents: i64,
+    pub currency: Currency,
+    /// Pesos per unit of `currency`; `None` for pesos
+    pub exchange_rate: Option<Rate>,
+}
+
+impl InvoiceQr {
+    /// Letter of the comprobante, when it is one of the common classes.
+    fn letter(&self) -> Option<&'static str> {
+        match self.kind {
+            1..=5 | 39 | 60 | 63 | 201..=203 => Some("A"),
+            6..=10 | 40 | 61 | 64 | 206..=208 => Some("B"),
+            11..=15 | 211..=213 => Some("C"),
+            19..=21 => Some("E"),
+            51..=54 => Some("M"),
+            _ => None,
+        }
+    }
+
+    /// Credit notes give money back, so they are income.
+    pub fn is_credit_note(&self) -> bool {
+        matches!(self.kind, 3 | 8 | 13 | 21 | 53 | 203 | 208 | 213)
+    }
+
+    /// Human name of the comprobante, e.g. "Factura B".
+    pub fn kind_name(&self) -> String {
+        let class = match self.kind {
+            2 | 7 | 12 | 20 | 52 | 202 | 207 | 212 => "Nota de Débito",
+            _ if self.is_credit_note() => "Nota de Crédito",
+            _ => "Factura",
+        };
+        match self.letter() {
+            Some(letter) => format!("{} {}", class, letter),
+            None => format!("Comprobante {}", self.kind),
+        }
+    }
+
+    /// Invoice number as printed, e.g. "Factura B 00003-00012345".
+    pub fn invoice_number(&self) -> String {
+        format!(
+            "{} {:05}-{:08}",
+            self.kind_name(),
+            self.point_of_sale,
+            self.number
+        )
+    }
+}
+
+/// Whether `digits` is an 11-digit CUIT/CUIL with a correct check digit.
+pub fn cuit_is_valid(digits: &str) -> bool {
+    const WEIGHTS: [u32; 10] = [5, 4, 3, 2, 7, 6, 5, 4, 3, 2];
+
+    let digits: Vec<u32> = digits
+        .chars()
+        .filter(|c| *c != '-')
+        .map(|c| c.to_digit(10))
+        .collect::<Option<_>>()
+        .unwrap_or_default();
+    if digits.len() != 11 {
+        return false;
+    }
+
+    let sum: u32 = WEIGHTS.iter().zip(&digits).map(|(w, d)| w * d).sum();
+    let check = match 11 - sum % 11 {
+        11 => 0,
+        10 => return false,
+        check => check,
+    };
+    digits[10] == check
+}
+
+/// `30712345679` as `30-71234567-9`.
+pub fn format_cuit(digits: &str) -> String {
+    let digits: String = digits.chars().filter(char::is_ascii_digit).collect();
+    if digits.len() != 11 {
+        return digits;
+    }
+    format!("{}-{}-{}", &digits[..2], &digits[2..10], &digits[10..])
+}
+
+/// Undo URL percent-encoding (`%3D` and the like).
+fn percent_decode(raw: &str) -> Result<String, String> {
+    let bytes = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let byte = raw
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or("El código QR tiene caracteres mal codificados")?;
+            decoded.push(byte);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).map_err(|_| "El código QR tiene caracteres mal codificados".into())
+}
+
+/// The base64 payload of a QR URL; a bare payload is accepted as is.
+fn payload(qr: &str) -> Result<String, String> {
+    let qr = qr.trim();
+    let Some((address, query)) = qr.split_once('?') else {
+        if qr.contains("://") {
+            return Err("El código QR no contiene los datos del comprobante".into());
+        }
+        return Ok(qr.to_string());
+    };
+
+    let host = address
+        .split_once("://")
+        .map_or(address, |(_, rest)| rest)
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    if !QR_HOSTS
+        .iter()
+        .any(|h| host == *h || host.ends_with(&format!(".{}", h)))
+    {
+        return Err("El código QR no es de una factura electrónica de AFIP".into());
+    }
+
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("p="))
+        .ok_or_else(|| "El código QR no contiene los datos del comprobante".into())
+        .and_then(percent_decode)
+}
+
+fn field<'a>(json: &'a Map<String, Value>, name: &str) -> Result<&'a Value, String> {
+    json.get(name)
+        .filter(|v| !v.is_null())
+        .ok_or_else(|| format!("Falta el dato '{}' en el código QR", name))
+}
+
+/// A number that some issuers encode as a string.
+fn number_field(json: &Map<String, Value>, name: &str) -> Result<f64, String> {
+    let value = field(json, name)?;
+    value
+        .as_f64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .filter(|n| n.is_finite())
+        .ok_or_else(|| format!("El dato '{}' del código QR no es un número", name))
+}
+
+fn integer_field(json: &Map<String, Value>, name: &str) -> Result<i64, String> {
+    let value = field(json, name)?;
+    value
+        .as_i64()
+        .or_else(|| value.as_str().and_then(|s| s.trim().parse().ok()))
+        .filter(|n| *n >= 0)
+        .ok_or_else(|| format!("El dato '{}' del código QR no es un número entero", name))
+}
+
+/// Decode and validate the text read from an invoice QR.
+pub fn parse(qr: &str) -> Result<InvoiceQr, String> {
+    // Standard or URL-safe alphabet, with or without padding
+    let payload: String = payload(qr)?
+        .trim()
+        .trim_end_matches('=')
+        .chars()
+        .map(|c| match c {
+            '-' => '+',
+            '_' => '/',
+            c => c,
+        })
+        .collect();
+    let bytes = STANDARD_NO_PAD
+        .decode(payload.as_bytes())
+        .map_err(|_| "Los datos del código QR no son base64 válido".to_string())?;
+    let json: Map<String, Value> = serde_json::from_slice(&bytes)
+        .map_err(|_| "Los datos del código QR no son un comprobante válido".to_string())?;
+
+    let date = field(&json, "fecha")?
+        .as_str()
+        .and_then(|s| NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d").ok())
+        .ok_or("La fecha del código QR no es válida")?;
+
+    let cuit = integer_field(&json, "cuit")?.to_string();
+    if !cuit_is_valid(&cuit) {
+        return Err(format!("El CUIT {} del emisor no es válido", cuit));
+    }
+
+    let amount = number_field(&json, "importe")?;
+    let amount_cents =
+        cents_from_decimal(amount.abs()).ok_or("El importe del código QR no es válido")?;
+    if amount_cents == 0 {
+        return Err("El importe del comprobante es 0".into());
+    }
+
+    let (currency, exchange_rate) = match field(&json, "moneda")?.as_str().map(str::trim) {
+        Some("PES") => (Currency::Ars, None),
+        Some("DOL") => {
+            let rate = Rate::from_decimal(number_field(&json, "ctz")?)
+                .filter(|rate| rate.millionths() > 0)
+                .ok_or("La cotización del código QR no es válida")?;
+            (Currency::Usd, Some(rate))
+        }
+        Some(other) => return Err(format!("Moneda '{}' no soportada", other)),
+        None => return Err("La moneda del código QR no es válida".into()),
+    };
+
+    Ok(InvoiceQr {
+        date,
+        issuer_cuit: format_cuit(&cuit),
+        kind: integer_field(&json, "tipoCmp")?,
+        point_of_sale: integer_field(&json, "ptoVta")?,
+        number: integer_field(&json, "nroCmp")?,
+        amount_cents,
+        currency,
+        exchange_rate,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+
+    const INVOICE: &str = r#"{"ver":1,"fecha":"2026-05-03","cuit":30712345671,"ptoVta":3,
+        "tipoCmp":6,"nroCmp":12345,"importe":15000.5,"moneda":"PES","ctz":1,
+        "tipoDocRec":80,"nroDocRec":20123456786,"tipoCodAut":"E","codAut":70417054367476}"#;
+
+    fn url(json: &str) -> String {
+        let payload = STANDARD.encode(json).replace('=', "%3D");
+        format!("https://www.afip.gob.ar/fe/qr/?p={}", payload)
+    }
+
+    #[test]
+    fn checks_cuit_digits() {
+        assert!(cuit_is_valid("30712345671"));
+        assert!(cuit_is_valid("20-12345678-6"));
+        assert!(!cuit_is_valid("30712345679"));
+        assert!(!cuit_is_valid("3071234567"));
+        assert!(!cuit_is_valid("3071234567a"));
+        assert_eq!(format_cuit("30712345671"), "30-71234567-1");
+        assert_eq!(format_cuit("123"), "123");
+    }
+
+    #[test]
+    fn reads_an_invoice_url() {
+        let invoice = parse(&url(INVOICE)).unwrap();
+        assert_eq!(invoice.date, NaiveDate::from_ymd_opt(2026, 5, 3).unwrap());
+        assert_eq!(invoice.issuer_cuit, "30-71234567-1");
+        assert_eq!(invoice.amount_cents, 1_500_050);
+        assert_eq!(invoice.currency, Currency::Ars);
+        assert_eq!(invoice.exchange_rate, None);
+        assert!(!invoice.is_credit_note());
+        assert_eq!(invoice.invoice_number(), "Factura B 00003-00012345");
+    }
+
+    #[test]
+    fn accepts_bare_and_url_safe_payloads() {
+        let bare = URL_SAFE_NO_PAD.encode(INVOICE);
+        assert_eq!(parse(&bare).unwrap().number, 12345);
+        let arca = url(INVOICE).replace("www.afip.gob.ar", "arca.gob.ar");
+        assert_eq!(parse(&arca).unwrap().number, 12345);
+    }
+
+    #[test]
+    fn reads_dollar_credit_notes() {
+        let json = INVOICE
+            .replace(
+                r#""moneda":"PES","ctz":1"#,
+                r#""moneda":"DOL","ctz":"1180.5""#,
+            )
+            .replace(r#""tipoCmp":6"#, r#""tipoCmp":3"#);
+        let invoice = parse(&url(&json)).unwrap();
+        assert_eq!(invoice.currency, Currency::Usd);
+        assert_eq!(invoice.exchange_rate, Some(Rate::parse("1180.5").unwrap()));
+        assert!(invoice.is_credit_note());
+        assert_eq!(invoice.kind_name(), "Nota de Crédito A");
+    }
+
+    #[test]
+    fn rejects_bad_codes() {
+        let cases = [
+            ("https://example.com/?p=abc".to_string(), "AFIP"),
+            ("https://www.afip.gob.ar/fe/qr/".to_string(), "datos"),
+            ("no es base64!".to_string(), "base64"),
+            (url(&INVOICE.replace("30712345671", "30712345679")), "CUIT"),
+            (url(&INVOICE.replace("15000.5", "0")), "0"),
+            (url(&INVOICE.replace("\"PES\"", "\"EUR\"")), "EUR"),
+            (
+                url(&INVOICE.replace("\"fecha\":\"2026-05-03\",", "")),
+                "fecha",
+            ),
+        ];
+        for (qr, expected) in cases {
+            let e = parse(&qr).unwrap_err();
+            assert!(e.contains(expected), "{}: {}", qr, e);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Content-addressed storage for receipt and invoice files.
+//!
+//! Files live under `<app_data_dir>/attachments/<first two hash chars>/` and
+//! are named after the SHA-256 of their content plus an extension derived
+//! from the detected type, so the same file attached twice is stored once.
+//! Rows in `attachments` point at a blob through their hash and MIME type,
+//! which together give its path; a blob is removed when the last row
+//! pointing at it goes away.
+
+use rusqlite::Connection;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Largest file accepted as an attachment.
+pub const MAX_ATTACHMENT_SIZE: usize = 25 * 1024 * 1024;
+
+/// Lowercase hex SHA-256 of `bytes`.
+pub fn hash_bytes(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// MIME type from the file signature, falling back to the file extension.
+pub fn detect_mime(bytes: &[u8], file_name: &str) -> &'static str {
+    if bytes.starts_with(b"%PDF") {
+        return "application/pdf";
+    }
+    if bytes.starts_with(&[0x89, b'P', b'N', b'G']) {
+        return "image/png";
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return "image/jpeg";
+    }
+    if bytes.starts_with(b"GIF8") {
+        return "image/gif";
+    }
+    if bytes.len() >= 12 && &bytes[..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    if bytes.len() >= 12
+        && &bytes[4..8] == b"ftyp"
+        && matches!(&bytes[8..12], b"heic" | b"heix" | b"mif1")
+    {
+        return "image/heic";
+    }
+
+    let extension = Path::new(file_name)
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "xml" => "application/xml",
+        "txt" => "text/plain",
+        _ => "application/octet-stream",
+    }
+}
+
+fn extension_for(mime_type: &str) -> Option<&'static str> {
+    match mime_type {
+        "application/pdf" => Some("pdf"),
+        "image/png" => Some("png"),
+        "image/jpeg" => Some("jpg"),
+        "image/gif" => Some("gif"),
+        "image/webp" => Some("webp"),
+        "image/heic" => Some("heic"),
+        "application/xml" => Some("xml"),
+        "text/plain" => Some("txt"),
+        _ => None,
+    }
+}
+
+/// Where the blob for `hash` is stored. The extension lets the system viewer
+/// pick the right application when the file is opened.
+pub fn blob_path(dir: &Path, hash: &str, mime_type: &str) -> PathBuf {
+    let name = match extension_for(mime_type) {
+        Some(extension) => format!("{}.{}", hash, extension),
+        None => hash.to_string(),
+    };
+    dir.join(hash.get(..2).unwrap_or("00")).join(name)
+}
+
+/// Write `bytes` to its blob unless an identical file is already stored.
+pub fn store(dir: &Path, hash: &str, mime_type: &str, bytes: &[u8]) -> Result<PathBuf, String> {
+    let path = blob_path(dir, hash, mime_type);
+    if path.exists() {
+        return Ok(path);
+    }
+
+    let parent = path.parent().unwrap_or(dir);
+    std::fs::create_dir_all(parent)
+        .map_err(|e| format!("No se pudo crear la carpeta de adjuntos: {}", e))?;
+    // Write to a temporary name first so a crash never leaves a truncated blob
+    let partial = path.with_extension("partial");
+    std::fs::write(&partial, bytes).map_err(|e| format!("No se pudo guardar el adjunto: {}", e))?;
+    std::fs::rename(&partial, &path)
+        .map_err(|e| format!("No se pudo guardar el adjunto: {}", e))?;
+
+    Ok(path)
+}
+
+/// Delete the blob for `hash` and `mime_type` if no attachment row points
+/// at it anymore.
+pub fn release(db: &Connection, dir: &Path, hash: &str, mime_type: &str) -> Result<(), String> {
+    let in_use: bool = db
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM attachments WHERE hash = ?1 AND mime_type = ?2",
+            [hash, mime_type],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if !in_use {
+        let path = blob_path(dir, hash, mime_type);
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("No se pudo eliminar el adjunto: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove blobs no row references, e.g. after transactions were deleted
+/// (their attachment rows go with them through ON DELETE CASCADE). Run at
+/// startup; failures only leave unused files behind.
+pub fn remove_orphans(db: &Connection, dir: &Path) -> Result<usize, String> {
+    let mut stmt = db
+        .prepare("SELECT DISTINCT hash, mime_type FROM attachments")
+        .map_err(|e| e.to_string())?;
+    let referenced: HashSet<PathBuf> = stmt
+        .query_map([], |row| {
+            let (hash, mime_type): (String, String) = (row.get(0)?, row.get(1)?);
+            Ok(blob_path(dir, &hash, &mime_type))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let Ok(buckets) = std::fs::read_dir(dir) else {
+        return Ok(0);
+    };
+    let mut removed = 0;
+    for bucket in buckets.flatten() {
+        let Ok(files) = std::fs::read_dir(bucket.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if !referenced.contains(&path) && std::fs::remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+
+    Ok(removed)
+}
@@ -0,0 +1,316 @@
+//! Category suggestions learned from the transaction history.
+//!
+//! A multinomial naive Bayes model over the words of the description, the
+//! order of magnitude of the amount and the currency. The model mirrors the
+//! `transactions` table: `refresh` compares ids and `updated_at` with what
+//! it has already seen and only retrains rows added, edited or deleted
+//! since, so keeping it current costs one cheap query per suggestion. Split
+//! transactions are left out, since they have no single category.
+
+use crate::dedup::description_tokens;
+use crate::money::Currency;
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// What one transaction contributed to the model.
+struct Sample {
+    updated_at: String,
+    category_id: i64,
+    features: Vec<String>,
+}
+
+#[derive(Default)]
+struct CategoryCounts {
+    samples: usize,
+    /// Sum of all feature counts
+    total: usize,
+    features: HashMap<String, usize>,
+}
+
+#[derive(Default)]
+pub struct Classifier {
+    seen: HashMap<i64, Sample>,
+    categories: HashMap<i64, CategoryCounts>,
+    /// Occurrences of every feature across categories; its size is the
+    /// vocabulary used for smoothing
+    vocabulary: HashMap<String, usize>,
+}
+
+/// Features of a movement: description words plus amount bucket and
+/// currency, which tell "Uber 3.500" apart from "Uber 95.000" only as much as
+/// the history says they differ.
+pub fn features(description: &str, amount_cents: i64, currency: Currency) -> Vec<String> {
+    let units = (amount_cents / 100).max(1);
+    let mut features = description_tokens(description);
+    features.push(format!("#monto:{}", units.ilog10()));
+    features.push(format!("#moneda:{}", currency));
+    features
+}
+
+impl Classifier {
+    /// Bring the model up to date with the `transactions` table.
+    pub fn refresh(&mut self, db: &Connection) -> Result<(), String> {
+        let mut stmt = db
+            .prepare(
+                "SELECT id, updated_at FROM transactions t
+                 WHERE t.deleted_at IS NULL
+                   AND NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)",
+            )
+            .map_err(|e| e.to_string())?;
+        let current: HashMap<i64, String> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let gone: Vec<i64> = self
+            .seen
+            .keys()
+            .filter(|id| !current.contains_key(id))
+            .copied()
+            .collect();
+        for id in gone {
+            self.remove(id);
+        }
+
+        let mut details = db
+            .prepare_cached(
+                "SELECT description, amount, currency, category_id FROM transactions WHERE id = ?1",
+            )
+            .map_err(|e| e.to_string())?;
+        for (id, updated_at) in current {
+            if self
+                .seen
+                .get(&id)
+                .is_some_and(|s| s.updated_at == updated_at)
+            {
+                continue;
+            }
+            self.remove(id);
+            let (description, amount_cents, currency, category_id) = details
+                .query_row([id], |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, i64>(1)?,
+                        row.get::<_, Currency>(2)?,
+                        row.get::<_, i64>(3)?,
+                    ))
+                })
+                .map_err(|e| e.to_string())?;
+            self.add(
+                id,
+                Sample {
+                    updated_at,
+                    category_id,
+                    features: features(&description, amount_cents, currency),
+                },
+            );
+        }
+
+        Ok(())
+    }
+
+    fn add(&mut self, id: i64, sample: Sample) {
+        let counts = self.categories.entry(sample.category_id).or_default();
+        counts.samples += 1;
+        counts.total += sample.features.len();
+        for feature in &sample.features {
+            *counts.features.entry(feature.clone()).or_default() += 1;
+            *self.vocabulary.entry(feature.clone()).or_default() += 1;
+        }
+        self.seen.insert(id, sample);
+    }
+
+    fn remove(&mut self, id: i64) {
+        let Some(sample) = self.seen.remove(&id) else {
+            return;
+        };
+        if let Some(counts) = self.categories.get_mut(&sample.category_id) {
+            counts.samples -= 1;
+            counts.total -= sample.features.len();
+            for feature in &sample.features {
+                decrement(&mut counts.features, feature);
+            }
+            if counts.samples == 0 {
+                self.categories.remove(&sample.category_id);
+            }
+        }
+        for feature in &sample.features {
+            decrement(&mut self.vocabulary, feature);
+        }
+    }
+
+    /// Rank `candidates` (category ids) for a movement with `features`, best
+    /// first, with the posterior probability of each. Categories never used
+    /// are not ranked; features never seen carry no information and are
+    /// ignored. Empty when nothing in the history relates to the movement.
+    pub fn rank(&self, features: &[String], candidates: &[i64]) -> Vec<(i64, f64)> {
+        let known: Vec<&String> = features
+            .iter()
+            .filter(|f| self.vocabulary.contains_key(*f))
+            .collect();
+        // Only amount and currency: not enough to tell categories apart
+        if !known.iter().any(|f| !f.starts_with('#')) {
+            return Vec::new();
+        }
+
+        let trained: Vec<(i64, &CategoryCounts)> = candidates
+            .iter()
+            .filter_map(|id| self.categories.get(id).map(|c| (*id, c)))
+            .collect();
+        let samples: usize = trained.iter().map(|(_, c)| c.samples).sum();
+        let vocabulary = self.vocabulary.len() as f64;
+
+        // Log-probabilities with Laplace smoothing
+        let scores: Vec<(i64, f64)> = trained
+            .iter()
+            .map(|(id, counts)| {
+                let prior = (counts.samples as f64 / samples as f64).ln();
+                let denominator = counts.total as f64 + vocabulary;
+                let likelihood: f64 = known
+                    .iter()
+                    .map(|f| {
+                        let count = counts.features.get(*f).copied().unwrap_or(0);
+                        ((count as f64 + 1.0) / denominator).ln()
+                    })
+                    .sum();
+                (*id, prior + likelihood)
+            })
+            .collect();
+
+        // Normalize into probabilities without overflowing exp()
+        let Some(best) = scores.iter().map(|(_, s)| *s).reduce(f64::max) else {
+            return Vec::new();
+        };
+        let total: f64 = scores.iter().map(|(_, s)| (s - best).exp()).sum();
+        let mut ranked: Vec<(i64, f64)> = scores
+            .into_iter()
+            .map(|(id, s)| (id, (s - best).exp() / total))
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        ranked
+    }
+}
+
+fn decrement(counts: &mut HashMap<String, usize>, feature: &str) {
+    if let Some(count) = counts.get_mut(feature) {
+        *count -= 1;
+        if *count == 0 {
+            counts.remove(feature);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+
+    fn sample(category_id: i64, description: &str, amount_cents: i64) -> Sample {
+        Sample {
+            updated_at: String::new(),
+            category_id,
+            features: features(description, amount_cents, Currency::Ars),
+        }
+    }
+
+    fn ranked_ids(classifier: &Classifier, description: &str, candidates: &[i64]) -> Vec<i64> {
+        let features = features(description, 350_000, Currency::Ars);
+        classifier
+            .rank(&features, candidates)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    #[test]
+    fn refresh_follows_edits_and_deletes() {
+        let db = Connection::open_in_memory().unwrap();
+        db::migrate(&db).unwrap();
+        let categories: Vec<i64> = db
+            .prepare("SELECT id FROM categories WHERE type = 'expense' ORDER BY id LIMIT 2")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let (rides, groceries) = (categories[0], categories[1]);
+        let insert = |description: &str, category_id: i64| {
+            db.execute(
+                "INSERT INTO transactions (description, amount, amount_in_ars, currency,
+                 category_id, date, type, created_at, updated_at)
+                 VALUES (?1, 350000, 350000, 'ARS', ?2, '2026-05-03', 'expense', 't0', 't0')",
+                rusqlite::params![description, category_id],
+            )
+            .unwrap();
+            db.last_insert_rowid()
+        };
+        let uber = insert("Uber viaje", rides);
+        insert("Coto super", groceries);
+
+        let mut classifier = Classifier::default();
+        classifier.refresh(&db).unwrap();
+        assert_eq!(
+            ranked_ids(&classifier, "uber", &categories),
+            [rides, groceries]
+        );
+
+        // Retrained on edit; the category left with no samples is dropped
+        db.execute(
+            "UPDATE transactions SET category_id = ?1, updated_at = 't1' WHERE id = ?2",
+            [groceries, uber],
+        )
+        .unwrap();
+        classifier.refresh(&db).unwrap();
+        assert_eq!(ranked_ids(&classifier, "uber", &categories), [groceries]);
+        assert_eq!(classifier.categories[&groceries].samples, 2);
+
+        db.execute("UPDATE transactions SET deleted_at = 't2'", [])
+            .unwrap();
+        classifier.refresh(&db).unwrap();
+        assert!(classifier.seen.is_empty());
+        assert!(classifier.categories.is_empty());
+        assert!(classifier.vocabulary.is_empty());
+    }
+
+    #[test]
+    fn add_and_remove_cancel_out() {
+        let mut classifier = Classifier::default();
+        classifier.add(1, sample(10, "Uber viaje", 350_000));
+        classifier.add(2, sample(10, "Uber", 9_500_000));
+        classifier.remove(1);
+        classifier.remove(1);
+        assert_eq!(classifier.categories[&10].samples, 1);
+        assert_eq!(classifier.categories[&10].total, 3);
+        assert!(!classifier.vocabulary.contains_key("viaje"));
+        classifier.remove(2);
+        assert!(classifier.categories.is_empty());
+        assert!(classifier.vocabulary.is_empty());
+    }
+
+    #[test]
+    fn ranks_only_on_known_words() {
+        let mut classifier = Classifier::default();
+        classifier.add(1, sample(10, "Uber viaje", 350_000));
+        classifier.add(2, sample(20, "Coto super", 350_000));
+
+        // Amount and currency match both, but no word is known
+        assert!(ranked_ids(&classifier, "Zapatería", &[10, 20]).is_empty());
+        assert_eq!(
+            ranked_ids(&classifier, "Uber zapatería", &[10, 20]),
+            [10, 20]
+        );
+        // Unused candidates are not ranked
+        assert_eq!(ranked_ids(&classifier, "Uber", &[30, 10]), [10]);
+    }
+
+    #[test]
+    fn ties_rank_by_category_id() {
+        let mut classifier = Classifier::default();
+        classifier.add(1, sample(20, "Kiosco", 350_000));
+        classifier.add(2, sample(10, "Kiosco", 350_000));
+
+        let ranked = classifier.rank(&features("Kiosco", 350_000, Currency::Ars), &[20, 10]);
+        assert_eq!(ranked, [(10, 0.5), (20, 0.5)]);
+    }
+}
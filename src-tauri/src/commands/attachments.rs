@@ -0,0 +1,186 @@
+use crate::attachments::{self, MAX_ATTACHMENT_SIZE};
+use crate::models::{timestamp_now, Attachment};
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension, Row};
+use std::path::Path;
+use tauri::State;
+use tauri_plugin_opener::OpenerExt;
+
+/// Column list matching `row_to_attachment`.
+pub(crate) const ATTACHMENT_COLUMNS: &str =
+    "id, transaction_id, file_name, mime_type, size, hash, created_at";
+
+/// Map a row selected with `ATTACHMENT_COLUMNS` into an `Attachment`.
+pub(crate) fn row_to_attachment(row: &Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        file_name: row.get(2)?,
+        mime_type: row.get(3)?,
+        size: row.get(4)?,
+        hash: row.get(5)?,
+        created_at: row.get(6)?,
+    })
+}
+
+fn fetch_attachment(db: &Connection, id: i64) -> Result<Attachment, String> {
+    db.query_row(
+        &format!(
+            "SELECT {} FROM attachments WHERE id = ?1",
+            ATTACHMENT_COLUMNS
+        ),
+        [id],
+        row_to_attachment,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Adjunto con id {} no encontrado", id))
+}
+
+/// Store `bytes` and link them to a transaction. Shared with backup restore,
+/// which passes the original `created_at`.
+pub(crate) fn insert_attachment(
+    db: &Connection,
+    dir: &Path,
+    transaction_id: i64,
+    file_name: &str,
+    bytes: &[u8],
+    created_at: Option<&str>,
+) -> Result<Attachment, String> {
+    if bytes.len() > MAX_ATTACHMENT_SIZE {
+        return Err(format!(
+            "El archivo supera el máximo de {} MB",
+            MAX_ATTACHMENT_SIZE / (1024 * 1024)
+        ));
+    }
+
+    let hash = attachments::hash_bytes(bytes);
+    let mime_type = attachments::detect_mime(bytes, file_name);
+    attachments::store(dir, &hash, mime_type, bytes)?;
+
+    let now = timestamp_now();
+    db.execute(
+        "INSERT INTO attachments (transaction_id, file_name, mime_type, size, hash, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            transaction_id,
+            file_name,
+            mime_type,
+            bytes.len() as i64,
+            hash,
+            created_at.unwrap_or(&now)
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    fetch_attachment(db, db.last_insert_rowid())
+}
+
+/// Copy a file into the attachment store and link it to a transaction.
+#[tauri::command]
+pub fn add_attachment(
+    state: State<AppState>,
+    transaction_id: i64,
+    path: String,
+) -> Result<Attachment, String> {
+    let bytes = std::fs::read(&path).map_err(|e| format!("No se pudo leer el archivo: {}", e))?;
+    let file_name = Path::new(&path)
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "adjunto".into());
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let exists: bool = db
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM transactions WHERE id = ?1 AND deleted_at IS NULL",
+            [transaction_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!(
+            "Transacción con id {} no encontrada",
+            transaction_id
+        ));
+    }
+
+    insert_attachment(
+        &db,
+        &state.attachments_dir,
+        transaction_id,
+        &file_name,
+        &bytes,
+        None,
+    )
+}
+
+#[tauri::command]
+pub fn list_attachments(
+    state: State<AppState>,
+    transaction_id: i64,
+) -> Result<Vec<Attachment>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM attachments WHERE transaction_id = ?1 ORDER BY id",
+            ATTACHMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([transaction_id], row_to_attachment)
+        .map_err(|e| e.to_string())?;
+
+    let result: Vec<Attachment> = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result)
+}
+
+/// Open the file with the system's default application.
+#[tauri::command]
+pub fn open_attachment(
+    app: tauri::AppHandle,
+    state: State<AppState>,
+    id: i64,
+) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let attachment = fetch_attachment(&db, id)?;
+    drop(db);
+
+    let path = attachments::blob_path(
+        &state.attachments_dir,
+        &attachment.hash,
+        &attachment.mime_type,
+    );
+    if !path.exists() {
+        return Err(format!(
+            "No se encontró el archivo de '{}'",
+            attachment.file_name
+        ));
+    }
+
+    app.opener()
+        .open_path(path.to_string_lossy(), None::<&str>)
+        .map_err(|e| format!("No se pudo abrir el adjunto: {}", e))
+}
+
+#[tauri::command]
+pub fn remove_attachment(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let attachment = fetch_attachment(&db, id)?;
+    db.execute("DELETE FROM attachments WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    // The same file may still be attached to another transaction
+    attachments::release(
+        &db,
+        &state.attachments_dir,
+        &attachment.hash,
+        &attachment.mime_type,
+    )
+}
@@ -0,0 +1,238 @@
+use crate::commands::categories::fetch_category;
+use crate::commands::transactions::query_transactions;
+use crate::models::{
+    timestamp_now, upgrade_amounts, AuditAction, AuditEntry, AuditOrigin, Entity, Transaction,
+};
+use crate::AppState;
+use rusqlite::{Connection, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+use tauri::State;
+
+const AUDIT_COLUMNS: &str = "id, entity, entity_id, action, origin, before, after, created_at";
+
+/// Entries returned by `get_activity` when no limit is given, and at most.
+const DEFAULT_ACTIVITY_LIMIT: u32 = 50;
+const MAX_ACTIVITY_LIMIT: u32 = 500;
+
+/// A stored snapshot; transactions recorded before amounts were `Money`
+/// are brought to the current layout.
+fn parse_snapshot(entity: Entity, raw: Option<String>) -> Option<serde_json::Value> {
+    let mut snapshot = raw.and_then(|json| serde_json::from_str(&json).ok())?;
+    if entity == Entity::Transaction {
+        upgrade_amounts(&mut snapshot);
+    }
+    Some(snapshot)
+}
+
+fn row_to_entry(row: &Row) -> rusqlite::Result<AuditEntry> {
+    let entity: String = row.get(1)?;
+    let action: String = row.get(3)?;
+    let origin: String = row.get(4)?;
+    let entity = Entity::parse(&entity).unwrap_or(Entity::Transaction);
+    Ok(AuditEntry {
+        id: row.get(0)?,
+        entity,
+        entity_id: row.get(2)?,
+        action: AuditAction::parse(&action).unwrap_or(AuditAction::Update),
+        origin: AuditOrigin::parse(&origin).unwrap_or(AuditOrigin::Manual),
+        before: parse_snapshot(entity, row.get(5)?),
+        after: parse_snapshot(entity, row.get(6)?),
+        created_at: row.get(7)?,
+    })
+}
+
+/// Record a change to one row, given its state before and after.
+pub(crate) fn record<T: Serialize>(
+    db: &Connection,
+    entity: Entity,
+    entity_id: i64,
+    action: AuditAction,
+    origin: AuditOrigin,
+    before: Option<&T>,
+    after: Option<&T>,
+) -> Result<(), String> {
+    let to_json = |value: Option<&T>| {
+        value
+            .map(serde_json::to_string)
+            .transpose()
+            .map_err(|e| e.to_string())
+    };
+
+    db.execute(
+        "INSERT INTO audit_log (entity, entity_id, action, origin, before, after, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        rusqlite::params![
+            entity.as_str(),
+            entity_id,
+            action.as_str(),
+            origin.as_str(),
+            to_json(before)?,
+            to_json(after)?,
+            timestamp_now()
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Entries with ids from `first` to `last`, oldest first.
+pub(crate) fn entries_between(
+    db: &Connection,
+    first: i64,
+    last: i64,
+) -> Result<Vec<AuditEntry>, String> {
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM audit_log WHERE id BETWEEN ?1 AND ?2 ORDER BY id",
+            AUDIT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map([first, last], row_to_entry)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(entries)
+}
+
+/// Current state of the transactions in `ids`, trashed or not.
+fn snapshot_transactions(db: &Connection, ids: &[i64]) -> Result<Vec<Transaction>, String> {
+    if ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let placeholders = vec!["?"; ids.len()].join(", ");
+    query_transactions(
+        db,
+        &format!("WHERE id IN ({}) ORDER BY id", placeholders),
+        rusqlite::params_from_iter(ids),
+    )
+}
+
+/// Run `change` over the transactions in `ids` and record an update for
+/// each one it actually modified.
+pub(crate) fn track_transaction_updates<R>(
+    db: &Connection,
+    ids: &[i64],
+    origin: AuditOrigin,
+    change: impl FnOnce() -> Result<R, String>,
+) -> Result<R, String> {
+    let before: HashMap<i64, Transaction> = snapshot_transactions(db, ids)?
+        .into_iter()
+        .map(|t| (t.id, t))
+        .collect();
+
+    let result = change()?;
+
+    for after in snapshot_transactions(db, ids)? {
+        let Some(before) = before.get(&after.id) else {
+            continue;
+        };
+        let unchanged = serde_json::to_value(before).ok() == serde_json::to_value(&after).ok();
+        if !unchanged {
+            record(
+                db,
+                Entity::Transaction,
+                after.id,
+                AuditAction::Update,
+                origin,
+                Some(before),
+                Some(&after),
+            )?;
+        }
+    }
+
+    Ok(result)
+}
+
+/// Record `action` for the transactions in `ids`, with their current state
+/// as `before` (for deletions) or `after` (for creations and restores).
+pub(crate) fn record_transactions(
+    db: &Connection,
+    ids: &[i64],
+    action: AuditAction,
+    origin: AuditOrigin,
+) -> Result<(), String> {
+    for t in snapshot_transactions(db, ids)? {
+        let (before, after) = match action {
+            AuditAction::Delete | AuditAction::Purge => (Some(&t), None),
+            AuditAction::Create | AuditAction::Restore | AuditAction::Update => (None, Some(&t)),
+        };
+        record(db, Entity::Transaction, t.id, action, origin, before, after)?;
+    }
+    Ok(())
+}
+
+/// Record `action` for a category, with its current state as in
+/// `record_transactions`.
+pub(crate) fn record_category(
+    db: &Connection,
+    id: i64,
+    action: AuditAction,
+    origin: AuditOrigin,
+) -> Result<(), String> {
+    let category = fetch_category(db, id)?;
+    let (before, after) = match action {
+        AuditAction::Delete | AuditAction::Purge => (Some(&category), None),
+        AuditAction::Create | AuditAction::Restore | AuditAction::Update => (None, Some(&category)),
+    };
+    record(db, Entity::Category, id, action, origin, before, after)
+}
+
+/// Every recorded change to one transaction or category, oldest first.
+#[tauri::command]
+pub fn get_history(
+    state: State<AppState>,
+    entity: Entity,
+    id: i64,
+) -> Result<Vec<AuditEntry>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM audit_log WHERE entity = ?1 AND entity_id = ?2 ORDER BY id",
+            AUDIT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(rusqlite::params![entity.as_str(), id], row_to_entry)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(entries)
+}
+
+/// Recent changes across all transactions and categories, newest first.
+/// Pass the id of the last entry received as `before_id` to page further
+/// back.
+#[tauri::command]
+pub fn get_activity(
+    state: State<AppState>,
+    limit: Option<u32>,
+    before_id: Option<i64>,
+) -> Result<Vec<AuditEntry>, String> {
+    let limit = limit
+        .unwrap_or(DEFAULT_ACTIVITY_LIMIT)
+        .clamp(1, MAX_ACTIVITY_LIMIT);
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM audit_log
+             WHERE ?1 IS NULL OR id < ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+            AUDIT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let entries = stmt
+        .query_map(rusqlite::params![before_id, limit], row_to_entry)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(entries)
+}
@@ -0,0 +1,380 @@
+use crate::attachments;
+use crate::commands::attachments::insert_attachment;
+use crate::commands::audit;
+use crate::commands::categories::{row_to_category, CATEGORY_COLUMNS};
+use crate::commands::payees::{all_payees, find_payee_by_name, insert_payee};
+use crate::commands::rules::{all_rules, insert_rule};
+use crate::commands::tags::{find_tag_by_name, insert_tag, row_to_tag, write_transaction_tags};
+use crate::commands::transactions::{query_transactions, write_splits};
+use crate::dedup;
+use crate::models::{
+    timestamp_now, AuditAction, AuditOrigin, Backup, BackupImportMode, BackupImportSummary,
+    Category, PayeeInput, RuleInput, SplitInput, Transaction, BACKUP_SCHEMA_VERSION,
+};
+use crate::AppState;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+use tauri::State;
+
+/// Snapshot every table into a `Backup`, ordered by id so repeated exports of
+/// the same data produce the same file. Attachment contents are read from
+/// `attachments_dir` and embedded.
+pub(crate) fn collect_backup(db: &Connection, attachments_dir: &Path) -> Result<Backup, String> {
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM categories WHERE deleted_at IS NULL ORDER BY id",
+            CATEGORY_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let categories = stmt
+        .query_map([], row_to_category)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare("SELECT id, name FROM tags ORDER BY id")
+        .map_err(|e| e.to_string())?;
+    let tags = stmt
+        .query_map([], row_to_tag)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Trashed rows are left out, and so are links to trashed categories
+    let category_ids: HashSet<i64> = categories.iter().map(|c| c.id).collect();
+
+    let mut payees = all_payees(db)?;
+    payees.sort_by_key(|p| p.id);
+    for payee in &mut payees {
+        payee.default_category_id = payee
+            .default_category_id
+            .filter(|id| category_ids.contains(id));
+    }
+
+    let mut rules = all_rules(db)?;
+    rules.sort_by_key(|r| r.id);
+    for rule in &mut rules {
+        rule.category_id = rule.category_id.filter(|id| category_ids.contains(id));
+    }
+
+    let transactions = query_transactions(db, "WHERE deleted_at IS NULL ORDER BY id", [])?;
+
+    let mut files = BTreeMap::new();
+    for a in transactions.iter().flat_map(|t| &t.attachments) {
+        if files.contains_key(&a.hash) {
+            continue;
+        }
+        let path = attachments::blob_path(attachments_dir, &a.hash, &a.mime_type);
+        let bytes = std::fs::read(&path).map_err(|e| {
+            format!(
+                "No se pudo leer el adjunto '{}' de la transacción {}: {}",
+                a.file_name, a.transaction_id, e
+            )
+        })?;
+        files.insert(a.hash.clone(), BASE64.encode(bytes));
+    }
+
+    Ok(Backup {
+        schema_version: BACKUP_SCHEMA_VERSION,
+        app: "TuBalance".into(),
+        exported_at: timestamp_now(),
+        categories,
+        tags,
+        payees,
+        rules,
+        transactions,
+        files,
+    })
+}
+
+fn restore_category(db: &Connection, category: &Category) -> Result<i64, String> {
+    db.execute(
+        "INSERT INTO categories (name, type, icon, color, is_default) VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            category.name,
+            category.r#type,
+            category.icon,
+            category.color,
+            category.is_default as i32
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let id = db.last_insert_rowid();
+    audit::record_category(db, id, AuditAction::Create, AuditOrigin::Backup)?;
+
+    Ok(id)
+}
+
+/// Backup ids mapped to the ids the same rows got in this database.
+#[derive(Default)]
+struct RestoredIds {
+    categories: HashMap<i64, i64>,
+    tags: HashMap<i64, i64>,
+    payees: HashMap<i64, i64>,
+}
+
+/// Insert a transaction from a backup, keeping its original timestamps.
+/// Import batches are not part of backups, so batch and duplicate links are
+/// dropped. Returns how many attachments were restored.
+fn restore_transaction(
+    db: &Connection,
+    t: &Transaction,
+    ids: &RestoredIds,
+    backup: &Backup,
+    attachments_dir: &Path,
+) -> Result<usize, String> {
+    let amount_cents = t.amount.cents;
+    let amount_in_ars_cents = t.amount_in_ars.cents;
+    let fingerprint = dedup::fingerprint(t.date, amount_cents, t.currency(), &t.description);
+
+    db.execute(
+        "INSERT INTO transactions (description, amount, amount_in_ars, currency, exchange_rate,
+         category_id, date, type, account, external_id, fingerprint, payee_id, invoice_number,
+         issuer_cuit, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        rusqlite::params![
+            t.description,
+            amount_cents,
+            amount_in_ars_cents,
+            t.currency(),
+            t.exchange_rate,
+            ids.categories[&t.category_id],
+            t.date,
+            t.r#type,
+            t.account,
+            t.external_id,
+            fingerprint,
+            t.payee_id.map(|id| ids.payees[&id]),
+            t.invoice.as_ref().map(|i| &i.number),
+            t.invoice.as_ref().map(|i| &i.issuer_cuit),
+            t.created_at,
+            t.updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let splits: Vec<SplitInput> = t
+        .splits
+        .iter()
+        .map(|s| SplitInput {
+            category_id: ids.categories[&s.category_id],
+            amount: s.amount.clone(),
+            note: s.note.clone(),
+        })
+        .collect();
+    let id = db.last_insert_rowid();
+    write_splits(db, id, amount_cents, amount_in_ars_cents, &splits)?;
+
+    let tag_ids: Vec<i64> = t.tags.iter().map(|tag| ids.tags[&tag.id]).collect();
+    write_transaction_tags(db, id, &tag_ids)?;
+
+    for a in &t.attachments {
+        // validate() guarantees the content is in the backup
+        let bytes = BASE64
+            .decode(&backup.files[&a.hash])
+            .map_err(|e| format!("Adjunto '{}' inválido: {}", a.file_name, e))?;
+        if attachments::hash_bytes(&bytes) != a.hash {
+            return Err(format!(
+                "Adjunto '{}' dañado: el contenido no coincide con su hash",
+                a.file_name
+            ));
+        }
+        insert_attachment(
+            db,
+            attachments_dir,
+            id,
+            &a.file_name,
+            &bytes,
+            Some(&a.created_at),
+        )?;
+    }
+
+    audit::record_transactions(db, &[id], AuditAction::Create, AuditOrigin::Backup)?;
+
+    Ok(t.attachments.len())
+}
+
+/// Ids of every row in `table`, trashed or not.
+fn all_ids(db: &Connection, table: &str) -> Result<Vec<i64>, String> {
+    let mut stmt = db
+        .prepare(&format!("SELECT id FROM {} ORDER BY id", table))
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Whether an equivalent transaction is already stored (used when merging).
+fn transaction_exists(db: &Connection, t: &Transaction) -> Result<bool, String> {
+    db.query_row(
+        "SELECT COUNT(*) > 0 FROM transactions
+         WHERE date = ?1 AND description = ?2 AND amount = ?3 AND currency = ?4 AND type = ?5
+           AND deleted_at IS NULL",
+        rusqlite::params![
+            t.date,
+            t.description,
+            t.amount.cents,
+            t.currency(),
+            t.r#type
+        ],
+        |row| row.get(0),
+    )
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_backup(state: State<AppState>, path: String) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let backup = collect_backup(&db, &state.attachments_dir)?;
+    drop(db);
+
+    let json = serde_json::to_string_pretty(&backup).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("No se pudo escribir el respaldo: {}", e))
+}
+
+#[tauri::command]
+pub fn import_backup(
+    state: State<AppState>,
+    path: String,
+    mode: BackupImportMode,
+) -> Result<BackupImportSummary, String> {
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("No se pudo leer el respaldo: {}", e))?;
+    let backup = Backup::from_json(&content)?;
+    backup.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mut summary = BackupImportSummary::default();
+
+    if let BackupImportMode::Replace = mode {
+        let transaction_ids = all_ids(&tx, "transactions")?;
+        audit::record_transactions(
+            &tx,
+            &transaction_ids,
+            AuditAction::Purge,
+            AuditOrigin::Backup,
+        )?;
+        for id in all_ids(&tx, "categories")? {
+            audit::record_category(&tx, id, AuditAction::Purge, AuditOrigin::Backup)?;
+        }
+        // Undo steps refer to the rows being replaced, so they go too
+        tx.execute_batch(
+            "DELETE FROM transactions; DELETE FROM import_batches; DELETE FROM rules;
+             DELETE FROM payees; DELETE FROM categories; DELETE FROM tags;
+             DELETE FROM undo_stack;",
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    let mut ids = RestoredIds::default();
+    for category in &backup.categories {
+        let existing = match mode {
+            BackupImportMode::Replace => None,
+            BackupImportMode::Merge => tx
+                .query_row(
+                    "SELECT id FROM categories
+                     WHERE LOWER(name) = LOWER(?1) AND type = ?2 AND deleted_at IS NULL",
+                    rusqlite::params![category.name.trim(), category.r#type],
+                    |row| row.get(0),
+                )
+                .optional()
+                .map_err(|e| e.to_string())?,
+        };
+
+        let id = match existing {
+            Some(id) => {
+                summary.categories_matched += 1;
+                id
+            }
+            None => {
+                summary.categories_created += 1;
+                restore_category(&tx, category)?
+            }
+        };
+        ids.categories.insert(category.id, id);
+    }
+
+    // Tag names are unique, so an existing tag is always reused
+    for tag in &backup.tags {
+        let id = match find_tag_by_name(&tx, &tag.name)? {
+            Some(id) => id,
+            None => {
+                summary.tags_created += 1;
+                insert_tag(&tx, &tag.name)?
+            }
+        };
+        ids.tags.insert(tag.id, id);
+    }
+
+    // Payees are matched by name too; aliases already taken by another
+    // payee are dropped
+    for payee in &backup.payees {
+        let id = match find_payee_by_name(&tx, &payee.name)? {
+            Some(id) => id,
+            None => {
+                summary.payees_created += 1;
+                let input = PayeeInput {
+                    name: payee.name.clone(),
+                    aliases: payee.aliases.clone(),
+                    default_category_id: payee.default_category_id.map(|id| ids.categories[&id]),
+                    notes: payee.notes.clone(),
+                };
+                insert_payee(&tx, &input, Some((&payee.created_at, &payee.updated_at)))?
+            }
+        };
+        ids.payees.insert(payee.id, id);
+    }
+
+    // When merging, a rule with the same name is assumed to be the same rule
+    for rule in &backup.rules {
+        let exists: bool = tx
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM rules WHERE LOWER(name) = LOWER(?1)",
+                [rule.name.trim()],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if exists {
+            continue;
+        }
+        let mut input = RuleInput::from(rule);
+        input.category_id = rule.category_id.map(|id| ids.categories[&id]);
+        input.payee_id = rule.payee_id.map(|id| ids.payees[&id]);
+        input.tag_ids = rule.tag_ids.iter().map(|id| ids.tags[id]).collect();
+        insert_rule(&tx, &input, Some((&rule.created_at, &rule.updated_at)))?;
+        summary.rules_created += 1;
+    }
+
+    for t in &backup.transactions {
+        if let BackupImportMode::Merge = mode {
+            if transaction_exists(&tx, t)? {
+                summary.transactions_skipped += 1;
+                continue;
+            }
+        }
+        // validate() guarantees every referenced category, tag and payee is
+        // in the backup
+        summary.attachments_restored +=
+            restore_transaction(&tx, t, &ids, &backup, &state.attachments_dir)?;
+        summary.transactions_imported += 1;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    // Replacing drops the previous transactions' attachments; failing to
+    // clean their files up only leaves unused blobs behind
+    if let BackupImportMode::Replace = mode {
+        let _ = attachments::remove_orphans(&db, &state.attachments_dir);
+    }
+
+    Ok(summary)
+}
@@ -0,0 +1,158 @@
+use crate::commands::audit;
+use crate::commands::tags::ensure_tags_exist;
+use crate::commands::transactions::{
+    ensure_category_exists, filter_transactions, query_transactions, trash_transaction,
+    write_transaction,
+};
+use crate::commands::undo;
+use crate::models::{
+    AuditAction, AuditOrigin, BulkItemResult, BulkOperation, BulkSelection, Transaction,
+    TransactionInput,
+};
+use crate::money::Currency;
+use crate::AppState;
+use std::collections::{HashMap, HashSet};
+use tauri::State;
+
+/// Name of the undo step a bulk command leaves.
+fn label(operation: &BulkOperation) -> &'static str {
+    match operation {
+        BulkOperation::Delete => "Eliminar transacciones",
+        BulkOperation::SetCategory { .. } => "Cambiar categoría",
+        BulkOperation::SetDate { .. } => "Cambiar fecha",
+        BulkOperation::AddTags { .. } => "Asignar etiquetas",
+        BulkOperation::RemoveTags { .. } => "Quitar etiquetas",
+        BulkOperation::SetExchangeRate { .. } => "Cambiar cotización",
+    }
+}
+
+/// What `operation` turns `t` into, or why it does not apply to it.
+fn edited(t: &Transaction, operation: &BulkOperation) -> Result<TransactionInput, String> {
+    let mut input = TransactionInput::from(t);
+    match operation {
+        BulkOperation::Delete => {}
+        BulkOperation::SetCategory { category_id } => {
+            if !t.splits.is_empty() {
+                return Err("Está dividida en varias categorías; cambiá cada parte".into());
+            }
+            input.category_id = *category_id;
+        }
+        BulkOperation::SetDate { date } => input.date = *date,
+        BulkOperation::AddTags { tag_ids } => {
+            let current = input.tag_ids.get_or_insert_with(Vec::new);
+            for id in tag_ids {
+                if !current.contains(id) {
+                    current.push(*id);
+                }
+            }
+        }
+        BulkOperation::RemoveTags { tag_ids } => {
+            if let Some(current) = &mut input.tag_ids {
+                current.retain(|id| !tag_ids.contains(id));
+            }
+        }
+        BulkOperation::SetExchangeRate { exchange_rate } => {
+            if t.currency() == Currency::Ars {
+                return Err("Está en pesos, no lleva cotización".into());
+            }
+            input.exchange_rate = Some(*exchange_rate);
+        }
+    }
+    input.validate()?;
+    Ok(input)
+}
+
+/// Apply one change to many transactions at once, picked by id or by
+/// filter, in a single SQL transaction and a single undo step. A
+/// transaction the change does not fit is left alone and its result says
+/// why; the rest are still changed.
+#[tauri::command]
+pub fn bulk_edit(
+    state: State<AppState>,
+    selection: BulkSelection,
+    operation: BulkOperation,
+) -> Result<Vec<BulkItemResult>, String> {
+    selection.validate()?;
+    operation.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    match &operation {
+        BulkOperation::SetCategory { category_id } => ensure_category_exists(&db, *category_id)?,
+        BulkOperation::AddTags { tag_ids } | BulkOperation::RemoveTags { tag_ids } => {
+            ensure_tags_exist(&db, tag_ids)?
+        }
+        _ => {}
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+
+    let (ids, selected) = match selection {
+        BulkSelection::Ids(mut ids) => {
+            let mut seen = HashSet::new();
+            ids.retain(|id| seen.insert(*id));
+            let placeholders = vec!["?"; ids.len()].join(", ");
+            let selected = query_transactions(
+                &tx,
+                &format!("WHERE id IN ({}) AND deleted_at IS NULL", placeholders),
+                rusqlite::params_from_iter(&ids),
+            )?;
+            (ids, selected)
+        }
+        BulkSelection::Filter(filter) => {
+            let selected = filter_transactions(&tx, &filter)?;
+            (selected.iter().map(|t| t.id).collect(), selected)
+        }
+    };
+    let mut selected: HashMap<i64, Transaction> = selected.into_iter().map(|t| (t.id, t)).collect();
+
+    // Trashing audits the rows it unflags itself (see `trash_transaction`),
+    // and deletions are recorded apart below
+    let tracked: &[i64] = match operation {
+        BulkOperation::Delete => &[],
+        _ => &ids,
+    };
+    let results = audit::track_transaction_updates(&tx, tracked, AuditOrigin::Manual, || {
+        let mut results = Vec::with_capacity(ids.len());
+        for &id in &ids {
+            let Some(transaction) = selected.remove(&id) else {
+                results.push(BulkItemResult {
+                    id,
+                    error: Some(format!("Transacción con id {} no encontrada", id)),
+                });
+                continue;
+            };
+
+            let error = match &operation {
+                BulkOperation::Delete => {
+                    trash_transaction(&tx, id, AuditOrigin::Manual)?;
+                    None
+                }
+                _ => match edited(&transaction, &operation) {
+                    Ok(input) => {
+                        write_transaction(&tx, id, &input)?;
+                        None
+                    }
+                    Err(e) => Some(e),
+                },
+            };
+            results.push(BulkItemResult { id, error });
+        }
+        Ok(results)
+    })?;
+
+    if let BulkOperation::Delete = operation {
+        let deleted: Vec<i64> = results
+            .iter()
+            .filter(|r| r.error.is_none())
+            .map(|r| r.id)
+            .collect();
+        audit::record_transactions(&tx, &deleted, AuditAction::Delete, AuditOrigin::Manual)?;
+    }
+
+    undo::push(&tx, mark, label(&operation))?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(results)
+}
@@ -1,27 +1,96 @@
-use crate::models::{Category, CategoryInput, CategoryUpdate};
+use crate::classifier::{self, Classifier};
+use crate::commands::audit;
+use crate::commands::transactions::ensure_category_exists;
+use crate::commands::undo;
+use crate::models::{
+    timestamp_now, AuditAction, AuditOrigin, Category, CategoryInput, CategoryPatch,
+    CategorySuggestion, CategoryUpdate, Entity, TransactionType, WriteError,
+};
+use crate::money::{Currency, Money};
 use crate::AppState;
+use rusqlite::{Connection, OptionalExtension, Row};
 use tauri::State;
 
+/// Column list matching `row_to_category`.
+pub(crate) const CATEGORY_COLUMNS: &str = "id, name, type, icon, color, is_default, version";
+
+/// Map a row selected with `CATEGORY_COLUMNS` into a `Category`.
+pub(crate) fn row_to_category(row: &Row) -> rusqlite::Result<Category> {
+    let is_default_int: i32 = row.get(5)?;
+    Ok(Category {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        r#type: row.get(2)?,
+        icon: row.get(3)?,
+        color: row.get(4)?,
+        is_default: is_default_int != 0,
+        version: row.get(6)?,
+    })
+}
+
+/// Load a category by id, even if it is in the trash.
+pub(crate) fn fetch_category(db: &Connection, id: i64) -> Result<Category, String> {
+    db.query_row(
+        &format!("SELECT {} FROM categories WHERE id = ?1", CATEGORY_COLUMNS),
+        [id],
+        row_to_category,
+    )
+    .optional()
+    .map_err(|e| e.to_string())?
+    .ok_or_else(|| format!("Categoría con id {} no encontrada", id))
+}
+
+/// The live category, as long as it is still at the `version` the client
+/// read.
+fn check_version(db: &Connection, id: i64, version: i64) -> Result<Category, WriteError<Category>> {
+    ensure_category_exists(db, id)?;
+    let current = fetch_category(db, id)?;
+    if current.version != version {
+        return Err(WriteError::Conflict {
+            message: "La categoría cambió mientras la editabas".into(),
+            current: Box::new(current),
+        });
+    }
+    Ok(current)
+}
+
+/// Fail if a live transaction uses the category, directly or through split
+/// lines.
+pub(crate) fn ensure_category_unused(db: &Connection, id: i64) -> Result<(), String> {
+    let tx_count: i64 = db
+        .query_row(
+            "SELECT COUNT(*) FROM transactions
+             WHERE deleted_at IS NULL
+               AND (category_id = ?1
+                    OR id IN (SELECT transaction_id FROM transaction_splits WHERE category_id = ?1))",
+            [id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if tx_count > 0 {
+        return Err(format!(
+            "No se puede eliminar: la categoría tiene {} transacciones asociadas",
+            tx_count
+        ));
+    }
+
+    Ok(())
+}
+
 #[tauri::command]
 pub fn get_categories(state: State<AppState>) -> Result<Vec<Category>, String> {
     let db = state.db.lock().map_err(|e| e.to_string())?;
 
     let mut stmt = db
-        .prepare("SELECT id, name, type, icon, color, is_default FROM categories ORDER BY id")
+        .prepare(&format!(
+            "SELECT {} FROM categories WHERE deleted_at IS NULL ORDER BY id",
+            CATEGORY_COLUMNS
+        ))
         .map_err(|e| e.to_string())?;
 
     let rows = stmt
-        .query_map([], |row| {
-            let is_default_int: i32 = row.get(5)?;
-            Ok(Category {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                r#type: row.get(2)?,
-                icon: row.get(3)?,
-                color: row.get(4)?,
-                is_default: is_default_int != 0,
-            })
-        })
+        .query_map([], row_to_category)
         .map_err(|e| e.to_string())?;
 
     let result: Vec<Category> = rows
@@ -35,15 +104,20 @@ pub fn get_categories(state: State<AppState>) -> Result<Vec<Category>, String> {
 pub fn add_category(state: State<AppState>, input: CategoryInput) -> Result<Category, String> {
     input.validate()?;
 
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
 
-    db.execute(
+    tx.execute(
         "INSERT INTO categories (name, type, icon, color, is_default) VALUES (?1, ?2, ?3, ?4, 0)",
         rusqlite::params![input.name, input.r#type, input.icon, input.color],
     )
     .map_err(|e| e.to_string())?;
 
-    let id = db.last_insert_rowid();
+    let id = tx.last_insert_rowid();
+    audit::record_category(&tx, id, AuditAction::Create, AuditOrigin::Manual)?;
+    undo::push(&tx, mark, "Agregar categoría")?;
+    tx.commit().map_err(|e| e.to_string())?;
 
     Ok(Category {
         id,
@@ -52,6 +126,7 @@ pub fn add_category(state: State<AppState>, input: CategoryInput) -> Result<Cate
         icon: input.icon,
         color: input.color,
         is_default: false,
+        version: 1,
     })
 }
 
@@ -60,14 +135,20 @@ pub fn update_category(
     state: State<AppState>,
     id: i64,
     updates: CategoryUpdate,
-) -> Result<Category, String> {
+    version: i64,
+) -> Result<Category, WriteError<Category>> {
     updates.validate()?;
 
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
 
-    let affected = db
+    let before = check_version(&tx, id, version)?;
+    let mark = undo::mark(&tx)?;
+    let affected = tx
         .execute(
-            "UPDATE categories SET name = ?1, type = ?2, icon = ?3, color = ?4 WHERE id = ?5",
+            "UPDATE categories SET name = ?1, type = ?2, icon = ?3, color = ?4,
+             version = version + 1
+             WHERE id = ?5 AND deleted_at IS NULL",
             rusqlite::params![
                 updates.name,
                 updates.r#type,
@@ -79,58 +160,186 @@ pub fn update_category(
         .map_err(|e| e.to_string())?;
 
     if affected == 0 {
-        return Err(format!("Categoría con id {} no encontrada", id));
+        return Err(format!("Categoría con id {} no encontrada", id).into());
     }
 
     // Re-fetch to get the is_default flag
-    let cat = db
-        .query_row(
-            "SELECT id, name, type, icon, color, is_default FROM categories WHERE id = ?1",
-            [id],
-            |row| {
-                let is_default_int: i32 = row.get(5)?;
-                Ok(Category {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    r#type: row.get(2)?,
-                    icon: row.get(3)?,
-                    color: row.get(4)?,
-                    is_default: is_default_int != 0,
-                })
-            },
-        )
-        .map_err(|e| e.to_string())?;
+    let cat = fetch_category(&tx, id)?;
+    audit::record(
+        &tx,
+        Entity::Category,
+        id,
+        AuditAction::Update,
+        AuditOrigin::Manual,
+        Some(&before),
+        Some(&cat),
+    )?;
+    undo::push(&tx, mark, "Editar categoría")?;
+    tx.commit().map_err(|e| e.to_string())?;
 
     Ok(cat)
 }
 
+/// Change only the given fields of a category, writing just what changed.
+/// A stale `version` fails with a conflict, as in `update_category`.
 #[tauri::command]
-pub fn delete_category(state: State<AppState>, id: i64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+pub fn patch_category(
+    state: State<AppState>,
+    id: i64,
+    patch: CategoryPatch,
+    version: i64,
+) -> Result<Category, WriteError<Category>> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
 
-    // Check if category has transactions
-    let tx_count: i64 = db
-        .query_row(
-            "SELECT COUNT(*) FROM transactions WHERE category_id = ?1",
-            [id],
-            |row| row.get(0),
+    let before = check_version(&tx, id, version)?;
+    let merged = patch.merge(&before)?;
+
+    let columns: Vec<(&str, &dyn rusqlite::ToSql)> = [
+        (
+            "name",
+            before.name != merged.name,
+            &merged.name as &dyn rusqlite::ToSql,
+        ),
+        ("type", before.r#type != merged.r#type, &merged.r#type),
+        ("icon", before.icon != merged.icon, &merged.icon),
+        ("color", before.color != merged.color, &merged.color),
+    ]
+    .into_iter()
+    .filter(|&(_, changed, _)| changed)
+    .map(|(name, _, new)| (name, new))
+    .collect();
+    if columns.is_empty() {
+        return Ok(before);
+    }
+
+    let mark = undo::mark(&tx)?;
+    let assignments: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| format!("{} = ?{}", name, i + 1))
+        .collect();
+    let mut params: Vec<&dyn rusqlite::ToSql> = columns.iter().map(|&(_, value)| value).collect();
+    params.push(&id);
+    let affected = tx
+        .execute(
+            &format!(
+                "UPDATE categories SET {}, version = version + 1
+                 WHERE id = ?{} AND deleted_at IS NULL",
+                assignments.join(", "),
+                params.len()
+            ),
+            params.as_slice(),
         )
         .map_err(|e| e.to_string())?;
 
-    if tx_count > 0 {
-        return Err(format!(
-            "No se puede eliminar: la categoría tiene {} transacciones asociadas",
-            tx_count
-        ));
+    if affected == 0 {
+        return Err(format!("Categoría con id {} no encontrada", id).into());
     }
 
-    let affected = db
-        .execute("DELETE FROM categories WHERE id = ?1", [id])
+    let cat = fetch_category(&tx, id)?;
+    audit::record(
+        &tx,
+        Entity::Category,
+        id,
+        AuditAction::Update,
+        AuditOrigin::Manual,
+        Some(&before),
+        Some(&cat),
+    )?;
+    undo::push(&tx, mark, "Editar categoría")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(cat)
+}
+
+#[tauri::command]
+pub fn delete_category(
+    state: State<AppState>,
+    id: i64,
+    version: i64,
+) -> Result<(), WriteError<Category>> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    check_version(&tx, id, version)?;
+    ensure_category_unused(&tx, id)?;
+    let mark = undo::mark(&tx)?;
+
+    // Payees and rules keep pointing at it until the trash is purged, but
+    // ignore it meanwhile
+    let affected = tx
+        .execute(
+            "UPDATE categories SET deleted_at = ?1, version = version + 1
+             WHERE id = ?2 AND deleted_at IS NULL",
+            rusqlite::params![timestamp_now(), id],
+        )
         .map_err(|e| e.to_string())?;
 
     if affected == 0 {
-        return Err(format!("Categoría con id {} no encontrada", id));
+        return Err(format!("Categoría con id {} no encontrada", id).into());
     }
 
-    Ok(())
+    audit::record_category(&tx, id, AuditAction::Delete, AuditOrigin::Manual)?;
+    undo::push(&tx, mark, "Eliminar categoría")?;
+
+    Ok(tx.commit().map_err(|e| e.to_string())?)
+}
+
+/// How many suggestions `suggest_category` returns at most.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Most likely categories of `type` for a movement, according to the
+/// history. Shared by `suggest_category` and quick entry.
+pub(crate) fn suggest_categories(
+    db: &Connection,
+    classifier: &mut Classifier,
+    description: &str,
+    amount_cents: i64,
+    currency: Currency,
+    r#type: TransactionType,
+) -> Result<Vec<CategorySuggestion>, String> {
+    classifier.refresh(db)?;
+
+    let mut stmt = db
+        .prepare("SELECT id FROM categories WHERE type = ?1 AND deleted_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let candidates = stmt
+        .query_map([r#type], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<i64>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let features = classifier::features(description, amount_cents, currency);
+    Ok(classifier
+        .rank(&features, &candidates)
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(category_id, confidence)| CategorySuggestion {
+            category_id,
+            confidence,
+        })
+        .collect())
+}
+
+/// Suggest categories for a new description, best first. Empty when the
+/// history has nothing similar.
+#[tauri::command]
+pub fn suggest_category(
+    state: State<AppState>,
+    description: String,
+    amount: Money,
+    r#type: TransactionType,
+) -> Result<Vec<CategorySuggestion>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut classifier = state.classifier.lock().map_err(|e| e.to_string())?;
+
+    suggest_categories(
+        &db,
+        &mut classifier,
+        &description,
+        amount.cents.abs(),
+        amount.currency,
+        r#type,
+    )
 }
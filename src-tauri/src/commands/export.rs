@@ -0,0 +1,54 @@
+use crate::commands::categories::{row_to_category, CATEGORY_COLUMNS};
+use crate::commands::payees::all_payees;
+use crate::commands::transactions::query_transactions;
+use crate::export::journal;
+use crate::models::{Category, JournalFormat, Payee, Transaction};
+use crate::AppState;
+use tauri::State;
+
+/// Write every transaction as a ledger-cli/hledger or beancount journal.
+#[tauri::command]
+pub fn export_journal(
+    state: State<AppState>,
+    path: String,
+    format: JournalFormat,
+) -> Result<(), String> {
+    let data = load_journal_data(&state)?;
+
+    let journal = journal::render(format, &data.categories, &data.payees, &data.transactions);
+    std::fs::write(&path, journal).map_err(|e| format!("No se pudo escribir el archivo: {}", e))
+}
+
+/// Everything a journal is rendered from.
+struct JournalData {
+    categories: Vec<Category>,
+    payees: Vec<Payee>,
+    transactions: Vec<Transaction>,
+}
+
+/// Read the journal data, releasing the lock before rendering.
+fn load_journal_data(state: &AppState) -> Result<JournalData, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM categories WHERE deleted_at IS NULL ORDER BY id",
+            CATEGORY_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let categories = stmt
+        .query_map([], row_to_category)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let transactions = query_transactions(&db, "WHERE deleted_at IS NULL ORDER BY id", [])?;
+
+    let payees = all_payees(&db)?;
+
+    Ok(JournalData {
+        categories,
+        payees,
+        transactions,
+    })
+}
@@ -0,0 +1,686 @@
+use crate::commands::audit;
+use crate::commands::payees::PayeeMatcher;
+use crate::commands::rules::load_rule_set;
+use crate::commands::transactions::{
+    ensure_category_exists, fetch_transaction, insert_transaction, query_transactions,
+    trash_transaction,
+};
+use crate::commands::undo;
+use crate::dedup::{self, DuplicateCheck, DuplicateProbe};
+use crate::import::{self, legacy, DateOrder, ParsedRow, ParsedStatement};
+use crate::models::{
+    parse_day, timestamp_now, AuditAction, AuditOrigin, CategoryInput, Entity, FlaggedDuplicate,
+    ImportBatch, ImportSummary, LegacyImportReport, PreviewRow, StatementFormat,
+    StatementImportOptions, StatementPreview, TransactionInput, TransactionOrigin, TransactionType,
+    MAX_DESCRIPTION_LEN,
+};
+use crate::money::{Currency, Money, Rate};
+use crate::AppState;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+
+/// How parsed rows become transactions when the file itself does not say.
+pub(crate) struct RowDefaults<'a> {
+    /// Importer name recorded on the batch
+    pub source: &'a str,
+    /// File the rows came from
+    pub path: &'a str,
+    pub account: Option<String>,
+    pub currency: Currency,
+    pub exchange_rate: Option<Rate>,
+    pub income_category_id: i64,
+    pub expense_category_id: i64,
+    /// Overrides keyed by `ParsedRow::operation`
+    pub operation_categories: HashMap<String, i64>,
+}
+
+fn read_bytes(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("No se pudo leer el archivo: {}", e))
+}
+
+/// Read a file picked by the user, tolerating Latin-1 encoded downloads.
+pub(crate) fn read_import_file(path: &str) -> Result<String, String> {
+    Ok(import::decode_text(&read_bytes(path)?))
+}
+
+/// Read a CSV or spreadsheet into rows, choosing the reader by extension.
+fn read_table_file(path: &str) -> Result<Vec<Vec<String>>, String> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    match extension.as_str() {
+        "xlsx" | "xls" | "ods" => import::table::read_workbook(read_bytes(path)?),
+        _ => import::table::read_csv(&read_import_file(path)?),
+    }
+}
+
+fn parse_statement(
+    path: &str,
+    format: StatementFormat,
+    date_order: DateOrder,
+) -> Result<ParsedStatement, String> {
+    match format {
+        StatementFormat::Ofx => import::ofx::parse(&read_import_file(path)?),
+        StatementFormat::Qif => import::qif::parse(&read_import_file(path)?, date_order),
+        StatementFormat::MercadoPago => import::mercadopago::parse(read_table_file(path)?),
+    }
+}
+
+/// Register a new import run; its id is stored on every row it inserts.
+pub(crate) fn start_batch(db: &Connection, source: &str, path: &str) -> Result<i64, String> {
+    let file_name = Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(str::to_string);
+
+    db.execute(
+        "INSERT INTO import_batches (source, file_name, created_at) VALUES (?1, ?2, ?3)",
+        rusqlite::params![source, file_name, timestamp_now()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(db.last_insert_rowid())
+}
+
+/// Store the final counts of an import run.
+fn finish_batch(
+    db: &Connection,
+    batch_id: i64,
+    imported: usize,
+    skipped: usize,
+    flagged: usize,
+) -> Result<(), String> {
+    db.execute(
+        "UPDATE import_batches SET imported = ?1, skipped = ?2, flagged = ?3 WHERE id = ?4",
+        rusqlite::params![imported as i64, skipped as i64, flagged as i64, batch_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Check a validated row against stored transactions and insert it unless it
+/// is an exact duplicate. Probable duplicates are inserted flagged.
+fn insert_unless_duplicate(
+    db: &Connection,
+    input: TransactionInput,
+    mut origin: TransactionOrigin,
+) -> Result<DuplicateCheck, String> {
+    let check = dedup::check(
+        db,
+        &DuplicateProbe {
+            external_id: origin.external_id.as_deref(),
+            account: origin.account.as_deref(),
+            date: input.date,
+            amount_cents: input.amount.cents,
+            currency: input.amount.currency,
+            r#type: input.r#type,
+            description: &input.description,
+            batch_id: origin.import_batch_id,
+        },
+    )?;
+
+    match check {
+        DuplicateCheck::Exact => return Ok(check),
+        DuplicateCheck::Probable(original) => origin.duplicate_of = Some(original),
+        DuplicateCheck::New => {}
+    }
+    let transaction = insert_transaction(db, input, origin)?;
+    audit::record(
+        db,
+        Entity::Transaction,
+        transaction.id,
+        AuditAction::Create,
+        AuditOrigin::Import,
+        None,
+        Some(&transaction),
+    )?;
+
+    Ok(check)
+}
+
+/// Cut a description down to the length `TransactionInput` accepts.
+fn clamp_description(description: &str) -> String {
+    let description = description.trim();
+    let mut end = description.len().min(MAX_DESCRIPTION_LEN);
+    while !description.is_char_boundary(end) {
+        end -= 1;
+    }
+    description[..end].trim_end().to_string()
+}
+
+/// Insert parsed rows as one import batch inside a single SQL transaction.
+/// Exact duplicates are skipped and probable ones flagged for review; rows
+/// that fail validation are reported as warnings instead of aborting.
+pub(crate) fn commit_rows(
+    db: &mut Connection,
+    rows: Vec<ParsedRow>,
+    defaults: &RowDefaults<'_>,
+) -> Result<ImportSummary, String> {
+    ensure_category_exists(db, defaults.income_category_id)?;
+    ensure_category_exists(db, defaults.expense_category_id)?;
+    for &category_id in defaults.operation_categories.values() {
+        ensure_category_exists(db, category_id)?;
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let mut summary = ImportSummary {
+        batch_id: start_batch(&tx, defaults.source, defaults.path)?,
+        ..Default::default()
+    };
+    let payees = PayeeMatcher::load(&tx)?;
+    let rules = load_rule_set(&tx)?;
+
+    for row in rows {
+        let amount = Money::new(row.amount_cents, defaults.currency);
+        // An explicit operation mapping wins over the payee's usual category
+        let payee = payees.find(&row.description);
+        let category_id = match row
+            .operation
+            .as_ref()
+            .and_then(|op| defaults.operation_categories.get(op))
+            .copied()
+            .or_else(|| payee.and_then(|p| p.category_for(row.r#type)))
+        {
+            Some(id) => id,
+            None if row.r#type == TransactionType::Income => defaults.income_category_id,
+            None => defaults.expense_category_id,
+        };
+
+        let mut input = TransactionInput {
+            description: clamp_description(&row.description),
+            amount,
+            amount_in_ars: None,
+            exchange_rate: defaults
+                .exchange_rate
+                .filter(|_| defaults.currency != Currency::Ars),
+            category_id,
+            date: row.date,
+            r#type: row.r#type,
+            payee_id: payee.map(|p| p.id),
+            invoice: None,
+            splits: Vec::new(),
+            tag_ids: None,
+        };
+        // The user's rules have the last word
+        rules.apply(&mut input, defaults.account.as_deref());
+
+        if let Err(e) = input.validate() {
+            summary
+                .warnings
+                .push(format!("'{}' no se importó: {}", input.description, e));
+            continue;
+        }
+
+        let origin = TransactionOrigin {
+            account: defaults.account.clone(),
+            external_id: row.external_id,
+            import_batch_id: Some(summary.batch_id),
+            duplicate_of: None,
+        };
+        match insert_unless_duplicate(&tx, input, origin)? {
+            DuplicateCheck::Exact => summary.skipped_duplicates += 1,
+            DuplicateCheck::Probable(_) => {
+                summary.imported += 1;
+                summary.flagged_duplicates += 1;
+            }
+            DuplicateCheck::New => summary.imported += 1,
+        }
+    }
+
+    finish_batch(
+        &tx,
+        summary.batch_id,
+        summary.imported,
+        summary.skipped_duplicates,
+        summary.flagged_duplicates,
+    )?;
+    undo::push(&tx, mark, "Importar extracto")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(summary)
+}
+
+/// Parse a statement and show what would be imported, without writing anything.
+/// `currency` is the one picked for files that do not declare one, as in
+/// `StatementImportOptions`, so duplicates are looked up in it.
+#[tauri::command]
+pub fn preview_statement(
+    state: State<AppState>,
+    path: String,
+    format: StatementFormat,
+    date_order: Option<DateOrder>,
+    account: Option<String>,
+    currency: Option<Currency>,
+) -> Result<StatementPreview, String> {
+    let statement = parse_statement(&path, format, date_order.unwrap_or_default())?;
+    let account = account.or(statement.account_id.clone());
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let currency = statement_currency(statement.currency, currency);
+    let rows = statement
+        .rows
+        .iter()
+        .map(|row| {
+            let check = dedup::check(
+                &db,
+                &DuplicateProbe {
+                    external_id: row.external_id.as_deref(),
+                    account: account.as_deref(),
+                    date: row.date,
+                    amount_cents: row.amount_cents,
+                    currency,
+                    r#type: row.r#type,
+                    description: &row.description,
+                    batch_id: None,
+                },
+            )?;
+            Ok(PreviewRow {
+                row: row.clone(),
+                already_imported: check == DuplicateCheck::Exact,
+                probable_duplicate_of: match check {
+                    DuplicateCheck::Probable(id) => Some(id),
+                    _ => None,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(StatementPreview {
+        account_id: statement.account_id,
+        currency: statement.currency,
+        rows,
+        skipped_internal: statement.skipped_internal,
+        warnings: statement.warnings,
+    })
+}
+
+/// Currency of a statement: the one the file declares, else the one chosen
+/// on import, else pesos.
+fn statement_currency(declared: Option<Currency>, chosen: Option<Currency>) -> Currency {
+    declared.or(chosen).unwrap_or(Currency::Ars)
+}
+
+#[tauri::command]
+pub fn import_statement(
+    state: State<AppState>,
+    path: String,
+    options: StatementImportOptions,
+) -> Result<ImportSummary, String> {
+    options.validate()?;
+
+    let statement = parse_statement(&path, options.format, options.date_order)?;
+
+    let currency = statement_currency(statement.currency, options.currency);
+    if currency != Currency::Ars && options.exchange_rate.is_none() {
+        return Err(format!(
+            "La cotización es obligatoria para extractos en {}",
+            currency
+        ));
+    }
+
+    let rows = statement
+        .rows
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !options.skip_rows.contains(i))
+        .map(|(_, row)| row)
+        .collect();
+
+    let defaults = RowDefaults {
+        source: options.format.as_str(),
+        path: &path,
+        account: options.account.or(statement.account_id),
+        exchange_rate: if currency == Currency::Ars {
+            None
+        } else {
+            options.exchange_rate
+        },
+        currency,
+        income_category_id: options.income_category_id,
+        expense_category_id: options.expense_category_id,
+        operation_categories: options.operation_categories,
+    };
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut summary = commit_rows(&mut db, rows, &defaults)?;
+    summary.warnings.splice(0..0, statement.warnings);
+
+    Ok(summary)
+}
+
+/// Find a category of the given type whose name matches ignoring case and
+/// accents.
+fn find_category_by_name(
+    db: &Connection,
+    name: &str,
+    r#type: TransactionType,
+) -> Result<Option<i64>, String> {
+    let wanted = import::normalize_text(name);
+    let mut stmt = db
+        .prepare(
+            "SELECT id, name FROM categories WHERE type = ?1 AND deleted_at IS NULL ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let candidates = stmt
+        .query_map([r#type], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(candidates
+        .into_iter()
+        .find(|(_, candidate)| import::normalize_text(candidate) == wanted)
+        .map(|(id, _)| id))
+}
+
+/// Import the JSON dump of the old localStorage app. Transactions whose
+/// category cannot be matched by name are reported and left out, unless
+/// `create_missing_categories` is set.
+#[tauri::command]
+pub fn import_legacy_data(
+    state: State<AppState>,
+    path: String,
+    create_missing_categories: Option<bool>,
+) -> Result<LegacyImportReport, String> {
+    let dump = import::legacy::parse(&read_import_file(&path)?)?;
+    let create_missing = create_missing_categories.unwrap_or(false);
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let mut report = LegacyImportReport {
+        batch_id: start_batch(&tx, "legacy", &path)?,
+        ..Default::default()
+    };
+    let payees = PayeeMatcher::load(&tx)?;
+
+    for t in &dump.transactions {
+        let label = format!("'{}' ({})", t.description.trim(), t.date);
+
+        let Some(r#type) = legacy::normalize_type(&t.r#type) else {
+            report
+                .skipped
+                .push(format!("{}: tipo desconocido '{}'", label, t.r#type));
+            continue;
+        };
+        let Some(date) = parse_day(&t.date) else {
+            report.skipped.push(format!("{}: fecha inválida", label));
+            continue;
+        };
+        let Some(category_name) = dump.category_name(t) else {
+            report.skipped.push(format!("{}: sin categoría", label));
+            continue;
+        };
+
+        let category_id = match find_category_by_name(&tx, &category_name, r#type)? {
+            Some(id) => id,
+            None if create_missing => {
+                let legacy_category = dump.category_by_name(&category_name, r#type);
+                let mut input = CategoryInput {
+                    name: category_name.clone(),
+                    r#type,
+                    icon: legacy_category
+                        .and_then(|c| c.icon.clone())
+                        .unwrap_or_else(|| "Tag".into()),
+                    color: legacy_category
+                        .and_then(|c| c.color.clone())
+                        .unwrap_or_else(|| "#9ca3af".into()),
+                };
+                // Old icons or colors may not pass today's validation
+                if input.validate().is_err() {
+                    input.icon = "Tag".into();
+                    input.color = "#9ca3af".into();
+                }
+                input
+                    .validate()
+                    .map_err(|e| format!("Categoría '{}': {}", category_name, e))?;
+
+                tx.execute(
+                    "INSERT INTO categories (name, type, icon, color, is_default) VALUES (?1, ?2, ?3, ?4, 0)",
+                    rusqlite::params![input.name.trim(), input.r#type, input.icon, input.color],
+                )
+                .map_err(|e| e.to_string())?;
+                let id = tx.last_insert_rowid();
+                audit::record_category(&tx, id, AuditAction::Create, AuditOrigin::Import)?;
+                report.categories_created.push(category_name.clone());
+                id
+            }
+            None => {
+                if !report.unmapped_categories.contains(&category_name) {
+                    report.unmapped_categories.push(category_name.clone());
+                }
+                report.skipped.push(format!(
+                    "{}: categoría '{}' sin equivalente",
+                    label, category_name
+                ));
+                continue;
+            }
+        };
+
+        let currency = match t
+            .currency
+            .as_deref()
+            .map(|code| Currency::parse(&code.trim().to_uppercase()))
+        {
+            None => Currency::Ars,
+            Some(Ok(currency)) => currency,
+            Some(Err(e)) => {
+                report.skipped.push(format!("{}: {}", label, e));
+                continue;
+            }
+        };
+        // Pesos are derived from the rate; rows saved without one keep the
+        // rate their pesos imply
+        let exchange_rate = if currency == Currency::Ars {
+            None
+        } else {
+            t.implied_rate()
+        };
+        let Some(amount) = t
+            .amount
+            .and_then(|amount| Money::from_decimal(amount, currency))
+        else {
+            report
+                .skipped
+                .push(format!("{}: el monto no es un número válido", label));
+            continue;
+        };
+
+        let input = TransactionInput {
+            description: clamp_description(&t.description),
+            amount,
+            amount_in_ars: None,
+            exchange_rate,
+            category_id,
+            date,
+            r#type,
+            payee_id: payees.find(&t.description).map(|p| p.id),
+            invoice: None,
+            splits: Vec::new(),
+            tag_ids: None,
+        };
+        if let Err(e) = input.validate() {
+            report.skipped.push(format!("{}: {}", label, e));
+            continue;
+        }
+
+        let origin = TransactionOrigin {
+            account: None,
+            external_id: legacy::value_to_string(&t.id).map(|id| format!("legacy:{}", id)),
+            import_batch_id: Some(report.batch_id),
+            duplicate_of: None,
+        };
+        match insert_unless_duplicate(&tx, input, origin)? {
+            DuplicateCheck::Exact => report.skipped_duplicates += 1,
+            DuplicateCheck::Probable(_) => {
+                report.imported += 1;
+                report.flagged_duplicates += 1;
+            }
+            DuplicateCheck::New => report.imported += 1,
+        }
+    }
+
+    finish_batch(
+        &tx,
+        report.batch_id,
+        report.imported,
+        report.skipped_duplicates + report.skipped.len(),
+        report.flagged_duplicates,
+    )?;
+    undo::push(&tx, mark, "Importar datos")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn list_import_batches(state: State<AppState>) -> Result<Vec<ImportBatch>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT id, source, file_name, imported, skipped, flagged, created_at
+             FROM import_batches ORDER BY id DESC",
+        )
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt
+        .query_map([], |row| {
+            Ok(ImportBatch {
+                id: row.get(0)?,
+                source: row.get(1)?,
+                file_name: row.get(2)?,
+                imported: row.get(3)?,
+                skipped: row.get(4)?,
+                flagged: row.get(5)?,
+                created_at: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?;
+
+    let result: Vec<ImportBatch> = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result)
+}
+
+/// Move every transaction created by an import to the trash, as one undo
+/// step. The batch stays in the history; its rows come back with `undo` or
+/// from the trash until they are purged. Returns how many transactions were
+/// removed.
+#[tauri::command]
+pub fn rollback_import(state: State<AppState>, batch_id: i64) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    let exists: bool = tx
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM import_batches WHERE id = ?1",
+            [batch_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if !exists {
+        return Err(format!("Importación con id {} no encontrada", batch_id));
+    }
+
+    let mark = undo::mark(&tx)?;
+    let mut stmt = tx
+        .prepare("SELECT id FROM transactions WHERE import_batch_id = ?1 AND deleted_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([batch_id], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+
+    for &id in &ids {
+        trash_transaction(&tx, id, AuditOrigin::Import)?;
+    }
+    audit::record_transactions(&tx, &ids, AuditAction::Delete, AuditOrigin::Import)?;
+    undo::push(&tx, mark, "Revertir importación")?;
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(ids.len())
+}
+
+/// Imported transactions flagged as probable duplicates, awaiting review.
+#[tauri::command]
+pub fn list_flagged_duplicates(state: State<AppState>) -> Result<Vec<FlaggedDuplicate>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let flagged = query_transactions(
+        &db,
+        "WHERE duplicate_of IS NOT NULL AND deleted_at IS NULL ORDER BY date DESC, id DESC",
+        [],
+    )?;
+
+    flagged
+        .into_iter()
+        .filter_map(|transaction| {
+            let original_id = transaction.duplicate_of?;
+            Some(
+                fetch_transaction(&db, original_id).map(|original| FlaggedDuplicate {
+                    transaction,
+                    original,
+                }),
+            )
+        })
+        .collect()
+}
+
+/// Settle a flagged row: keep it as a genuine transaction, or move it to
+/// the trash.
+#[tauri::command]
+pub fn resolve_duplicate(state: State<AppState>, id: i64, keep: bool) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    resolve_flagged(&tx, id, keep)?;
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// The body of `resolve_duplicate`, recorded as one undo step.
+pub(crate) fn resolve_flagged(db: &Connection, id: i64, keep: bool) -> Result<(), String> {
+    let mark = undo::mark(db)?;
+
+    let deleted_at = (!keep).then(timestamp_now);
+    let resolve = || {
+        db.execute(
+            "UPDATE transactions SET duplicate_of = NULL, deleted_at = ?2, version = version + 1
+             WHERE id = ?1 AND duplicate_of IS NOT NULL AND deleted_at IS NULL",
+            rusqlite::params![id, deleted_at],
+        )
+        .map_err(|e| e.to_string())
+    };
+    let affected = if keep {
+        audit::track_transaction_updates(db, &[id], AuditOrigin::Manual, resolve)?
+    } else {
+        let affected = resolve()?;
+        if affected > 0 {
+            audit::record_transactions(db, &[id], AuditAction::Delete, AuditOrigin::Manual)?;
+        }
+        affected
+    };
+
+    if affected == 0 {
+        return Err(format!(
+            "Transacción con id {} no encontrada o no marcada como duplicada",
+            id
+        ));
+    }
+
+    undo::push(db, mark, "Resolver duplicado")
+}
@@ -1,2 +1,15 @@
+pub mod attachments;
+pub mod audit;
+pub mod backup;
+pub mod bulk;
 pub mod categories;
+pub mod export;
+pub mod import;
+pub mod payees;
+pub mod quick_entry;
+pub mod reports;
+pub mod rules;
+pub mod tags;
 pub mod transactions;
+pub mod trash;
+pub mod undo;
@@ -0,0 +1,547 @@
+use crate::commands::audit;
+use crate::commands::transactions::ensure_category_exists;
+use crate::commands::undo;
+use crate::models::{
+    timestamp_now, AuditOrigin, Payee, PayeeInput, PayeeSuggestion, TransactionType,
+};
+use crate::money::Money;
+use crate::payees::{alias_key, merchant_key, suggested_name};
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
+use tauri::State;
+
+/// A payee as seen by `PayeeMatcher`.
+pub(crate) struct MatchedPayee {
+    pub id: i64,
+    /// Default category and its type
+    default_category: Option<(i64, TransactionType)>,
+}
+
+impl MatchedPayee {
+    /// The payee's default category, if it suits a movement of `type`.
+    pub fn category_for(&self, r#type: TransactionType) -> Option<i64> {
+        self.default_category
+            .filter(|&(_, category_type)| category_type == r#type)
+            .map(|(id, _)| id)
+    }
+}
+
+/// Every payee name and alias by alias key, loaded once per operation.
+pub(crate) struct PayeeMatcher {
+    by_key: HashMap<String, MatchedPayee>,
+}
+
+impl PayeeMatcher {
+    pub fn load(db: &Connection) -> Result<Self, String> {
+        let mut stmt = db
+            .prepare(
+                "SELECT p.id, p.name, c.id, c.type FROM payees p
+                 LEFT JOIN categories c
+                   ON c.id = p.default_category_id AND c.deleted_at IS NULL",
+            )
+            .map_err(|e| e.to_string())?;
+        let payees = stmt
+            .query_map([], |row| {
+                let category_id: Option<i64> = row.get(2)?;
+                let category_type: Option<TransactionType> = row.get(3)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    category_id.zip(category_type),
+                ))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = db
+            .prepare("SELECT payee_id, key FROM payee_aliases")
+            .map_err(|e| e.to_string())?;
+        let aliases = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })
+            .map_err(|e| e.to_string())?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        let defaults: HashMap<i64, Option<(i64, TransactionType)>> = payees
+            .iter()
+            .map(|(id, _, category)| (*id, *category))
+            .collect();
+        let mut by_key = HashMap::new();
+        let keys = payees
+            .iter()
+            .map(|(id, name, _)| (*id, alias_key(name)))
+            .chain(aliases);
+        for (id, key) in keys {
+            if key.is_empty() {
+                continue;
+            }
+            by_key.entry(key).or_insert_with(|| MatchedPayee {
+                id,
+                default_category: defaults.get(&id).copied().flatten(),
+            });
+        }
+
+        Ok(PayeeMatcher { by_key })
+    }
+
+    /// The payee whose name or an alias matches `description`.
+    pub fn find(&self, description: &str) -> Option<&MatchedPayee> {
+        self.by_key.get(&alias_key(description))
+    }
+}
+
+/// Load payees with their aliases and transaction counts, by name.
+fn query_payees(db: &Connection, only: Option<i64>) -> Result<Vec<Payee>, String> {
+    let mut stmt = db
+        .prepare(
+            "SELECT p.id, p.name, p.default_category_id, p.notes, p.created_at, p.updated_at,
+                    (SELECT COUNT(*) FROM transactions t
+                     WHERE t.payee_id = p.id AND t.deleted_at IS NULL)
+             FROM payees p
+             WHERE ?1 IS NULL OR p.id = ?1
+             ORDER BY p.name COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+    let mut payees = stmt
+        .query_map([only], |row| {
+            Ok(Payee {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                aliases: Vec::new(),
+                default_category_id: row.get(2)?,
+                notes: row.get(3)?,
+                created_at: row.get(4)?,
+                updated_at: row.get(5)?,
+                transaction_count: row.get(6)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT payee_id, alias FROM payee_aliases
+             WHERE ?1 IS NULL OR payee_id = ?1
+             ORDER BY alias COLLATE NOCASE",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([only], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut by_payee: HashMap<i64, Vec<String>> = HashMap::new();
+    for row in rows {
+        let (payee_id, alias) = row.map_err(|e| e.to_string())?;
+        by_payee.entry(payee_id).or_default().push(alias);
+    }
+    for payee in &mut payees {
+        payee.aliases = by_payee.remove(&payee.id).unwrap_or_default();
+    }
+
+    Ok(payees)
+}
+
+pub(crate) fn fetch_payee(db: &Connection, id: i64) -> Result<Payee, String> {
+    query_payees(db, Some(id))?
+        .pop()
+        .ok_or_else(|| format!("Beneficiario con id {} no encontrado", id))
+}
+
+/// Every payee, for backups and exports.
+pub(crate) fn all_payees(db: &Connection) -> Result<Vec<Payee>, String> {
+    query_payees(db, None)
+}
+
+/// Id of the payee called `name`, ignoring case.
+pub(crate) fn find_payee_by_name(db: &Connection, name: &str) -> Result<Option<i64>, String> {
+    db.query_row(
+        "SELECT id FROM payees WHERE name = ?1 COLLATE NOCASE",
+        [name.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Fail unless the payee exists.
+pub(crate) fn ensure_payee_exists(db: &Connection, id: i64) -> Result<(), String> {
+    fetch_payee(db, id).map(|_| ())
+}
+
+/// Check that neither the name nor any alias of `input` already identifies
+/// a payee other than `id`.
+fn ensure_unique(db: &Connection, id: Option<i64>, input: &PayeeInput) -> Result<(), String> {
+    if let Some(existing) = find_payee_by_name(db, &input.name)? {
+        if Some(existing) != id {
+            return Err(format!(
+                "Ya existe un beneficiario llamado '{}'",
+                input.name.trim()
+            ));
+        }
+    }
+
+    let matcher = PayeeMatcher::load(db)?;
+    for text in std::iter::once(&input.name).chain(&input.aliases) {
+        if let Some(other) = matcher.find(text).filter(|m| Some(m.id) != id) {
+            let other = fetch_payee(db, other.id)?;
+            return Err(format!(
+                "'{}' ya identifica al beneficiario '{}'",
+                text.trim(),
+                other.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Insert a payee that was already validated and checked with
+/// `ensure_unique`. Shared with backup restore, which keeps timestamps.
+pub(crate) fn insert_payee(
+    db: &Connection,
+    input: &PayeeInput,
+    timestamps: Option<(&str, &str)>,
+) -> Result<i64, String> {
+    let now = timestamp_now();
+    let (created_at, updated_at) = timestamps.unwrap_or((&now, &now));
+
+    db.execute(
+        "INSERT INTO payees (name, default_category_id, notes, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        rusqlite::params![
+            input.name.trim(),
+            input.default_category_id,
+            clean_notes(&input.notes),
+            created_at,
+            updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let id = db.last_insert_rowid();
+    write_aliases(db, id, &input.aliases)?;
+
+    Ok(id)
+}
+
+fn clean_notes(notes: &Option<String>) -> Option<&str> {
+    notes.as_deref().map(str::trim).filter(|n| !n.is_empty())
+}
+
+/// Replace the aliases of a payee. Aliases equivalent to the name or to each
+/// other are stored once.
+fn write_aliases(db: &Connection, payee_id: i64, aliases: &[String]) -> Result<(), String> {
+    db.execute("DELETE FROM payee_aliases WHERE payee_id = ?1", [payee_id])
+        .map_err(|e| e.to_string())?;
+
+    for alias in aliases {
+        let key = alias_key(alias);
+        if key.is_empty() {
+            continue;
+        }
+        db.execute(
+            "INSERT OR IGNORE INTO payee_aliases (payee_id, alias, key) VALUES (?1, ?2, ?3)",
+            rusqlite::params![payee_id, alias.trim(), key],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Link transactions without a payee whose description matches `payee_id`.
+/// Returns how many were linked.
+fn link_matching_transactions(db: &Connection, payee_id: i64) -> Result<usize, String> {
+    let matcher = PayeeMatcher::load(db)?;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT id, description FROM transactions
+             WHERE payee_id IS NULL AND deleted_at IS NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let candidates = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut linked = 0;
+    for (id, description) in candidates {
+        if matcher.find(&description).is_some_and(|m| m.id == payee_id) {
+            linked += db
+                .execute(
+                    "UPDATE transactions SET payee_id = ?1, version = version + 1 WHERE id = ?2",
+                    [payee_id, id],
+                )
+                .map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(linked)
+}
+
+#[tauri::command]
+pub fn get_payees(state: State<AppState>) -> Result<Vec<Payee>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    all_payees(&db)
+}
+
+/// Create a payee and link the existing transactions whose description
+/// matches its name or aliases.
+#[tauri::command]
+pub fn add_payee(state: State<AppState>, input: PayeeInput) -> Result<Payee, String> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    if let Some(category_id) = input.default_category_id {
+        ensure_category_exists(&db, category_id)?;
+    }
+    ensure_unique(&db, None, &input)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let id = insert_payee(&tx, &input, None)?;
+    link_matching_transactions(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fetch_payee(&db, id)
+}
+
+/// Update a payee and link transactions matching its new name or aliases.
+/// Transactions already linked stay linked.
+#[tauri::command]
+pub fn update_payee(state: State<AppState>, id: i64, input: PayeeInput) -> Result<Payee, String> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_payee_exists(&db, id)?;
+    if let Some(category_id) = input.default_category_id {
+        ensure_category_exists(&db, category_id)?;
+    }
+    ensure_unique(&db, Some(id), &input)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE payees SET name = ?1, default_category_id = ?2, notes = ?3, updated_at = ?4
+         WHERE id = ?5",
+        rusqlite::params![
+            input.name.trim(),
+            input.default_category_id,
+            clean_notes(&input.notes),
+            timestamp_now(),
+            id
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+    write_aliases(&tx, id, &input.aliases)?;
+    link_matching_transactions(&tx, id)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fetch_payee(&db, id)
+}
+
+/// Delete a payee. Its transactions are kept, without a payee.
+#[tauri::command]
+pub fn delete_payee(state: State<AppState>, id: i64) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    tx.execute(
+        "UPDATE transactions SET payee_id = NULL, version = version + 1 WHERE payee_id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    let affected = tx
+        .execute("DELETE FROM payees WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    if affected == 0 {
+        return Err(format!("Beneficiario con id {} no encontrado", id));
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Fold `source_ids` into `target_id`: their transactions move over and
+/// their names and aliases become aliases of the target.
+#[tauri::command]
+pub fn merge_payees(
+    state: State<AppState>,
+    source_ids: Vec<i64>,
+    target_id: i64,
+) -> Result<Payee, String> {
+    if source_ids.contains(&target_id) {
+        return Err("No se puede fusionar un beneficiario consigo mismo".into());
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let target = fetch_payee(&db, target_id)?;
+    let mut aliases = target.aliases;
+    for &source_id in &source_ids {
+        let source = fetch_payee(&db, source_id)?;
+        aliases.push(source.name);
+        aliases.extend(source.aliases);
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    for source_id in &source_ids {
+        tx.execute(
+            "UPDATE transactions SET payee_id = ?1, version = version + 1 WHERE payee_id = ?2",
+            [target_id, *source_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "UPDATE rules SET payee_id = ?1 WHERE payee_id = ?2",
+            [target_id, *source_id],
+        )
+        .map_err(|e| e.to_string())?;
+        // Aliases go with the payee (ON DELETE CASCADE)
+        tx.execute("DELETE FROM payees WHERE id = ?1", [source_id])
+            .map_err(|e| e.to_string())?;
+    }
+    write_aliases(&tx, target_id, &aliases)?;
+    tx.execute(
+        "UPDATE payees SET updated_at = ?1 WHERE id = ?2",
+        rusqlite::params![timestamp_now(), target_id],
+    )
+    .map_err(|e| e.to_string())?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fetch_payee(&db, target_id)
+}
+
+/// Set (or clear, with `None`) the payee of several transactions at once.
+/// Returns how many transactions were updated.
+#[tauri::command]
+pub fn assign_payee(
+    state: State<AppState>,
+    transaction_ids: Vec<i64>,
+    payee_id: Option<i64>,
+) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    if let Some(payee_id) = payee_id {
+        ensure_payee_exists(&db, payee_id)?;
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let updated =
+        audit::track_transaction_updates(&tx, &transaction_ids, AuditOrigin::Manual, || {
+            let mut updated = 0;
+            for id in &transaction_ids {
+                updated += tx
+                    .execute(
+                        "UPDATE transactions SET payee_id = ?1, version = version + 1
+                         WHERE id = ?2 AND deleted_at IS NULL",
+                        rusqlite::params![payee_id, id],
+                    )
+                    .map_err(|e| e.to_string())?;
+            }
+            Ok(updated)
+        })?;
+    undo::push(&tx, mark, "Asignar beneficiario")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(updated)
+}
+
+/// Group descriptions of transactions without a payee that probably name
+/// the same merchant. A group is suggested when it spells the merchant in
+/// more than one way, or when it matches an existing payee; applying a
+/// suggestion is `add_payee` (or `update_payee`) with the descriptions as
+/// aliases.
+#[tauri::command]
+pub fn suggest_payees(state: State<AppState>) -> Result<Vec<PayeeSuggestion>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT id, description, amount_in_ars FROM transactions
+             WHERE payee_id IS NULL AND deleted_at IS NULL ORDER BY id",
+        )
+        .map_err(|e| e.to_string())?;
+    let unassigned = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Existing payees by the merchant key of their name and aliases
+    let mut existing: HashMap<String, i64> = HashMap::new();
+    for payee in all_payees(&db)? {
+        for text in std::iter::once(&payee.name).chain(&payee.aliases) {
+            if let Some(key) = merchant_key(text) {
+                existing.entry(key).or_insert(payee.id);
+            }
+        }
+    }
+
+    struct Group {
+        descriptions: BTreeMap<String, String>,
+        transaction_ids: Vec<i64>,
+        total_cents: i64,
+    }
+    let mut groups: BTreeMap<String, Group> = BTreeMap::new();
+    for (id, description, amount_in_ars) in unassigned {
+        let Some(key) = merchant_key(&description) else {
+            continue;
+        };
+        let group = groups.entry(key).or_insert_with(|| Group {
+            descriptions: BTreeMap::new(),
+            transaction_ids: Vec::new(),
+            total_cents: 0,
+        });
+        // One spelling per alias key
+        group
+            .descriptions
+            .entry(alias_key(&description))
+            .or_insert_with(|| description.trim().to_string());
+        group.transaction_ids.push(id);
+        group.total_cents += amount_in_ars;
+    }
+
+    let mut suggestions: Vec<PayeeSuggestion> = groups
+        .into_iter()
+        .filter_map(|(key, group)| {
+            let existing_payee_id = existing.get(&key).copied();
+            if existing_payee_id.is_none() && group.descriptions.len() < 2 {
+                return None;
+            }
+            Some(PayeeSuggestion {
+                name: suggested_name(&key),
+                existing_payee_id,
+                descriptions: group.descriptions.into_values().collect(),
+                transaction_ids: group.transaction_ids,
+                total_in_ars: Money::ars(group.total_cents),
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.transaction_ids
+            .len()
+            .cmp(&a.transaction_ids.len())
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    Ok(suggestions)
+}
@@ -0,0 +1,228 @@
+use crate::afip;
+use crate::commands::categories::suggest_categories;
+use crate::commands::payees::PayeeMatcher;
+use crate::commands::rules::load_rule_set;
+use crate::models::{DraftField, Invoice, QuickEntryDraft, TransactionInput, TransactionType};
+use crate::money::{Currency, Money};
+use crate::quick_entry;
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension};
+use tauri::State;
+
+/// The category of `type` used most often (the oldest when there is no
+/// history), for entries nothing else says anything about.
+fn fallback_category(db: &Connection, r#type: TransactionType) -> Result<Option<i64>, String> {
+    db.query_row(
+        "SELECT c.id FROM categories c
+         LEFT JOIN transactions t ON t.category_id = c.id AND t.deleted_at IS NULL
+         WHERE c.type = ?1 AND c.deleted_at IS NULL
+         GROUP BY c.id
+         ORDER BY COUNT(t.id) DESC, c.id
+         LIMIT 1",
+        [r#type],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Turn a line such as "café 1.500 ayer" or "uber 12 usd a 1180 el 3/5"
+/// into a transaction draft. Nothing is stored: the UI shows the draft,
+/// highlighting what was inferred or is missing, and submits it with
+/// `add_transaction` once confirmed.
+#[tauri::command]
+pub fn parse_quick_entry(state: State<AppState>, text: String) -> Result<QuickEntryDraft, String> {
+    if text.trim().is_empty() {
+        return Err("Escribí un movimiento, por ejemplo \"café 1.500 ayer\"".into());
+    }
+
+    let today = chrono::Local::now().date_naive();
+    let parsed = quick_entry::parse(&text, today);
+    let mut inferred = Vec::new();
+    let mut missing = Vec::new();
+
+    let r#type = parsed.r#type.unwrap_or_else(|| {
+        inferred.push(DraftField::Type);
+        TransactionType::Expense
+    });
+    // A rate only makes sense for dollars
+    let currency = parsed.currency.unwrap_or_else(|| {
+        inferred.push(DraftField::Currency);
+        if parsed.exchange_rate.is_some() {
+            Currency::Usd
+        } else {
+            Currency::Ars
+        }
+    });
+    let exchange_rate = if currency == Currency::Ars {
+        None
+    } else {
+        if parsed.exchange_rate.is_none() {
+            missing.push(DraftField::ExchangeRate);
+        }
+        parsed.exchange_rate
+    };
+
+    let amount_cents = parsed.amount_cents.unwrap_or_else(|| {
+        missing.push(DraftField::Amount);
+        0
+    });
+    let amount = Money::new(amount_cents, currency);
+
+    let date = parsed.date.unwrap_or_else(|| {
+        inferred.push(DraftField::Date);
+        today
+    });
+    if parsed.description.is_empty() {
+        missing.push(DraftField::Description);
+    }
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut classifier = state.classifier.lock().map_err(|e| e.to_string())?;
+
+    let matcher = PayeeMatcher::load(&db)?;
+    let payee = matcher.find(&parsed.description);
+    let suggestions = if parsed.description.is_empty() {
+        Vec::new()
+    } else {
+        suggest_categories(
+            &db,
+            &mut classifier,
+            &parsed.description,
+            amount_cents,
+            currency,
+            r#type,
+        )?
+    };
+
+    // The payee's usual category, then the history, then the usual bucket
+    let category_id = match payee
+        .and_then(|p| p.category_for(r#type))
+        .or(suggestions.first().map(|s| s.category_id))
+    {
+        Some(id) => Some(id),
+        None => fallback_category(&db, r#type)?,
+    };
+
+    let mut input = TransactionInput {
+        description: parsed.description,
+        amount,
+        amount_in_ars: None,
+        exchange_rate,
+        category_id: category_id.unwrap_or(0),
+        date,
+        r#type,
+        payee_id: payee.map(|p| p.id),
+        invoice: None,
+        splits: Vec::new(),
+        tag_ids: None,
+    };
+    // Same rules `add_transaction` will apply, so the draft shows the outcome
+    load_rule_set(&db)?.apply(&mut input, None);
+    // Only for display; saving derives it again
+    input.amount_in_ars = input.derived_amount_in_ars().ok();
+
+    if input.category_id == 0 {
+        missing.push(DraftField::Category);
+    } else {
+        inferred.push(DraftField::Category);
+    }
+    if input.payee_id.is_some() {
+        inferred.push(DraftField::Payee);
+    }
+
+    Ok(QuickEntryDraft {
+        input,
+        inferred,
+        missing,
+        suggestions,
+    })
+}
+
+/// Description, category and payee of the last movement of `type` billed
+/// by `issuer_cuit`.
+fn last_from_issuer(
+    db: &Connection,
+    issuer_cuit: &str,
+    r#type: TransactionType,
+) -> Result<Option<(String, i64, Option<i64>)>, String> {
+    db.query_row(
+        "SELECT description, category_id, payee_id FROM transactions
+         WHERE issuer_cuit = ?1 AND type = ?2 AND deleted_at IS NULL
+         ORDER BY date DESC, id DESC
+         LIMIT 1",
+        rusqlite::params![issuer_cuit, r#type],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Turn the text read from the QR of an electronic invoice into a
+/// transaction ready to confirm, carrying the invoice number and issuer
+/// CUIT. Description, category and payee repeat the last movement from the
+/// same issuer, when there is one. Fails when the invoice was already
+/// entered.
+#[tauri::command]
+pub fn parse_invoice_qr(state: State<AppState>, qr: String) -> Result<TransactionInput, String> {
+    let invoice = afip::parse(&qr)?;
+    let number = invoice.invoice_number();
+    let r#type = if invoice.is_credit_note() {
+        TransactionType::Income
+    } else {
+        TransactionType::Expense
+    };
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let existing: Option<i64> = db
+        .query_row(
+            "SELECT id FROM transactions
+             WHERE issuer_cuit = ?1 AND invoice_number = ?2 AND deleted_at IS NULL",
+            [&invoice.issuer_cuit, &number],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    if let Some(id) = existing {
+        return Err(format!(
+            "El comprobante {} ya está cargado (transacción {})",
+            number, id
+        ));
+    }
+
+    let (description, category_id, payee_id) =
+        match last_from_issuer(&db, &invoice.issuer_cuit, r#type)? {
+            Some((description, category_id, payee_id)) => {
+                (description, Some(category_id), payee_id)
+            }
+            None => (
+                format!("{} CUIT {}", invoice.kind_name(), invoice.issuer_cuit),
+                fallback_category(&db, r#type)?,
+                None,
+            ),
+        };
+
+    let amount = Money::new(invoice.amount_cents, invoice.currency);
+
+    let mut input = TransactionInput {
+        description,
+        amount,
+        amount_in_ars: None,
+        exchange_rate: invoice.exchange_rate,
+        category_id: category_id.unwrap_or(0),
+        date: invoice.date,
+        r#type,
+        payee_id,
+        invoice: Some(Invoice {
+            number,
+            issuer_cuit: invoice.issuer_cuit,
+        }),
+        splits: Vec::new(),
+        tag_ids: None,
+    };
+    load_rule_set(&db)?.apply(&mut input, None);
+    input.amount_in_ars = input.derived_amount_in_ars().ok();
+
+    Ok(input)
+}
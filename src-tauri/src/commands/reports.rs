@@ -0,0 +1,232 @@
+use crate::commands::transactions::filter_condition;
+use crate::models::{
+    CategoryTotal, MonthTotal, PayeeTotal, Tag, TagTotal, TransactionFilter, TransactionType,
+};
+use crate::money::Money;
+use crate::AppState;
+use chrono::NaiveDate;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet};
+use tauri::State;
+
+/// One row per category share of a transaction: every split line of a split
+/// transaction, or the transaction itself when it has no splits. Columns:
+/// transaction id, category id, amount in ARS (centavos), date, type.
+pub(crate) const CATEGORY_LINES_SQL: &str =
+    "SELECT t.id AS transaction_id, t.category_id, t.amount_in_ars, t.date, t.type
+     FROM transactions t
+     WHERE NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.transaction_id = t.id)
+     UNION ALL
+     SELECT t.id, s.category_id, s.amount_in_ars, t.date, t.type
+     FROM transaction_splits s JOIN transactions t ON t.id = s.transaction_id";
+
+/// Totals in ARS per category for the transactions matching `filter`,
+/// largest first. Split transactions count towards each of their lines'
+/// categories.
+#[tauri::command]
+pub fn get_category_totals(
+    state: State<AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<CategoryTotal>, String> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let (condition, params) = filter_condition(&filter);
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT * FROM ({}) WHERE transaction_id IN (SELECT id FROM transactions WHERE {})",
+            CATEGORY_LINES_SQL, condition
+        ))
+        .map_err(|e| e.to_string())?;
+    let lines = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, TransactionType>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // (category, type) -> (centavos, transactions)
+    let mut totals: BTreeMap<(i64, TransactionType), (i64, HashSet<i64>)> = BTreeMap::new();
+    for (transaction_id, category_id, amount_in_ars, r#type) in lines {
+        let entry = totals.entry((category_id, r#type)).or_default();
+        entry.0 += amount_in_ars;
+        entry.1.insert(transaction_id);
+    }
+
+    let mut result: Vec<CategoryTotal> = totals
+        .into_iter()
+        .map(
+            |((category_id, r#type), (cents, transactions))| CategoryTotal {
+                category_id,
+                r#type,
+                total_in_ars: Money::ars(cents),
+                transaction_count: transactions.len(),
+            },
+        )
+        .collect();
+    result.sort_by_key(|t| Reverse(t.total_in_ars.cents));
+
+    Ok(result)
+}
+
+/// Income and spending per tag for the transactions matching `filter`,
+/// largest spending first. Untagged transactions are not included.
+#[tauri::command]
+pub fn get_tag_totals(
+    state: State<AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<TagTotal>, String> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let (condition, params) = filter_condition(&filter);
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT g.id, g.name, t.type, t.amount_in_ars
+             FROM transaction_tags tt
+             JOIN tags g ON g.id = tt.tag_id
+             JOIN transactions t ON t.id = tt.transaction_id
+             WHERE t.id IN (SELECT id FROM transactions WHERE {})",
+            condition
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((
+                Tag {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                },
+                row.get::<_, TransactionType>(2)?,
+                row.get::<_, i64>(3)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // tag id -> (tag, income, expense, transactions)
+    let mut totals: BTreeMap<i64, (Tag, i64, i64, usize)> = BTreeMap::new();
+    for (tag, r#type, amount_in_ars) in rows {
+        let entry = totals.entry(tag.id).or_insert((tag, 0, 0, 0));
+        if r#type == TransactionType::Income {
+            entry.1 += amount_in_ars;
+        } else {
+            entry.2 += amount_in_ars;
+        }
+        entry.3 += 1;
+    }
+
+    let mut result: Vec<TagTotal> = totals
+        .into_values()
+        .map(|(tag, income, expense, count)| TagTotal {
+            tag,
+            income_in_ars: Money::ars(income),
+            expense_in_ars: Money::ars(expense),
+            transaction_count: count,
+        })
+        .collect();
+    result.sort_by_key(|t| Reverse(t.expense_in_ars.cents));
+
+    Ok(result)
+}
+
+/// Income and spending per payee for the transactions matching `filter`,
+/// broken down by month, largest spending first. Transactions without a
+/// payee are not included.
+#[tauri::command]
+pub fn get_payee_totals(
+    state: State<AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<PayeeTotal>, String> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+    let (condition, params) = filter_condition(&filter);
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT p.id, p.name, t.type, t.amount_in_ars, t.date
+             FROM transactions t
+             JOIN payees p ON p.id = t.payee_id
+             WHERE t.id IN (SELECT id FROM transactions WHERE {})",
+            condition
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, TransactionType>(2)?,
+                row.get::<_, i64>(3)?,
+                row.get::<_, NaiveDate>(4)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    struct Totals {
+        name: String,
+        count: usize,
+        // month -> (income, expense)
+        months: BTreeMap<String, (i64, i64)>,
+    }
+    let mut totals: BTreeMap<i64, Totals> = BTreeMap::new();
+    for (payee_id, name, r#type, amount_in_ars, day) in rows {
+        let entry = totals.entry(payee_id).or_insert_with(|| Totals {
+            name,
+            count: 0,
+            months: BTreeMap::new(),
+        });
+        entry.count += 1;
+        let month = entry
+            .months
+            .entry(day.format("%Y-%m").to_string())
+            .or_default();
+        if r#type == TransactionType::Income {
+            month.0 += amount_in_ars;
+        } else {
+            month.1 += amount_in_ars;
+        }
+    }
+
+    let mut result: Vec<PayeeTotal> = totals
+        .into_iter()
+        .map(|(payee_id, totals)| {
+            let income: i64 = totals.months.values().map(|m| m.0).sum();
+            let expense: i64 = totals.months.values().map(|m| m.1).sum();
+            PayeeTotal {
+                payee_id,
+                name: totals.name,
+                income_in_ars: Money::ars(income),
+                expense_in_ars: Money::ars(expense),
+                transaction_count: totals.count,
+                months: totals
+                    .months
+                    .into_iter()
+                    .map(|(month, (income, expense))| MonthTotal {
+                        month,
+                        income_in_ars: Money::ars(income),
+                        expense_in_ars: Money::ars(expense),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+    result.sort_by_key(|t| Reverse(t.expense_in_ars.cents));
+
+    Ok(result)
+}
@@ -0,0 +1,399 @@
+use crate::commands::audit;
+use crate::commands::payees::ensure_payee_exists;
+use crate::commands::tags::ensure_tags_exist;
+use crate::commands::transactions::{ensure_category_exists, query_transactions};
+use crate::commands::undo;
+use crate::models::{
+    timestamp_now, AuditOrigin, PatternKind, Period, Rule, RuleChange, RuleInput, TransactionType,
+};
+use crate::rules::{CompiledRule, RuleSet, Subject};
+use crate::AppState;
+use rusqlite::{Connection, Row};
+use std::collections::HashMap;
+use tauri::State;
+
+/// Column list matching `row_to_rule`.
+const RULE_COLUMNS: &str = "id, name, priority, enabled, pattern, pattern_kind, min_amount,
+     max_amount, currency, account, category_id, payee_id, created_at, updated_at";
+
+fn row_to_rule(row: &Row) -> rusqlite::Result<Rule> {
+    let pattern_kind: String = row.get(5)?;
+    Ok(Rule {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        priority: row.get(2)?,
+        enabled: row.get(3)?,
+        pattern: row.get(4)?,
+        pattern_kind: PatternKind::parse(&pattern_kind).unwrap_or_default(),
+        min_amount_cents: row.get(6)?,
+        max_amount_cents: row.get(7)?,
+        currency: row.get(8)?,
+        account: row.get(9)?,
+        category_id: row.get(10)?,
+        payee_id: row.get(11)?,
+        // Filled in by `query_rules`
+        tag_ids: Vec::new(),
+        created_at: row.get(12)?,
+        updated_at: row.get(13)?,
+    })
+}
+
+/// Load rules (or only `only`) with their tags, in evaluation order.
+fn query_rules(db: &Connection, only: Option<i64>) -> Result<Vec<Rule>, String> {
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM rules WHERE ?1 IS NULL OR id = ?1 ORDER BY priority, id",
+            RULE_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut rules = stmt
+        .query_map([only], row_to_rule)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare(
+            "SELECT rule_id, tag_id FROM rule_tags
+             WHERE ?1 IS NULL OR rule_id = ?1
+             ORDER BY rule_id, tag_id",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([only], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?))
+        })
+        .map_err(|e| e.to_string())?;
+    let mut by_rule: HashMap<i64, Vec<i64>> = HashMap::new();
+    for row in rows {
+        let (rule_id, tag_id) = row.map_err(|e| e.to_string())?;
+        by_rule.entry(rule_id).or_default().push(tag_id);
+    }
+    for rule in &mut rules {
+        rule.tag_ids = by_rule.remove(&rule.id).unwrap_or_default();
+    }
+
+    Ok(rules)
+}
+
+fn fetch_rule(db: &Connection, id: i64) -> Result<Rule, String> {
+    query_rules(db, Some(id))?
+        .pop()
+        .ok_or_else(|| format!("Regla con id {} no encontrada", id))
+}
+
+/// Every rule, for backups.
+pub(crate) fn all_rules(db: &Connection) -> Result<Vec<Rule>, String> {
+    query_rules(db, None)
+}
+
+fn category_types(db: &Connection) -> Result<HashMap<i64, TransactionType>, String> {
+    let mut stmt = db
+        .prepare("SELECT id, type FROM categories WHERE deleted_at IS NULL")
+        .map_err(|e| e.to_string())?;
+    let types = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(types)
+}
+
+/// The enabled rules, ready to apply to new transactions.
+pub(crate) fn load_rule_set(db: &Connection) -> Result<RuleSet, String> {
+    RuleSet::new(query_rules(db, None)?, &category_types(db)?)
+}
+
+/// Fail unless every category, payee and tag `input` refers to exists.
+fn ensure_rule_references_exist(db: &Connection, input: &RuleInput) -> Result<(), String> {
+    if let Some(category_id) = input.category_id {
+        ensure_category_exists(db, category_id)?;
+    }
+    if let Some(payee_id) = input.payee_id {
+        ensure_payee_exists(db, payee_id)?;
+    }
+    ensure_tags_exist(db, &input.tag_ids)
+}
+
+fn clean(text: &Option<String>) -> Option<&str> {
+    text.as_deref().map(str::trim).filter(|t| !t.is_empty())
+}
+
+/// Insert a rule that was already validated. Shared with backup restore,
+/// which keeps timestamps.
+pub(crate) fn insert_rule(
+    db: &Connection,
+    input: &RuleInput,
+    timestamps: Option<(&str, &str)>,
+) -> Result<i64, String> {
+    let now = timestamp_now();
+    let (created_at, updated_at) = timestamps.unwrap_or((&now, &now));
+
+    db.execute(
+        "INSERT INTO rules (name, priority, enabled, pattern, pattern_kind, min_amount,
+         max_amount, currency, account, category_id, payee_id, created_at, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+        rusqlite::params![
+            input.name.trim(),
+            input.priority,
+            input.enabled,
+            clean(&input.pattern),
+            input.pattern_kind.as_str(),
+            input.min_amount_cents,
+            input.max_amount_cents,
+            input.currency,
+            clean(&input.account),
+            input.category_id,
+            input.payee_id,
+            created_at,
+            updated_at
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    let id = db.last_insert_rowid();
+    write_rule_tags(db, id, &input.tag_ids)?;
+
+    Ok(id)
+}
+
+fn write_rule_tags(db: &Connection, rule_id: i64, tag_ids: &[i64]) -> Result<(), String> {
+    db.execute("DELETE FROM rule_tags WHERE rule_id = ?1", [rule_id])
+        .map_err(|e| e.to_string())?;
+    for tag_id in tag_ids {
+        db.execute(
+            "INSERT OR IGNORE INTO rule_tags (rule_id, tag_id) VALUES (?1, ?2)",
+            [rule_id, *tag_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Stored transactions in `period` that `rule` matches and would change,
+/// newest first. The rule is looked at on its own, whatever the other rules
+/// and its enabled flag say.
+fn rule_changes(
+    db: &Connection,
+    rule: &CompiledRule,
+    period: &Period,
+) -> Result<Vec<RuleChange>, String> {
+    let transactions = query_transactions(
+        db,
+        "WHERE deleted_at IS NULL ORDER BY date DESC, id DESC",
+        [],
+    )?;
+
+    let mut changes = Vec::new();
+    for t in transactions {
+        if !period.contains(t.date) || !rule.matches(&Subject::of_transaction(&t)) {
+            continue;
+        }
+        let category_id = rule
+            .category_for(t.r#type)
+            .filter(|&id| t.splits.is_empty() && id != t.category_id);
+        let payee_id = rule.rule.payee_id.filter(|&id| t.payee_id != Some(id));
+        let added_tag_ids: Vec<i64> = rule
+            .rule
+            .tag_ids
+            .iter()
+            .copied()
+            .filter(|&id| !t.tags.iter().any(|tag| tag.id == id))
+            .collect();
+
+        if category_id.is_some() || payee_id.is_some() || !added_tag_ids.is_empty() {
+            changes.push(RuleChange {
+                transaction: t,
+                category_id,
+                payee_id,
+                added_tag_ids,
+            });
+        }
+    }
+
+    Ok(changes)
+}
+
+/// Compile a rule that is not stored (yet), for dry runs.
+fn compile_input(db: &Connection, input: &RuleInput) -> Result<CompiledRule, String> {
+    let now = timestamp_now();
+    let rule = Rule {
+        id: 0,
+        name: input.name.trim().to_string(),
+        priority: input.priority,
+        enabled: input.enabled,
+        pattern: clean(&input.pattern).map(str::to_string),
+        pattern_kind: input.pattern_kind,
+        min_amount_cents: input.min_amount_cents,
+        max_amount_cents: input.max_amount_cents,
+        currency: input.currency,
+        account: clean(&input.account).map(str::to_string),
+        category_id: input.category_id,
+        payee_id: input.payee_id,
+        tag_ids: input.tag_ids.clone(),
+        created_at: now.clone(),
+        updated_at: now,
+    };
+
+    compile_rule(db, rule)
+}
+
+/// Compile a single rule, outside of any `RuleSet`.
+fn compile_rule(db: &Connection, rule: Rule) -> Result<CompiledRule, String> {
+    let category_type = match rule.category_id {
+        Some(id) => category_types(db)?.remove(&id),
+        None => None,
+    };
+
+    CompiledRule::new(rule, category_type)
+}
+
+#[tauri::command]
+pub fn get_rules(state: State<AppState>) -> Result<Vec<Rule>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    all_rules(&db)
+}
+
+#[tauri::command]
+pub fn add_rule(state: State<AppState>, input: RuleInput) -> Result<Rule, String> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_rule_references_exist(&db, &input)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let id = insert_rule(&tx, &input, None)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fetch_rule(&db, id)
+}
+
+#[tauri::command]
+pub fn update_rule(state: State<AppState>, id: i64, input: RuleInput) -> Result<Rule, String> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_rule_references_exist(&db, &input)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let affected = tx
+        .execute(
+            "UPDATE rules SET name = ?1, priority = ?2, enabled = ?3, pattern = ?4,
+             pattern_kind = ?5, min_amount = ?6, max_amount = ?7, currency = ?8, account = ?9,
+             category_id = ?10, payee_id = ?11, updated_at = ?12
+             WHERE id = ?13",
+            rusqlite::params![
+                input.name.trim(),
+                input.priority,
+                input.enabled,
+                clean(&input.pattern),
+                input.pattern_kind.as_str(),
+                input.min_amount_cents,
+                input.max_amount_cents,
+                input.currency,
+                clean(&input.account),
+                input.category_id,
+                input.payee_id,
+                timestamp_now(),
+                id
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if affected == 0 {
+        return Err(format!("Regla con id {} no encontrada", id));
+    }
+    write_rule_tags(&tx, id, &input.tag_ids)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    fetch_rule(&db, id)
+}
+
+#[tauri::command]
+pub fn delete_rule(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let affected = db
+        .execute("DELETE FROM rules WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    if affected == 0 {
+        return Err(format!("Regla con id {} no encontrada", id));
+    }
+
+    Ok(())
+}
+
+/// Show which stored transactions in `period` a rule would change, without
+/// changing anything. The rule does not need to be saved.
+#[tauri::command]
+pub fn dry_run_rule(
+    state: State<AppState>,
+    input: RuleInput,
+    period: Option<Period>,
+) -> Result<Vec<RuleChange>, String> {
+    input.validate()?;
+    let period = period.unwrap_or_default();
+    period.validate()?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_rule_references_exist(&db, &input)?;
+    let rule = compile_input(&db, &input)?;
+
+    rule_changes(&db, &rule, &period)
+}
+
+/// Apply a saved rule to the stored transactions in `period`, as shown by
+/// `dry_run_rule`. Returns how many transactions changed.
+#[tauri::command]
+pub fn apply_rule(
+    state: State<AppState>,
+    id: i64,
+    period: Option<Period>,
+) -> Result<usize, String> {
+    let period = period.unwrap_or_default();
+    period.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let rule = compile_rule(&db, fetch_rule(&db, id)?)?;
+    let changes = rule_changes(&db, &rule, &period)?;
+
+    let now = timestamp_now();
+    let ids: Vec<i64> = changes.iter().map(|c| c.transaction.id).collect();
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    audit::track_transaction_updates(&tx, &ids, AuditOrigin::Rule, || {
+        for change in &changes {
+            let t = &change.transaction;
+            tx.execute(
+                "UPDATE transactions SET category_id = ?1, payee_id = ?2, updated_at = ?3,
+                 version = version + 1
+                 WHERE id = ?4",
+                rusqlite::params![
+                    change.category_id.unwrap_or(t.category_id),
+                    change.payee_id.or(t.payee_id),
+                    &now,
+                    t.id
+                ],
+            )
+            .map_err(|e| e.to_string())?;
+            for tag_id in &change.added_tag_ids {
+                tx.execute(
+                    "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+                    [t.id, *tag_id],
+                )
+                .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    })?;
+    undo::push(&tx, mark, &format!("Aplicar la regla {}", rule.rule.name))?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(changes.len())
+}
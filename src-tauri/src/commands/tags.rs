@@ -0,0 +1,290 @@
+use crate::commands::audit;
+use crate::commands::transactions::bump_version;
+use crate::commands::undo;
+use crate::models::{timestamp_now, validate_tag_name, AuditOrigin, Tag};
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension, Row};
+use tauri::State;
+
+/// Map a row selected as `id, name` into a `Tag`.
+pub(crate) fn row_to_tag(row: &Row) -> rusqlite::Result<Tag> {
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+    })
+}
+
+fn fetch_tag(db: &Connection, id: i64) -> Result<Tag, String> {
+    db.query_row("SELECT id, name FROM tags WHERE id = ?1", [id], row_to_tag)
+        .optional()
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("Etiqueta con id {} no encontrada", id))
+}
+
+/// Id of the tag called `name`, ignoring case.
+pub(crate) fn find_tag_by_name(db: &Connection, name: &str) -> Result<Option<i64>, String> {
+    db.query_row(
+        "SELECT id FROM tags WHERE name = ?1 COLLATE NOCASE",
+        [name.trim()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Insert a tag whose name was already validated and checked for clashes.
+pub(crate) fn insert_tag(db: &Connection, name: &str) -> Result<i64, String> {
+    db.execute(
+        "INSERT INTO tags (name, created_at) VALUES (?1, ?2)",
+        rusqlite::params![name.trim(), timestamp_now()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(db.last_insert_rowid())
+}
+
+/// Fail with a user-facing message unless every tag exists.
+pub(crate) fn ensure_tags_exist(db: &Connection, tag_ids: &[i64]) -> Result<(), String> {
+    for &id in tag_ids {
+        fetch_tag(db, id)?;
+    }
+    Ok(())
+}
+
+/// Replace the tags of a transaction.
+pub(crate) fn write_transaction_tags(
+    db: &Connection,
+    transaction_id: i64,
+    tag_ids: &[i64],
+) -> Result<(), String> {
+    db.execute(
+        "DELETE FROM transaction_tags WHERE transaction_id = ?1",
+        [transaction_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    for tag_id in tag_ids {
+        db.execute(
+            "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+            [transaction_id, *tag_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+fn ensure_transactions_exist(db: &Connection, transaction_ids: &[i64]) -> Result<(), String> {
+    for &id in transaction_ids {
+        let exists: bool = db
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM transactions WHERE id = ?1 AND deleted_at IS NULL",
+                [id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if !exists {
+            return Err(format!("Transacción con id {} no encontrada", id));
+        }
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_tags(state: State<AppState>) -> Result<Vec<Tag>, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare("SELECT id, name FROM tags ORDER BY name COLLATE NOCASE")
+        .map_err(|e| e.to_string())?;
+
+    let rows = stmt.query_map([], row_to_tag).map_err(|e| e.to_string())?;
+
+    let result: Vec<Tag> = rows
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(result)
+}
+
+#[tauri::command]
+pub fn create_tag(state: State<AppState>, name: String) -> Result<Tag, String> {
+    validate_tag_name(&name)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    if find_tag_by_name(&db, &name)?.is_some() {
+        return Err(format!("Ya existe una etiqueta llamada '{}'", name.trim()));
+    }
+
+    let id = insert_tag(&db, &name)?;
+
+    fetch_tag(&db, id)
+}
+
+#[tauri::command]
+pub fn rename_tag(state: State<AppState>, id: i64, name: String) -> Result<Tag, String> {
+    validate_tag_name(&name)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    // Renaming to a different case of the same name is allowed
+    if find_tag_by_name(&db, &name)?.is_some_and(|existing| existing != id) {
+        return Err(format!("Ya existe una etiqueta llamada '{}'", name.trim()));
+    }
+
+    let affected = db
+        .execute(
+            "UPDATE tags SET name = ?1 WHERE id = ?2",
+            rusqlite::params![name.trim(), id],
+        )
+        .map_err(|e| e.to_string())?;
+
+    if affected == 0 {
+        return Err(format!("Etiqueta con id {} no encontrada", id));
+    }
+
+    fetch_tag(&db, id)
+}
+
+/// Move every transaction tagged with one of `source_ids` to `target_id` and
+/// delete the source tags.
+#[tauri::command]
+pub fn merge_tags(
+    state: State<AppState>,
+    source_ids: Vec<i64>,
+    target_id: i64,
+) -> Result<Tag, String> {
+    if source_ids.contains(&target_id) {
+        return Err("No se puede fusionar una etiqueta consigo misma".into());
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_tags_exist(&db, &source_ids)?;
+    let target = fetch_tag(&db, target_id)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    for source_id in &source_ids {
+        tx.execute(
+            "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id)
+             SELECT transaction_id, ?2 FROM transaction_tags WHERE tag_id = ?1",
+            [source_id, &target_id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute(
+            "INSERT OR IGNORE INTO rule_tags (rule_id, tag_id)
+             SELECT rule_id, ?2 FROM rule_tags WHERE tag_id = ?1",
+            [source_id, &target_id],
+        )
+        .map_err(|e| e.to_string())?;
+        // Links to the source go with it (ON DELETE CASCADE)
+        tx.execute("DELETE FROM tags WHERE id = ?1", [source_id])
+            .map_err(|e| e.to_string())?;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(target)
+}
+
+#[tauri::command]
+pub fn delete_tag(state: State<AppState>, id: i64) -> Result<(), String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let affected = db
+        .execute("DELETE FROM tags WHERE id = ?1", [id])
+        .map_err(|e| e.to_string())?;
+
+    if affected == 0 {
+        return Err(format!("Etiqueta con id {} no encontrada", id));
+    }
+
+    Ok(())
+}
+
+/// Add every tag in `tag_ids` to every transaction in `transaction_ids`.
+/// Returns how many links were created (existing ones are left as they are).
+#[tauri::command]
+pub fn assign_tags(
+    state: State<AppState>,
+    transaction_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    ensure_transactions_exist(&db, &transaction_ids)?;
+    ensure_tags_exist(&db, &tag_ids)?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let added = audit::track_transaction_updates(
+        &tx,
+        &transaction_ids,
+        AuditOrigin::Manual,
+        || {
+            let mut added = 0;
+            for transaction_id in &transaction_ids {
+                let mut linked = 0;
+                for tag_id in &tag_ids {
+                    linked += tx
+                        .execute(
+                            "INSERT OR IGNORE INTO transaction_tags (transaction_id, tag_id) VALUES (?1, ?2)",
+                            [transaction_id, tag_id],
+                        )
+                        .map_err(|e| e.to_string())?;
+                }
+                if linked > 0 {
+                    bump_version(&tx, *transaction_id)?;
+                }
+                added += linked;
+            }
+            Ok(added)
+        },
+    )?;
+    undo::push(&tx, mark, "Asignar etiquetas")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(added)
+}
+
+/// Remove every tag in `tag_ids` from every transaction in
+/// `transaction_ids`. Returns how many links were removed.
+#[tauri::command]
+pub fn unassign_tags(
+    state: State<AppState>,
+    transaction_ids: Vec<i64>,
+    tag_ids: Vec<i64>,
+) -> Result<usize, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let removed = audit::track_transaction_updates(
+        &tx,
+        &transaction_ids,
+        AuditOrigin::Manual,
+        || {
+            let mut removed = 0;
+            for transaction_id in &transaction_ids {
+                let mut unlinked = 0;
+                for tag_id in &tag_ids {
+                    unlinked += tx
+                        .execute(
+                            "DELETE FROM transaction_tags WHERE transaction_id = ?1 AND tag_id = ?2",
+                            [transaction_id, tag_id],
+                        )
+                        .map_err(|e| e.to_string())?;
+                }
+                if unlinked > 0 {
+                    bump_version(&tx, *transaction_id)?;
+                }
+                removed += unlinked;
+            }
+            Ok(removed)
+        },
+    )?;
+    undo::push(&tx, mark, "Quitar etiquetas")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(removed)
+}
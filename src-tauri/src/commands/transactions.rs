@@ -1,175 +1,467 @@
-use crate::models::{Transaction, TransactionInput, TransactionUpdate};
+use crate::commands::attachments::{row_to_attachment, ATTACHMENT_COLUMNS};
+use crate::commands::audit;
+use crate::commands::payees::{ensure_payee_exists, PayeeMatcher};
+use crate::commands::rules::load_rule_set;
+use crate::commands::tags::{ensure_tags_exist, write_transaction_tags};
+use crate::commands::undo;
+use crate::dedup::{self, DuplicateProbe};
+use crate::models::{
+    timestamp_now, AddTransactionResult, AddTransactionsResult, AmountRepair, Attachment,
+    AuditAction, AuditOrigin, DuplicateMatch, DuplicatePair, Entity, InputError, Invoice, Period,
+    SplitInput, Tag, TagMatch, Transaction, TransactionFilter, TransactionInput, TransactionOrigin,
+    TransactionPatch, TransactionSplit, TransactionUpdate, WriteError,
+};
+use crate::money::{prorate, Currency, Money, Rate};
 use crate::AppState;
+use rusqlite::types::Value;
+use rusqlite::{Connection, Row};
+use std::collections::{HashMap, HashSet};
 use tauri::State;
 
-#[tauri::command]
-pub fn get_transactions(state: State<AppState>) -> Result<Vec<Transaction>, String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+/// Column list matching `row_to_transaction`.
+pub(crate) const TRANSACTION_COLUMNS: &str =
+    "id, description, amount, amount_in_ars, currency, exchange_rate,
+     category_id, date, type, account, external_id, import_batch_id, duplicate_of,
+     payee_id, invoice_number, issuer_cuit, version, created_at, updated_at";
+
+/// The invoice stored in the `invoice_number` and `issuer_cuit` columns.
+fn invoice_from_columns(number: Option<String>, issuer_cuit: Option<String>) -> Option<Invoice> {
+    number
+        .zip(issuer_cuit)
+        .map(|(number, issuer_cuit)| Invoice {
+            number,
+            issuer_cuit,
+        })
+}
+
+/// Map a row selected with `TRANSACTION_COLUMNS` into a `Transaction`.
+pub(crate) fn row_to_transaction(row: &Row) -> rusqlite::Result<Transaction> {
+    Ok(Transaction {
+        id: row.get(0)?,
+        description: row.get(1)?,
+        amount: Money::new(row.get(2)?, row.get(4)?),
+        amount_in_ars: Money::ars(row.get(3)?),
+        exchange_rate: row.get(5)?,
+        category_id: row.get(6)?,
+        date: row.get(7)?,
+        r#type: row.get(8)?,
+        account: row.get(9)?,
+        external_id: row.get(10)?,
+        import_batch_id: row.get(11)?,
+        duplicate_of: row.get(12)?,
+        payee_id: row.get(13)?,
+        invoice: invoice_from_columns(row.get(14)?, row.get(15)?),
+        // Filled in by `query_transactions`
+        splits: Vec::new(),
+        tags: Vec::new(),
+        attachments: Vec::new(),
+        version: row.get(16)?,
+        created_at: row.get(17)?,
+        updated_at: row.get(18)?,
+    })
+}
+
+/// Load the transactions selected by `clause` (whatever follows
+/// `FROM transactions`), with their split lines, tags and attachments.
+pub(crate) fn query_transactions<P: rusqlite::Params>(
+    db: &Connection,
+    clause: &str,
+    params: P,
+) -> Result<Vec<Transaction>, String> {
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM transactions {}",
+            TRANSACTION_COLUMNS, clause
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut transactions = stmt
+        .query_map(params, row_to_transaction)
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    attach_splits(db, &mut transactions)?;
+    attach_tags(db, &mut transactions)?;
+    attach_attachments(db, &mut transactions)?;
+
+    Ok(transactions)
+}
+
+/// SQL condition on `transactions` (and its parameters) for `filter`.
+pub(crate) fn filter_condition(filter: &TransactionFilter) -> (String, Vec<Value>) {
+    let mut conditions = vec!["transactions.deleted_at IS NULL".to_string()];
+    let mut params: Vec<Value> = Vec::new();
+
+    // Dates are stored as `YYYY-MM-DD`, so they compare as text
+    if let Some(from) = filter.period.from {
+        params.push(from.to_string().into());
+        conditions.push(format!("transactions.date >= ?{}", params.len()));
+    }
+    if let Some(to) = filter.period.to {
+        params.push(to.to_string().into());
+        conditions.push(format!("transactions.date <= ?{}", params.len()));
+    }
+
+    if let Some(category_id) = filter.category_id {
+        params.push(category_id.into());
+        conditions.push(format!(
+            "(transactions.category_id = ?{n} OR transactions.id IN
+              (SELECT transaction_id FROM transaction_splits WHERE category_id = ?{n}))",
+            n = params.len()
+        ));
+    }
+
+    if let Some(payee_id) = filter.payee_id {
+        params.push(payee_id.into());
+        conditions.push(format!("transactions.payee_id = ?{}", params.len()));
+    }
+
+    let mut tag_ids = filter.tag_ids.clone();
+    tag_ids.sort_unstable();
+    tag_ids.dedup();
+    if !tag_ids.is_empty() {
+        let placeholders: Vec<String> = tag_ids
+            .iter()
+            .map(|&id| {
+                params.push(id.into());
+                format!("?{}", params.len())
+            })
+            .collect();
+        let placeholders = placeholders.join(", ");
+        conditions.push(match filter.tag_match {
+            TagMatch::Any => format!(
+                "transactions.id IN (SELECT transaction_id FROM transaction_tags WHERE tag_id IN ({}))",
+                placeholders
+            ),
+            TagMatch::All => format!(
+                "(SELECT COUNT(*) FROM transaction_tags
+                  WHERE transaction_id = transactions.id AND tag_id IN ({})) = {}",
+                placeholders,
+                tag_ids.len()
+            ),
+        });
+    }
+
+    (conditions.join(" AND "), params)
+}
+
+/// Transactions matching `filter`, newest first.
+pub(crate) fn filter_transactions(
+    db: &Connection,
+    filter: &TransactionFilter,
+) -> Result<Vec<Transaction>, String> {
+    let (condition, params) = filter_condition(filter);
+    query_transactions(
+        db,
+        &format!("WHERE {} ORDER BY date DESC, id DESC", condition),
+        rusqlite::params_from_iter(params),
+    )
+}
+
+fn attach_splits(db: &Connection, transactions: &mut [Transaction]) -> Result<(), String> {
+    // A single row is the common case (fetch after insert/update)
+    let only = match transactions {
+        [] => return Ok(()),
+        [t] => Some(t.id),
+        _ => None,
+    };
 
     let mut stmt = db
         .prepare(
-            "SELECT id, description, amount, amount_in_ars, currency, exchange_rate,
-                category_id, date, type, created_at, updated_at
-         FROM transactions ORDER BY date DESC, id DESC",
+            "SELECT s.transaction_id, s.id, s.category_id, s.amount, s.amount_in_ars, s.note,
+             t.currency
+             FROM transaction_splits s JOIN transactions t ON t.id = s.transaction_id
+             WHERE ?1 IS NULL OR s.transaction_id = ?1
+             ORDER BY s.transaction_id, s.position",
         )
         .map_err(|e| e.to_string())?;
-
     let rows = stmt
-        .query_map([], |row| {
-            let amount_cents: i64 = row.get(2)?;
-            let amount_in_ars_cents: i64 = row.get(3)?;
-            Ok(Transaction {
-                id: row.get(0)?,
-                description: row.get(1)?,
-                amount: amount_cents as f64 / 100.0,
-                amount_in_ars: amount_in_ars_cents as f64 / 100.0,
-                currency: row.get(4)?,
-                exchange_rate: row.get(5)?,
-                category_id: row.get(6)?,
-                date: row.get(7)?,
-                r#type: row.get(8)?,
-                created_at: row.get(9)?,
-                updated_at: row.get(10)?,
-            })
+        .query_map([only], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                TransactionSplit {
+                    id: row.get(1)?,
+                    category_id: row.get(2)?,
+                    amount: Money::new(row.get(3)?, row.get(6)?),
+                    amount_in_ars: Money::ars(row.get(4)?),
+                    note: row.get(5)?,
+                },
+            ))
         })
         .map_err(|e| e.to_string())?;
 
-    let result: Vec<Transaction> = rows
-        .collect::<Result<Vec<_>, _>>()
+    let mut by_transaction: HashMap<i64, Vec<TransactionSplit>> = HashMap::new();
+    for row in rows {
+        let (transaction_id, split) = row.map_err(|e| e.to_string())?;
+        by_transaction
+            .entry(transaction_id)
+            .or_default()
+            .push(split);
+    }
+    for t in transactions {
+        t.splits = by_transaction.remove(&t.id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+fn attach_tags(db: &Connection, transactions: &mut [Transaction]) -> Result<(), String> {
+    let only = match transactions {
+        [] => return Ok(()),
+        [t] => Some(t.id),
+        _ => None,
+    };
+
+    let mut stmt = db
+        .prepare(
+            "SELECT tt.transaction_id, t.id, t.name
+             FROM transaction_tags tt JOIN tags t ON t.id = tt.tag_id
+             WHERE ?1 IS NULL OR tt.transaction_id = ?1
+             ORDER BY t.name COLLATE NOCASE",
+        )
         .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([only], |row| {
+            let tag = Tag {
+                id: row.get(1)?,
+                name: row.get(2)?,
+            };
+            Ok((row.get::<_, i64>(0)?, tag))
+        })
+        .map_err(|e| e.to_string())?;
+
+    let mut by_transaction: HashMap<i64, Vec<Tag>> = HashMap::new();
+    for row in rows {
+        let (transaction_id, tag) = row.map_err(|e| e.to_string())?;
+        by_transaction.entry(transaction_id).or_default().push(tag);
+    }
+    for t in transactions {
+        t.tags = by_transaction.remove(&t.id).unwrap_or_default();
+    }
 
-    Ok(result)
+    Ok(())
 }
 
-#[tauri::command]
-pub fn add_transaction(
-    state: State<AppState>,
-    input: TransactionInput,
-) -> Result<Transaction, String> {
-    input.validate()?;
+fn attach_attachments(db: &Connection, transactions: &mut [Transaction]) -> Result<(), String> {
+    let only = match transactions {
+        [] => return Ok(()),
+        [t] => Some(t.id),
+        _ => None,
+    };
 
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {} FROM attachments
+             WHERE ?1 IS NULL OR transaction_id = ?1
+             ORDER BY id",
+            ATTACHMENT_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([only], row_to_attachment)
+        .map_err(|e| e.to_string())?;
+
+    let mut by_transaction: HashMap<i64, Vec<Attachment>> = HashMap::new();
+    for row in rows {
+        let attachment = row.map_err(|e| e.to_string())?;
+        by_transaction
+            .entry(attachment.transaction_id)
+            .or_default()
+            .push(attachment);
+    }
+    for t in transactions {
+        t.attachments = by_transaction.remove(&t.id).unwrap_or_default();
+    }
 
-    // Verify that the category exists
+    Ok(())
+}
+
+/// Load a single transaction by id.
+pub(crate) fn fetch_transaction(db: &Connection, id: i64) -> Result<Transaction, String> {
+    query_transactions(db, "WHERE id = ?1 AND deleted_at IS NULL", [id])?
+        .pop()
+        .ok_or_else(|| format!("Transacción con id {} no encontrada", id))
+}
+
+/// Fail with a user-facing message if the category does not exist.
+pub(crate) fn ensure_category_exists(db: &Connection, category_id: i64) -> Result<(), String> {
     let category_exists: bool = db
         .query_row(
-            "SELECT COUNT(*) > 0 FROM categories WHERE id = ?1",
-            [input.category_id],
+            "SELECT COUNT(*) > 0 FROM categories WHERE id = ?1 AND deleted_at IS NULL",
+            [category_id],
             |row| row.get(0),
         )
         .map_err(|e| e.to_string())?;
 
     if !category_exists {
-        return Err(format!(
-            "La categoría con id {} no existe",
-            input.category_id
-        ));
+        return Err(format!("La categoría con id {} no existe", category_id));
     }
 
-    let now = chrono::Utc::now().to_rfc3339();
+    Ok(())
+}
 
-    // Convert floats to centavos for storage
-    let amount_cents = (input.amount * 100.0).round() as i64;
-    let amount_in_ars_cents = (input.amount_in_ars * 100.0).round() as i64;
+/// Check that every category, tag and payee `input` refers to exists.
+fn ensure_input_references_exist(db: &Connection, input: &TransactionInput) -> Result<(), String> {
+    ensure_category_exists(db, input.category_id)?;
+    if let Some(payee_id) = input.payee_id {
+        ensure_payee_exists(db, payee_id)?;
+    }
+    for split in &input.splits {
+        ensure_category_exists(db, split.category_id)?;
+    }
+    if let Some(tag_ids) = &input.tag_ids {
+        ensure_tags_exist(db, tag_ids)?;
+    }
+    Ok(())
+}
 
+/// Replace the split lines of a transaction. Each line's share of
+/// `amount_in_ars` is prorated by its amount; the last line takes the
+/// rounding remainder so the shares always add up to the parent.
+pub(crate) fn write_splits(
+    db: &Connection,
+    transaction_id: i64,
+    amount_cents: i64,
+    amount_in_ars_cents: i64,
+    splits: &[SplitInput],
+) -> Result<(), String> {
     db.execute(
-        "INSERT INTO transactions (description, amount, amount_in_ars, currency, exchange_rate,
-         category_id, date, type, created_at, updated_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
-        rusqlite::params![
-            input.description,
-            amount_cents,
-            amount_in_ars_cents,
-            input.currency,
-            input.exchange_rate,
-            input.category_id,
-            input.date,
-            input.r#type,
-            &now,
-            &now
-        ],
+        "DELETE FROM transaction_splits WHERE transaction_id = ?1",
+        [transaction_id],
     )
     .map_err(|e| e.to_string())?;
 
-    let id = db.last_insert_rowid();
+    let mut remaining_ars = amount_in_ars_cents;
+    for (position, split) in splits.iter().enumerate() {
+        let split_cents = split.amount.cents;
+        let split_ars = if position + 1 == splits.len() {
+            remaining_ars
+        } else {
+            prorate(amount_in_ars_cents, split_cents, amount_cents)
+        };
+        remaining_ars -= split_ars;
 
-    Ok(Transaction {
-        id,
-        description: input.description,
-        amount: input.amount,
-        amount_in_ars: input.amount_in_ars,
-        currency: input.currency,
-        exchange_rate: input.exchange_rate,
-        category_id: input.category_id,
-        date: input.date,
-        r#type: input.r#type,
-        created_at: now.clone(),
-        updated_at: now,
-    })
-}
-
-#[tauri::command]
-pub fn delete_transaction(state: State<AppState>, id: i64) -> Result<(), String> {
-    let db = state.db.lock().map_err(|e| e.to_string())?;
-
-    let affected = db
-        .execute("DELETE FROM transactions WHERE id = ?1", [id])
+        db.execute(
+            "INSERT INTO transaction_splits
+             (transaction_id, position, category_id, amount, amount_in_ars, note)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                transaction_id,
+                position as i64,
+                split.category_id,
+                split_cents,
+                split_ars,
+                split
+                    .note
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty())
+            ],
+        )
         .map_err(|e| e.to_string())?;
-
-    if affected == 0 {
-        return Err(format!("Transacción con id {} no encontrada", id));
     }
 
     Ok(())
 }
 
-#[tauri::command]
-pub fn update_transaction(
-    state: State<AppState>,
-    id: i64,
-    input: TransactionUpdate,
+/// Insert an already validated transaction. Shared by `add_transaction` and
+/// the importers, which also fill in the origin fields.
+pub(crate) fn insert_transaction(
+    db: &Connection,
+    input: TransactionInput,
+    origin: TransactionOrigin,
 ) -> Result<Transaction, String> {
-    input.validate()?;
+    let now = timestamp_now();
 
-    let db = state.db.lock().map_err(|e| e.to_string())?;
+    let amount_cents = input.amount.cents;
+    let amount_in_ars_cents = input.derived_amount_in_ars()?.cents;
+    let fingerprint = dedup::fingerprint(
+        input.date,
+        amount_cents,
+        input.amount.currency,
+        &input.description,
+    );
 
-    // Verify that the category exists
-    let category_exists: bool = db
-        .query_row(
-            "SELECT COUNT(*) > 0 FROM categories WHERE id = ?1",
-            [input.category_id],
-            |row| row.get(0),
+    // Cached, since batches and imports insert many rows in a row
+    let mut stmt = db
+        .prepare_cached(
+            "INSERT INTO transactions (description, amount, amount_in_ars, currency,
+             exchange_rate, category_id, date, type, account, external_id, fingerprint,
+             import_batch_id, duplicate_of, payee_id, invoice_number, issuer_cuit, created_at,
+             updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+             ?17, ?18)",
         )
         .map_err(|e| e.to_string())?;
+    stmt.execute(rusqlite::params![
+        input.description,
+        amount_cents,
+        amount_in_ars_cents,
+        input.amount.currency,
+        input.exchange_rate,
+        input.category_id,
+        input.date,
+        input.r#type,
+        origin.account,
+        origin.external_id,
+        fingerprint,
+        origin.import_batch_id,
+        origin.duplicate_of,
+        input.payee_id,
+        input.invoice.as_ref().map(|i| i.number.trim()),
+        input.invoice.as_ref().map(|i| i.issuer_cuit.trim()),
+        &now,
+        &now
+    ])
+    .map_err(|e| e.to_string())?;
 
-    if !category_exists {
-        return Err(format!(
-            "La categoría con id {} no existe",
-            input.category_id
-        ));
+    let id = db.last_insert_rowid();
+    write_splits(db, id, amount_cents, amount_in_ars_cents, &input.splits)?;
+    if let Some(tag_ids) = &input.tag_ids {
+        write_transaction_tags(db, id, tag_ids)?;
     }
 
-    let now = chrono::Utc::now().to_rfc3339();
+    fetch_transaction(db, id)
+}
+
+/// Overwrite a live transaction with `input`, splits and tags included.
+/// Returns false when there is no such transaction.
+pub(crate) fn write_transaction(
+    db: &Connection,
+    id: i64,
+    input: &TransactionInput,
+) -> Result<bool, String> {
+    let now = timestamp_now();
 
-    // Convert floats to centavos for storage
-    let amount_cents = (input.amount * 100.0).round() as i64;
-    let amount_in_ars_cents = (input.amount_in_ars * 100.0).round() as i64;
+    let amount_cents = input.amount.cents;
+    let amount_in_ars_cents = input.derived_amount_in_ars()?.cents;
+    let fingerprint = dedup::fingerprint(
+        input.date,
+        amount_cents,
+        input.amount.currency,
+        &input.description,
+    );
 
     let affected = db
         .execute(
             "UPDATE transactions SET description = ?1, amount = ?2, amount_in_ars = ?3,
              currency = ?4, exchange_rate = ?5, category_id = ?6, date = ?7,
-             type = ?8, updated_at = ?9
-             WHERE id = ?10",
+             type = ?8, payee_id = ?9, invoice_number = ?10, issuer_cuit = ?11,
+             fingerprint = ?12, updated_at = ?13, version = version + 1
+             WHERE id = ?14 AND deleted_at IS NULL",
             rusqlite::params![
                 input.description,
                 amount_cents,
                 amount_in_ars_cents,
-                input.currency,
+                input.amount.currency,
                 input.exchange_rate,
                 input.category_id,
                 input.date,
                 input.r#type,
+                input.payee_id,
+                input.invoice.as_ref().map(|i| i.number.trim()),
+                input.invoice.as_ref().map(|i| i.issuer_cuit.trim()),
+                fingerprint,
                 &now,
                 id
             ],
@@ -177,29 +469,629 @@ pub fn update_transaction(
         .map_err(|e| e.to_string())?;
 
     if affected == 0 {
-        return Err(format!("Transacción con id {} no encontrada", id));
+        return Ok(false);
     }
 
-    // Return the updated transaction with the original created_at
-    let created_at: String = db
-        .query_row(
-            "SELECT created_at FROM transactions WHERE id = ?1",
-            [id],
-            |row| row.get(0),
+    write_splits(db, id, amount_cents, amount_in_ars_cents, &input.splits)?;
+    if let Some(tag_ids) = &input.tag_ids {
+        write_transaction_tags(db, id, tag_ids)?;
+    }
+
+    Ok(true)
+}
+
+/// Mark a transaction as changed by something that does not touch its row,
+/// such as its tags.
+pub(crate) fn bump_version(db: &Connection, id: i64) -> Result<(), String> {
+    db.execute(
+        "UPDATE transactions SET version = version + 1 WHERE id = ?1",
+        [id],
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Move a live transaction to the trash. Returns false when there is no
+/// such transaction.
+pub(crate) fn trash_transaction(
+    db: &Connection,
+    id: i64,
+    origin: AuditOrigin,
+) -> Result<bool, String> {
+    let affected = db
+        .execute(
+            "UPDATE transactions SET deleted_at = ?1, version = version + 1
+             WHERE id = ?2 AND deleted_at IS NULL",
+            rusqlite::params![timestamp_now(), id],
         )
         .map_err(|e| e.to_string())?;
 
-    Ok(Transaction {
-        id,
-        description: input.description,
-        amount: input.amount,
-        amount_in_ars: input.amount_in_ars,
-        currency: input.currency,
-        exchange_rate: input.exchange_rate,
-        category_id: input.category_id,
+    if affected == 0 {
+        return Ok(false);
+    }
+
+    // Rows flagged as probable duplicates of this one no longer need review.
+    // The live ones are audited so that undoing the deletion flags them again.
+    let flagged: Vec<i64> =
+        query_transactions(db, "WHERE duplicate_of = ?1 AND deleted_at IS NULL", [id])?
+            .iter()
+            .map(|t| t.id)
+            .collect();
+    audit::track_transaction_updates(db, &flagged, origin, || {
+        db.execute(
+            "UPDATE transactions SET duplicate_of = NULL, version = version + 1
+             WHERE duplicate_of = ?1",
+            [id],
+        )
+        .map_err(|e| e.to_string())
+    })?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn get_transactions(
+    state: State<AppState>,
+    filter: Option<TransactionFilter>,
+) -> Result<Vec<Transaction>, String> {
+    let filter = filter.unwrap_or_default();
+    filter.validate()?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    filter_transactions(&db, &filter)
+}
+
+/// Stored transactions that `input` probably duplicates, best match first.
+fn likely_duplicates(
+    db: &Connection,
+    input: &TransactionInput,
+) -> Result<Vec<DuplicateMatch>, String> {
+    let probe = DuplicateProbe {
+        external_id: None,
+        account: None,
         date: input.date,
+        amount_cents: input.amount.cents,
+        currency: input.amount.currency,
         r#type: input.r#type,
-        created_at,
-        updated_at: now,
+        description: &input.description,
+        batch_id: None,
+    };
+
+    dedup::find_similar(db, &probe, dedup::MANUAL_DUPLICATE_DAYS)?
+        .into_iter()
+        .map(|(id, similarity)| {
+            Ok(DuplicateMatch {
+                transaction: fetch_transaction(db, id)?,
+                similarity,
+            })
+        })
+        .collect()
+}
+
+/// Add a transaction unless it looks like one already stored, in which case
+/// the matches are returned instead. `allow_duplicate` skips the check once
+/// the user has confirmed. Without a payee, the one whose name or alias
+/// matches the description is linked; the user's rules run last and may
+/// replace the category and payee and add tags.
+#[tauri::command]
+pub fn add_transaction(
+    state: State<AppState>,
+    mut input: TransactionInput,
+    allow_duplicate: Option<bool>,
+) -> Result<AddTransactionResult, String> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    if input.payee_id.is_none() {
+        input.payee_id = PayeeMatcher::load(&db)?
+            .find(&input.description)
+            .map(|payee| payee.id);
+    }
+    load_rule_set(&db)?.apply(&mut input, None);
+    // Checked as it will be stored, including what the rules set
+    input.validate()?;
+    ensure_input_references_exist(&db, &input)?;
+
+    if !allow_duplicate.unwrap_or(false) {
+        let duplicates = likely_duplicates(&db, &input)?;
+        if !duplicates.is_empty() {
+            return Ok(AddTransactionResult::PossibleDuplicates { duplicates });
+        }
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+    let transaction = insert_transaction(&tx, input, TransactionOrigin::default())?;
+    audit::record(
+        &tx,
+        Entity::Transaction,
+        transaction.id,
+        AuditAction::Create,
+        AuditOrigin::Manual,
+        None,
+        Some(&transaction),
+    )?;
+    undo::push(&tx, mark, "Agregar transacción")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(AddTransactionResult::Created {
+        transaction: Box::new(transaction),
     })
 }
+
+/// Ids returned by `sql`, for checking many references with one query.
+fn id_set(db: &Connection, sql: &str) -> Result<HashSet<i64>, String> {
+    let mut stmt = db.prepare(sql).map_err(|e| e.to_string())?;
+    let ids = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(ids)
+}
+
+/// Why `input` cannot be stored, checking its references against the ids
+/// that exist.
+fn batch_input_error(
+    input: &TransactionInput,
+    categories: &HashSet<i64>,
+    payees: &HashSet<i64>,
+    tags: &HashSet<i64>,
+) -> Option<String> {
+    if let Err(e) = input.validate() {
+        return Some(e);
+    }
+    let category_ids =
+        std::iter::once(input.category_id).chain(input.splits.iter().map(|s| s.category_id));
+    for id in category_ids {
+        if !categories.contains(&id) {
+            return Some(format!("La categoría con id {} no existe", id));
+        }
+    }
+    if let Some(id) = input.payee_id.filter(|id| !payees.contains(id)) {
+        return Some(format!("Beneficiario con id {} no encontrado", id));
+    }
+    let tag_ids = input.tag_ids.iter().flatten();
+    if let Some(id) = tag_ids.copied().find(|id| !tags.contains(id)) {
+        return Some(format!("Etiqueta con id {} no encontrada", id));
+    }
+    None
+}
+
+/// Store many transactions at once, e.g. a month of receipts. Payees and
+/// rules are applied as in `add_transaction`, but there is no duplicate
+/// check. Every resulting input is checked and nothing is written unless
+/// all pass; otherwise the errors come back by index.
+#[tauri::command]
+pub fn add_transactions(
+    state: State<AppState>,
+    mut inputs: Vec<TransactionInput>,
+) -> Result<AddTransactionsResult, String> {
+    if inputs.is_empty() {
+        return Err("No hay transacciones para agregar".into());
+    }
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let matcher = PayeeMatcher::load(&db)?;
+    let rules = load_rule_set(&db)?;
+    for input in &mut inputs {
+        if input.payee_id.is_none() {
+            input.payee_id = matcher.find(&input.description).map(|payee| payee.id);
+        }
+        rules.apply(input, None);
+    }
+
+    let categories = id_set(&db, "SELECT id FROM categories WHERE deleted_at IS NULL")?;
+    let payees = id_set(&db, "SELECT id FROM payees")?;
+    let tags = id_set(&db, "SELECT id FROM tags")?;
+    let errors: Vec<InputError> = inputs
+        .iter()
+        .enumerate()
+        .filter_map(|(index, input)| {
+            batch_input_error(input, &categories, &payees, &tags)
+                .map(|error| InputError { index, error })
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Ok(AddTransactionsResult::Invalid { errors });
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+
+    let mut transactions = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let transaction = insert_transaction(&tx, input, TransactionOrigin::default())?;
+        audit::record(
+            &tx,
+            Entity::Transaction,
+            transaction.id,
+            AuditAction::Create,
+            AuditOrigin::Manual,
+            None,
+            Some(&transaction),
+        )?;
+        transactions.push(transaction);
+    }
+
+    undo::push(&tx, mark, "Agregar transacciones")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(AddTransactionsResult::Created { transactions })
+}
+
+/// Sweep stored transactions in `period` for pairs that look like the same
+/// movement entered twice.
+#[tauri::command]
+pub fn find_duplicates(
+    state: State<AppState>,
+    period: Option<Period>,
+) -> Result<Vec<DuplicatePair>, String> {
+    let period = period.unwrap_or_default();
+    period.validate()?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    dedup::sweep(&db, &period)?
+        .into_iter()
+        .map(|(original, duplicate, similarity)| {
+            Ok(DuplicatePair {
+                original: fetch_transaction(&db, original)?,
+                duplicate: fetch_transaction(&db, duplicate)?,
+                similarity,
+            })
+        })
+        .collect()
+}
+
+/// Fail with a conflict unless `current` is still at the `version` the
+/// client read.
+fn check_version(
+    current: Transaction,
+    version: i64,
+) -> Result<Transaction, WriteError<Transaction>> {
+    if current.version != version {
+        return Err(WriteError::Conflict {
+            message: "La transacción cambió mientras la editabas".into(),
+            current: Box::new(current),
+        });
+    }
+    Ok(current)
+}
+
+/// Move a transaction to the trash (see `commands::trash`). `version` is
+/// the one the client read; a stale one fails with a conflict.
+#[tauri::command]
+pub fn delete_transaction(
+    state: State<AppState>,
+    id: i64,
+    version: i64,
+) -> Result<(), WriteError<Transaction>> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    check_version(fetch_transaction(&tx, id)?, version)?;
+    let mark = undo::mark(&tx)?;
+
+    if !trash_transaction(&tx, id, AuditOrigin::Manual)? {
+        return Err(format!("Transacción con id {} no encontrada", id).into());
+    }
+
+    audit::record_transactions(&tx, &[id], AuditAction::Delete, AuditOrigin::Manual)?;
+    undo::push(&tx, mark, "Eliminar transacción")?;
+
+    Ok(tx.commit().map_err(|e| e.to_string())?)
+}
+
+/// Replace every field of a transaction. Fails with a conflict carrying the
+/// current row when `version` is not the one stored.
+#[tauri::command]
+pub fn update_transaction(
+    state: State<AppState>,
+    id: i64,
+    input: TransactionUpdate,
+    version: i64,
+) -> Result<Transaction, WriteError<Transaction>> {
+    input.validate()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    ensure_input_references_exist(&tx, &input)?;
+    let before = check_version(fetch_transaction(&tx, id)?, version)?;
+    let mark = undo::mark(&tx)?;
+
+    if !write_transaction(&tx, id, &input)? {
+        return Err(format!("Transacción con id {} no encontrada", id).into());
+    }
+
+    // Return the updated row with its original created_at and origin
+    let transaction = fetch_transaction(&tx, id)?;
+    audit::record(
+        &tx,
+        Entity::Transaction,
+        id,
+        AuditAction::Update,
+        AuditOrigin::Manual,
+        Some(&before),
+        Some(&transaction),
+    )?;
+    undo::push(&tx, mark, "Editar transacción")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(transaction)
+}
+
+/// Columns of `transactions` that differ between `current` and `merged`,
+/// with their new values.
+fn changed_columns(
+    current: &TransactionInput,
+    merged: &TransactionInput,
+) -> Vec<(&'static str, Value)> {
+    let mut columns: Vec<(&'static str, Value)> = Vec::new();
+    if merged.description != current.description {
+        columns.push(("description", merged.description.clone().into()));
+    }
+    if merged.amount.cents != current.amount.cents {
+        columns.push(("amount", merged.amount.cents.into()));
+    }
+    if merged.amount.currency != current.amount.currency {
+        columns.push((
+            "currency",
+            merged.amount.currency.as_str().to_string().into(),
+        ));
+    }
+    if merged.exchange_rate != current.exchange_rate {
+        columns.push((
+            "exchange_rate",
+            merged.exchange_rate.map(Rate::millionths).into(),
+        ));
+    }
+    if merged.category_id != current.category_id {
+        columns.push(("category_id", merged.category_id.into()));
+    }
+    if merged.date != current.date {
+        columns.push(("date", merged.date.to_string().into()));
+    }
+    if merged.r#type != current.r#type {
+        columns.push(("type", merged.r#type.as_str().to_string().into()));
+    }
+    if merged.payee_id != current.payee_id {
+        columns.push(("payee_id", merged.payee_id.into()));
+    }
+
+    let invoice = |input: &TransactionInput| {
+        input.invoice.as_ref().map(|i| {
+            (
+                i.number.trim().to_string(),
+                i.issuer_cuit.trim().to_string(),
+            )
+        })
+    };
+    if invoice(merged) != invoice(current) {
+        let (number, issuer_cuit) = invoice(merged).unzip();
+        columns.push(("invoice_number", number.into()));
+        columns.push(("issuer_cuit", issuer_cuit.into()));
+    }
+
+    // The fingerprint follows what duplicate detection compares
+    if columns
+        .iter()
+        .any(|(name, _)| matches!(*name, "description" | "amount" | "currency" | "date"))
+    {
+        let fingerprint = dedup::fingerprint(
+            merged.date,
+            merged.amount.cents,
+            merged.amount.currency,
+            &merged.description,
+        );
+        columns.push(("fingerprint", fingerprint.into()));
+    }
+
+    columns
+}
+
+/// Change only the given fields of a transaction. The result is validated
+/// as a whole and only what actually changed is written. A stale `version`
+/// fails with a conflict, as in `update_transaction`.
+#[tauri::command]
+pub fn patch_transaction(
+    state: State<AppState>,
+    id: i64,
+    patch: TransactionPatch,
+    version: i64,
+) -> Result<Transaction, WriteError<Transaction>> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let before = check_version(fetch_transaction(&db, id)?, version)?;
+    let current = TransactionInput::from(&before);
+    let merged = patch.merge(&before)?;
+    ensure_input_references_exist(&db, &merged)?;
+
+    let mut columns = changed_columns(&current, &merged);
+    // Compared with the stored value, which repairs a drifted one too
+    let amount_in_ars = merged.derived_amount_in_ars()?;
+    if amount_in_ars != before.amount_in_ars {
+        columns.push(("amount_in_ars", amount_in_ars.cents.into()));
+    }
+    let amounts_changed = columns
+        .iter()
+        .any(|(name, _)| matches!(*name, "amount" | "amount_in_ars"));
+    // Split shares are prorated from the amounts, so they follow them
+    let splits_changed = serde_json::to_value(&merged.splits).ok()
+        != serde_json::to_value(&current.splits).ok()
+        || (amounts_changed && !merged.splits.is_empty());
+    let tags_changed = merged.tag_ids != current.tag_ids;
+    if columns.is_empty() && !splits_changed && !tags_changed {
+        return Ok(before);
+    }
+
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+
+    columns.push(("updated_at", timestamp_now().into()));
+    let assignments: Vec<String> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (name, _))| format!("{} = ?{}", name, i + 1))
+        .collect();
+    let mut params: Vec<Value> = columns.into_iter().map(|(_, value)| value).collect();
+    params.push(id.into());
+    tx.execute(
+        &format!(
+            "UPDATE transactions SET {}, version = version + 1
+             WHERE id = ?{} AND deleted_at IS NULL",
+            assignments.join(", "),
+            params.len()
+        ),
+        rusqlite::params_from_iter(params),
+    )
+    .map_err(|e| e.to_string())?;
+
+    if splits_changed {
+        write_splits(
+            &tx,
+            id,
+            merged.amount.cents,
+            amount_in_ars.cents,
+            &merged.splits,
+        )?;
+    }
+    if let Some(tag_ids) = merged.tag_ids.as_ref().filter(|_| tags_changed) {
+        write_transaction_tags(&tx, id, tag_ids)?;
+    }
+
+    let transaction = fetch_transaction(&tx, id)?;
+    audit::record(
+        &tx,
+        Entity::Transaction,
+        id,
+        AuditAction::Update,
+        AuditOrigin::Manual,
+        Some(&before),
+        Some(&transaction),
+    )?;
+    undo::push(&tx, mark, "Editar transacción")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(transaction)
+}
+
+/// Recompute `amount_in_ars` of every live transaction from its amount and
+/// exchange rate and fix the rows that drifted, such as ones saved while
+/// the client still sent it. A rate left on a peso row is dropped too.
+/// Split shares are prorated again along with their parent. All fixes are
+/// one undo step; rows that cannot be derived are only reported.
+#[tauri::command]
+pub fn repair_amounts_in_ars(state: State<AppState>) -> Result<Vec<AmountRepair>, String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    let mut drifted = Vec::new();
+    for t in query_transactions(&tx, "WHERE deleted_at IS NULL ORDER BY id", [])? {
+        let mut input = TransactionInput::from(&t);
+        if t.currency() == Currency::Ars {
+            input.exchange_rate = None;
+        }
+        let derived = input.derived_amount_in_ars();
+        let splits_ars: i64 = t.splits.iter().map(|s| s.amount_in_ars.cents).sum();
+        let in_sync = derived.as_ref().is_ok_and(|amount_in_ars| {
+            *amount_in_ars == t.amount_in_ars
+                && input.exchange_rate == t.exchange_rate
+                && (t.splits.is_empty() || splits_ars == amount_in_ars.cents)
+        });
+        if !in_sync {
+            drifted.push((t, input, derived));
+        }
+    }
+    if drifted.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mark = undo::mark(&tx)?;
+    let ids: Vec<i64> = drifted.iter().map(|(t, _, _)| t.id).collect();
+    let repairs = audit::track_transaction_updates(&tx, &ids, AuditOrigin::Manual, || {
+        let mut repairs = Vec::with_capacity(drifted.len());
+        for (t, input, derived) in drifted {
+            if derived.is_ok() {
+                write_transaction(&tx, t.id, &input)?;
+            }
+            let (amount_in_ars, error) = match derived {
+                Ok(amount_in_ars) => (Some(amount_in_ars), None),
+                Err(e) => (None, Some(e)),
+            };
+            repairs.push(AmountRepair {
+                id: t.id,
+                description: t.description,
+                amount: t.amount,
+                exchange_rate: input.exchange_rate,
+                stored: t.amount_in_ars,
+                amount_in_ars,
+                error,
+            });
+        }
+        Ok(repairs)
+    })?;
+    undo::push(&tx, mark, "Corregir montos en ARS")?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(repairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::models::TransactionType;
+    use chrono::NaiveDate;
+
+    fn split(category_id: i64, cents: i64) -> SplitInput {
+        SplitInput {
+            category_id,
+            amount: Money::new(cents, Currency::Usd),
+            note: None,
+        }
+    }
+
+    #[test]
+    fn split_shares_add_up_to_the_parent() {
+        let db = Connection::open_in_memory().unwrap();
+        db::migrate(&db).unwrap();
+        let category_id = db
+            .query_row(
+                "SELECT id FROM categories WHERE type = 'expense' LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let input = TransactionInput {
+            description: "Cena".into(),
+            amount: Money::new(100, Currency::Usd),
+            amount_in_ars: None,
+            exchange_rate: Some(Rate::parse("1180.5").unwrap()),
+            category_id,
+            date: NaiveDate::from_ymd_opt(2026, 5, 3).unwrap(),
+            r#type: TransactionType::Expense,
+            payee_id: None,
+            invoice: None,
+            splits: vec![
+                split(category_id, 33),
+                split(category_id, 33),
+                split(category_id, 34),
+            ],
+            tag_ids: None,
+        };
+        input.validate().unwrap();
+
+        let t = insert_transaction(&db, input, TransactionOrigin::default()).unwrap();
+        assert_eq!(t.amount_in_ars, Money::ars(118_050));
+        let shares: Vec<i64> = t.splits.iter().map(|s| s.amount_in_ars.cents).collect();
+        // 33% of 118_050 is 38_956.5; the last line takes what is left,
+        // one less than its own 34% would round to
+        assert_eq!(shares, [38_957, 38_957, 40_136]);
+        assert_eq!(prorate(118_050, 34, 100), 40_137);
+        assert_eq!(shares.iter().sum::<i64>(), t.amount_in_ars.cents);
+    }
+}
@@ -0,0 +1,297 @@
+use crate::attachments;
+use crate::commands::audit;
+use crate::commands::categories::{row_to_category, CATEGORY_COLUMNS};
+use crate::commands::transactions::query_transactions;
+use crate::commands::undo;
+use crate::models::{
+    timestamp, validate_trash_retention, AuditAction, AuditOrigin, Entity, PurgeSummary, Trash,
+    Trashed,
+};
+use crate::AppState;
+use rusqlite::{Connection, OptionalExtension};
+use std::collections::HashMap;
+use tauri::State;
+
+const RETENTION_KEY: &str = "trash_retention_days";
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
+fn retention_days(db: &Connection) -> Result<u32, String> {
+    let value: Option<String> = db
+        .query_row(
+            "SELECT value FROM settings WHERE key = ?1",
+            [RETENTION_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    Ok(value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_RETENTION_DAYS))
+}
+
+/// `deleted_at` limit for rows trashed more than `days` ago.
+fn cutoff(days: u32) -> Result<String, String> {
+    chrono::TimeDelta::try_days(days.into())
+        .and_then(|age| chrono::Utc::now().checked_sub_signed(age))
+        .map(timestamp)
+        .ok_or_else(|| format!("Antigüedad fuera de rango: {} días", days))
+}
+
+/// Permanently delete what was trashed before `before` (everything when
+/// `None`). Splits, tags and attachment rows go with their transactions;
+/// attachment files are left for `attachments::remove_orphans`.
+fn purge(
+    db: &mut Connection,
+    before: Option<&str>,
+    origin: AuditOrigin,
+) -> Result<PurgeSummary, String> {
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+
+    let mut stmt = tx
+        .prepare(
+            "SELECT id FROM transactions
+             WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)",
+        )
+        .map_err(|e| e.to_string())?;
+    let transaction_ids = stmt
+        .query_map([before], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+    undo::forget(&tx, Entity::Transaction, &transaction_ids)?;
+    audit::record_transactions(&tx, &transaction_ids, AuditAction::Purge, origin)?;
+
+    let transactions = tx
+        .execute(
+            "DELETE FROM transactions
+             WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)",
+            [before],
+        )
+        .map_err(|e| e.to_string())?;
+
+    // A category can only go once no transaction, even a trashed one, uses it
+    let mut stmt = tx
+        .prepare(
+            "SELECT id FROM categories c
+             WHERE deleted_at IS NOT NULL AND (?1 IS NULL OR deleted_at < ?1)
+               AND NOT EXISTS (SELECT 1 FROM transactions t WHERE t.category_id = c.id)
+               AND NOT EXISTS (SELECT 1 FROM transaction_splits s WHERE s.category_id = c.id)",
+        )
+        .map_err(|e| e.to_string())?;
+    let category_ids = stmt
+        .query_map([before], |row| row.get::<_, i64>(0))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    drop(stmt);
+    undo::forget(&tx, Entity::Category, &category_ids)?;
+
+    for &id in &category_ids {
+        audit::record_category(&tx, id, AuditAction::Purge, origin)?;
+        // Payees only suggest a category, so they just lose their default;
+        // rules let go through ON DELETE SET NULL
+        tx.execute(
+            "UPDATE payees SET default_category_id = NULL WHERE default_category_id = ?1",
+            [id],
+        )
+        .map_err(|e| e.to_string())?;
+        tx.execute("DELETE FROM categories WHERE id = ?1", [id])
+            .map_err(|e| e.to_string())?;
+    }
+
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(PurgeSummary {
+        transactions,
+        categories: category_ids.len(),
+        warnings: Vec::new(),
+    })
+}
+
+/// Purge what has been in the trash longer than the retention. Run at
+/// startup.
+pub(crate) fn purge_expired(db: &mut Connection) -> Result<PurgeSummary, String> {
+    match retention_days(db)? {
+        0 => Ok(PurgeSummary::default()),
+        days => purge(db, Some(&cutoff(days)?), AuditOrigin::Retention),
+    }
+}
+
+/// Deleted transactions and categories. Deleting only sets `deleted_at`;
+/// every other query leaves such rows out until they are restored or purged.
+#[tauri::command]
+pub fn list_trash(state: State<AppState>) -> Result<Trash, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut stmt = db
+        .prepare("SELECT id, deleted_at FROM transactions WHERE deleted_at IS NOT NULL")
+        .map_err(|e| e.to_string())?;
+    let mut deleted_at: HashMap<i64, String> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| e.to_string())?
+        .collect::<Result<_, _>>()
+        .map_err(|e| e.to_string())?;
+    let transactions = query_transactions(
+        &db,
+        "WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC, id DESC",
+        [],
+    )?
+    .into_iter()
+    .map(|t| Trashed {
+        deleted_at: deleted_at.remove(&t.id).unwrap_or_default(),
+        item: t,
+    })
+    .collect();
+
+    let mut stmt = db
+        .prepare(&format!(
+            "SELECT {}, deleted_at FROM categories
+             WHERE deleted_at IS NOT NULL
+             ORDER BY deleted_at DESC, id DESC",
+            CATEGORY_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let categories = stmt
+        .query_map([], |row| {
+            Ok(Trashed {
+                item: row_to_category(row)?,
+                deleted_at: row.get("deleted_at")?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(Trash {
+        transactions,
+        categories,
+        retention_days: retention_days(&db)?,
+    })
+}
+
+/// Take a row out of the trash. Restoring a transaction also restores the
+/// categories it uses.
+#[tauri::command]
+pub fn restore(state: State<AppState>, entity: Entity, id: i64) -> Result<(), String> {
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let mark = undo::mark(&tx)?;
+
+    let restored = match entity {
+        Entity::Transaction => {
+            let mut stmt = tx
+                .prepare(
+                    "SELECT id FROM categories
+                     WHERE deleted_at IS NOT NULL
+                       AND id IN (SELECT category_id FROM transactions WHERE id = ?1
+                                  UNION SELECT category_id FROM transaction_splits
+                                        WHERE transaction_id = ?1)",
+                )
+                .map_err(|e| e.to_string())?;
+            let category_ids = stmt
+                .query_map([id], |row| row.get::<_, i64>(0))
+                .map_err(|e| e.to_string())?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| e.to_string())?;
+            drop(stmt);
+            for &category_id in &category_ids {
+                tx.execute(
+                    "UPDATE categories SET deleted_at = NULL, version = version + 1 WHERE id = ?1",
+                    [category_id],
+                )
+                .map_err(|e| e.to_string())?;
+                audit::record_category(
+                    &tx,
+                    category_id,
+                    AuditAction::Restore,
+                    AuditOrigin::Manual,
+                )?;
+            }
+            tx.execute(
+                "UPDATE transactions SET deleted_at = NULL, version = version + 1
+                 WHERE id = ?1 AND deleted_at IS NOT NULL",
+                [id],
+            )
+            .map_err(|e| e.to_string())?
+        }
+        Entity::Category => tx
+            .execute(
+                "UPDATE categories SET deleted_at = NULL, version = version + 1
+                 WHERE id = ?1 AND deleted_at IS NOT NULL",
+                [id],
+            )
+            .map_err(|e| e.to_string())?,
+    };
+
+    if restored == 0 {
+        return Err(match entity {
+            Entity::Transaction => {
+                format!("La transacción con id {} no está en la papelera", id)
+            }
+            Entity::Category => format!("La categoría con id {} no está en la papelera", id),
+        });
+    }
+
+    match entity {
+        Entity::Transaction => {
+            audit::record_transactions(&tx, &[id], AuditAction::Restore, AuditOrigin::Manual)?
+        }
+        Entity::Category => {
+            audit::record_category(&tx, id, AuditAction::Restore, AuditOrigin::Manual)?
+        }
+    }
+    undo::push(&tx, mark, "Restaurar de la papelera")?;
+
+    tx.commit().map_err(|e| e.to_string())
+}
+
+/// Permanently delete what has been in the trash for more than
+/// `older_than` days, or everything when omitted.
+#[tauri::command]
+pub fn purge_trash(
+    state: State<AppState>,
+    older_than: Option<u32>,
+) -> Result<PurgeSummary, String> {
+    if let Some(days) = older_than {
+        validate_trash_retention(days)?;
+    }
+    let before = older_than.map(cutoff).transpose()?;
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let mut summary = purge(&mut db, before.as_deref(), AuditOrigin::Manual)?;
+
+    // Files of purged attachments; failing only leaves unused files behind
+    if let Err(e) = attachments::remove_orphans(&db, &state.attachments_dir) {
+        summary
+            .warnings
+            .push(format!("No se pudieron limpiar los adjuntos: {}", e));
+    }
+
+    Ok(summary)
+}
+
+#[tauri::command]
+pub fn get_trash_retention(state: State<AppState>) -> Result<u32, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+    retention_days(&db)
+}
+
+/// Set how many days rows stay in the trash before being purged at
+/// startup; 0 keeps them until purged by hand.
+#[tauri::command]
+pub fn set_trash_retention(state: State<AppState>, days: u32) -> Result<(), String> {
+    validate_trash_retention(days)?;
+
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    db.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        rusqlite::params![RETENTION_KEY, days.to_string()],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,478 @@
+use crate::commands::audit;
+use crate::commands::categories::{ensure_category_unused, fetch_category};
+use crate::commands::transactions::{query_transactions, write_transaction};
+use crate::models::{
+    timestamp_now, AuditAction, AuditEntry, AuditOrigin, Category, CategoryUpdate, Entity,
+    Transaction, TransactionInput, UndoStack, UndoStep,
+};
+use crate::AppState;
+use rusqlite::{Connection, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+/// Steps kept on the stack; older ones can no longer be undone.
+const MAX_UNDO_STEPS: u32 = 100;
+
+fn row_to_step(row: &Row) -> rusqlite::Result<UndoStep> {
+    Ok(UndoStep {
+        id: row.get(0)?,
+        label: row.get(1)?,
+        created_at: row.get(2)?,
+    })
+}
+
+/// Id of the last audit entry, to pass to `push` once the command is done.
+pub(crate) fn mark(db: &Connection) -> Result<i64, String> {
+    db.query_row("SELECT COALESCE(MAX(id), 0) FROM audit_log", [], |row| {
+        row.get(0)
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Make the changes audited since `since` one undo step. A new step drops
+/// whatever was undone and not redone, and the oldest steps beyond
+/// `MAX_UNDO_STEPS`.
+pub(crate) fn push(db: &Connection, since: i64, label: &str) -> Result<(), String> {
+    let last = mark(db)?;
+    if last == since {
+        return Ok(());
+    }
+
+    db.execute("DELETE FROM undo_stack WHERE undone = 1", [])
+        .map_err(|e| e.to_string())?;
+    db.execute(
+        "INSERT INTO undo_stack (label, first_entry, last_entry, undone, created_at)
+         VALUES (?1, ?2, ?3, 0, ?4)",
+        rusqlite::params![label, since + 1, last, timestamp_now()],
+    )
+    .map_err(|e| e.to_string())?;
+    db.execute(
+        "DELETE FROM undo_stack
+         WHERE id NOT IN (SELECT id FROM undo_stack ORDER BY id DESC LIMIT ?1)",
+        [MAX_UNDO_STEPS],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Drop the steps that changed any of `ids`, which are about to be deleted
+/// for good. They could never be undone again, and since `undo` always
+/// takes the newest step they would block every older one.
+pub(crate) fn forget(db: &Connection, entity: Entity, ids: &[i64]) -> Result<(), String> {
+    let mut stmt = db
+        .prepare_cached(
+            "DELETE FROM undo_stack WHERE EXISTS (
+                SELECT 1 FROM audit_log a
+                WHERE a.entity = ?1 AND a.entity_id = ?2
+                  AND a.id BETWEEN undo_stack.first_entry AND undo_stack.last_entry)",
+        )
+        .map_err(|e| e.to_string())?;
+    for &id in ids {
+        stmt.execute(rusqlite::params![entity.as_str(), id])
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn query_steps(
+    db: &Connection,
+    sql: &str,
+    limit: u32,
+) -> Result<Vec<(UndoStep, i64, i64)>, String> {
+    let mut stmt = db.prepare(sql).map_err(|e| e.to_string())?;
+    let steps = stmt
+        .query_map([limit], |row| {
+            Ok((row_to_step(row)?, row.get(3)?, row.get(4)?))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+    Ok(steps)
+}
+
+/// "la transacción 12", for error messages.
+fn describe(entity: Entity, id: i64) -> String {
+    match entity {
+        Entity::Transaction => format!("la transacción {}", id),
+        Entity::Category => format!("la categoría {}", id),
+    }
+}
+
+/// The part of a transaction undo compares and writes back: what the user
+/// can edit, plus the duplicate flag that reviewing an import clears.
+#[derive(Serialize, Deserialize)]
+struct TransactionState {
+    #[serde(flatten)]
+    input: TransactionInput,
+    duplicate_of: Option<i64>,
+}
+
+/// The state of a transaction snapshot; `None` stands for a row in the trash
+/// or gone.
+fn transaction_state(snapshot: Option<&Value>) -> Result<Option<Value>, String> {
+    snapshot
+        .map(|value| {
+            let t: Transaction =
+                serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            serde_json::to_value(TransactionState {
+                input: TransactionInput::from(&t),
+                duplicate_of: t.duplicate_of,
+            })
+            .map_err(|e| e.to_string())
+        })
+        .transpose()
+}
+
+fn category_state(snapshot: Option<&Value>) -> Result<Option<Value>, String> {
+    snapshot
+        .map(|value| {
+            let c: Category = serde_json::from_value(value.clone()).map_err(|e| e.to_string())?;
+            serde_json::to_value(CategoryUpdate {
+                name: c.name,
+                r#type: c.r#type,
+                icon: c.icon,
+                color: c.color,
+            })
+            .map_err(|e| e.to_string())
+        })
+        .transpose()
+}
+
+fn current_state(db: &Connection, entity: Entity, id: i64) -> Result<Option<Value>, String> {
+    match entity {
+        Entity::Transaction => {
+            let live = query_transactions(db, "WHERE id = ?1 AND deleted_at IS NULL", [id])?;
+            let snapshot = live
+                .first()
+                .map(|t| serde_json::to_value(t).map_err(|e| e.to_string()))
+                .transpose()?;
+            transaction_state(snapshot.as_ref())
+        }
+        Entity::Category => {
+            let live: bool = db
+                .query_row(
+                    "SELECT COUNT(*) > 0 FROM categories WHERE id = ?1 AND deleted_at IS NULL",
+                    [id],
+                    |row| row.get(0),
+                )
+                .map_err(|e| e.to_string())?;
+            if !live {
+                return Ok(None);
+            }
+            let snapshot =
+                serde_json::to_value(fetch_category(db, id)?).map_err(|e| e.to_string())?;
+            category_state(Some(&snapshot))
+        }
+    }
+}
+
+/// Take the row of `entry` from the `from` snapshot to the `to` one,
+/// recording the change with `origin`. Fails when the row no longer matches
+/// `from`, since something outside the stack changed it meanwhile.
+fn revert(
+    db: &Connection,
+    entry: &AuditEntry,
+    from: Option<&Value>,
+    to: Option<&Value>,
+    origin: AuditOrigin,
+) -> Result<(), String> {
+    let verb = match origin {
+        AuditOrigin::Redo => "rehacer",
+        _ => "deshacer",
+    };
+    let (id, entity) = (entry.entity_id, entry.entity);
+    let (from, to) = match entity {
+        Entity::Transaction => (transaction_state(from)?, transaction_state(to)?),
+        Entity::Category => (category_state(from)?, category_state(to)?),
+    };
+    if current_state(db, entity, id)? != from {
+        return Err(format!(
+            "No se puede {}: {} cambió después",
+            verb,
+            describe(entity, id)
+        ));
+    }
+
+    let now = timestamp_now();
+    match (entity, to) {
+        (Entity::Transaction, Some(state)) => {
+            let state: TransactionState =
+                serde_json::from_value(state).map_err(|e| e.to_string())?;
+            let before = query_transactions(db, "WHERE id = ?1", [id])?.pop();
+            // `write_transaction` bumps the version
+            db.execute(
+                "UPDATE transactions SET deleted_at = NULL, duplicate_of = ?2 WHERE id = ?1",
+                rusqlite::params![id, state.duplicate_of],
+            )
+            .map_err(|e| e.to_string())?;
+            if before.is_none() || !write_transaction(db, id, &state.input)? {
+                return Err(format!(
+                    "No se puede {}: {} ya no existe",
+                    verb,
+                    describe(entity, id)
+                ));
+            }
+            if from.is_none() {
+                audit::record_transactions(db, &[id], AuditAction::Restore, origin)
+            } else {
+                let after = query_transactions(db, "WHERE id = ?1", [id])?.pop();
+                audit::record(
+                    db,
+                    entity,
+                    id,
+                    AuditAction::Update,
+                    origin,
+                    before.as_ref(),
+                    after.as_ref(),
+                )
+            }
+        }
+        (Entity::Transaction, None) => {
+            db.execute(
+                "UPDATE transactions SET deleted_at = ?1, version = version + 1 WHERE id = ?2",
+                rusqlite::params![now, id],
+            )
+            .map_err(|e| e.to_string())?;
+            audit::record_transactions(db, &[id], AuditAction::Delete, origin)
+        }
+        (Entity::Category, Some(state)) => {
+            let category: CategoryUpdate =
+                serde_json::from_value(state).map_err(|e| e.to_string())?;
+            let before = fetch_category(db, id).ok();
+            let affected = db
+                .execute(
+                    "UPDATE categories SET name = ?1, type = ?2, icon = ?3, color = ?4,
+                     deleted_at = NULL, version = version + 1
+                     WHERE id = ?5",
+                    rusqlite::params![
+                        category.name,
+                        category.r#type,
+                        category.icon,
+                        category.color,
+                        id
+                    ],
+                )
+                .map_err(|e| e.to_string())?;
+            if affected == 0 {
+                return Err(format!(
+                    "No se puede {}: {} ya no existe",
+                    verb,
+                    describe(entity, id)
+                ));
+            }
+            if from.is_none() {
+                audit::record_category(db, id, AuditAction::Restore, origin)
+            } else {
+                audit::record(
+                    db,
+                    entity,
+                    id,
+                    AuditAction::Update,
+                    origin,
+                    before.as_ref(),
+                    Some(&fetch_category(db, id)?),
+                )
+            }
+        }
+        (Entity::Category, None) => {
+            ensure_category_unused(db, id)?;
+            db.execute(
+                "UPDATE categories SET deleted_at = ?1, version = version + 1 WHERE id = ?2",
+                rusqlite::params![now, id],
+            )
+            .map_err(|e| e.to_string())?;
+            audit::record_category(db, id, AuditAction::Delete, origin)
+        }
+    }
+}
+
+/// Revert the last `steps` commands (one by default), newest first. Every
+/// step is undone or none is.
+#[tauri::command]
+pub fn undo(state: State<AppState>, steps: Option<u32>) -> Result<Vec<UndoStep>, String> {
+    let steps = steps.unwrap_or(1).clamp(1, MAX_UNDO_STEPS);
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let undone = undo_steps(&tx, steps)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(undone)
+}
+
+/// The body of `undo`, to run inside a transaction.
+fn undo_steps(db: &Connection, steps: u32) -> Result<Vec<UndoStep>, String> {
+    let pending = query_steps(
+        db,
+        "SELECT id, label, created_at, first_entry, last_entry FROM undo_stack
+         WHERE undone = 0 ORDER BY id DESC LIMIT ?1",
+        steps,
+    )?;
+    if pending.is_empty() {
+        return Err("No hay nada para deshacer".into());
+    }
+
+    for (step, first, last) in &pending {
+        for entry in audit::entries_between(db, *first, *last)?.iter().rev() {
+            revert(
+                db,
+                entry,
+                entry.after.as_ref(),
+                entry.before.as_ref(),
+                AuditOrigin::Undo,
+            )?;
+        }
+        db.execute("UPDATE undo_stack SET undone = 1 WHERE id = ?1", [step.id])
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(pending.into_iter().map(|(step, _, _)| step).collect())
+}
+
+/// Apply again the last `steps` undone commands (one by default), in the
+/// order they were first made. Any new change empties what can be redone.
+#[tauri::command]
+pub fn redo(state: State<AppState>, steps: Option<u32>) -> Result<Vec<UndoStep>, String> {
+    let steps = steps.unwrap_or(1).clamp(1, MAX_UNDO_STEPS);
+
+    let mut db = state.db.lock().map_err(|e| e.to_string())?;
+    let tx = db.transaction().map_err(|e| e.to_string())?;
+    let redone = redo_steps(&tx, steps)?;
+    tx.commit().map_err(|e| e.to_string())?;
+
+    Ok(redone)
+}
+
+/// The body of `redo`, to run inside a transaction.
+fn redo_steps(db: &Connection, steps: u32) -> Result<Vec<UndoStep>, String> {
+    let pending = query_steps(
+        db,
+        "SELECT id, label, created_at, first_entry, last_entry FROM undo_stack
+         WHERE undone = 1 ORDER BY id LIMIT ?1",
+        steps,
+    )?;
+    if pending.is_empty() {
+        return Err("No hay nada para rehacer".into());
+    }
+
+    for (step, first, last) in &pending {
+        for entry in audit::entries_between(db, *first, *last)? {
+            revert(
+                db,
+                &entry,
+                entry.before.as_ref(),
+                entry.after.as_ref(),
+                AuditOrigin::Redo,
+            )?;
+        }
+        db.execute("UPDATE undo_stack SET undone = 0 WHERE id = ?1", [step.id])
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(pending.into_iter().map(|(step, _, _)| step).collect())
+}
+
+/// What `undo` and `redo` would act on, for menus and tooltips.
+#[tauri::command]
+pub fn get_undo_stack(state: State<AppState>) -> Result<UndoStack, String> {
+    let db = state.db.lock().map_err(|e| e.to_string())?;
+
+    let steps = |sql: &str| {
+        query_steps(&db, sql, MAX_UNDO_STEPS)
+            .map(|steps| steps.into_iter().map(|(step, _, _)| step).collect())
+    };
+
+    Ok(UndoStack {
+        undo: steps(
+            "SELECT id, label, created_at, first_entry, last_entry FROM undo_stack
+             WHERE undone = 0 ORDER BY id DESC LIMIT ?1",
+        )?,
+        redo: steps(
+            "SELECT id, label, created_at, first_entry, last_entry FROM undo_stack
+             WHERE undone = 1 ORDER BY id LIMIT ?1",
+        )?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::import::resolve_flagged;
+    use crate::commands::transactions::{fetch_transaction, insert_transaction, trash_transaction};
+    use crate::db;
+    use crate::models::{TransactionOrigin, TransactionType};
+    use crate::money::Money;
+    use chrono::NaiveDate;
+
+    fn insert(db: &Connection, description: &str, duplicate_of: Option<i64>) -> i64 {
+        let category_id = db
+            .query_row(
+                "SELECT id FROM categories WHERE type = 'expense' LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap();
+        let input = TransactionInput {
+            description: description.into(),
+            amount: Money::ars(150_000),
+            amount_in_ars: None,
+            exchange_rate: None,
+            category_id,
+            date: NaiveDate::from_ymd_opt(2026, 5, 3).unwrap(),
+            r#type: TransactionType::Expense,
+            payee_id: None,
+            invoice: None,
+            splits: Vec::new(),
+            tag_ids: None,
+        };
+        let origin = TransactionOrigin {
+            duplicate_of,
+            ..Default::default()
+        };
+        insert_transaction(db, input, origin).unwrap().id
+    }
+
+    fn flag(db: &Connection, id: i64) -> Option<i64> {
+        fetch_transaction(db, id).unwrap().duplicate_of
+    }
+
+    #[test]
+    fn keeping_a_duplicate_can_be_undone() {
+        let db = Connection::open_in_memory().unwrap();
+        db::migrate(&db).unwrap();
+        let original = insert(&db, "Coto", None);
+        let flagged = insert(&db, "COTO 1234", Some(original));
+
+        resolve_flagged(&db, flagged, true).unwrap();
+        assert_eq!(flag(&db, flagged), None);
+
+        undo_steps(&db, 1).unwrap();
+        assert_eq!(flag(&db, flagged), Some(original));
+        redo_steps(&db, 1).unwrap();
+        assert_eq!(flag(&db, flagged), None);
+    }
+
+    #[test]
+    fn undoing_a_deletion_flags_its_duplicates_again() {
+        let db = Connection::open_in_memory().unwrap();
+        db::migrate(&db).unwrap();
+        let original = insert(&db, "Coto", None);
+        let flagged = insert(&db, "COTO 1234", Some(original));
+
+        let since = mark(&db).unwrap();
+        assert!(trash_transaction(&db, original, AuditOrigin::Manual).unwrap());
+        audit::record_transactions(&db, &[original], AuditAction::Delete, AuditOrigin::Manual)
+            .unwrap();
+        push(&db, since, "Eliminar transacción").unwrap();
+        assert_eq!(flag(&db, flagged), None);
+
+        undo_steps(&db, 1).unwrap();
+        assert!(fetch_transaction(&db, original).is_ok());
+        assert_eq!(flag(&db, flagged), Some(original));
+        redo_steps(&db, 1).unwrap();
+        assert!(fetch_transaction(&db, original).is_err());
+        assert_eq!(flag(&db, flagged), None);
+    }
+}
@@ -1,3 +1,7 @@
+use crate::dedup;
+use crate::models::{calendar_date, normalize_timestamp};
+use crate::money::Currency;
+use chrono::NaiveDate;
 use rusqlite::{Connection, Result};
 use tauri::Manager;
 
@@ -12,6 +16,14 @@ pub fn init_db(app: &tauri::App) -> Result<Connection, Box<dyn std::error::Error
     // Enable WAL mode for better concurrent read performance
     conn.execute_batch("PRAGMA journal_mode=WAL;")?;
 
+    migrate(&conn)?;
+
+    Ok(conn)
+}
+
+/// Bring an open database up to the current schema: enforce foreign keys,
+/// create or upgrade the tables and seed default categories.
+pub(crate) fn migrate(conn: &Connection) -> Result<()> {
     // Enforce foreign key constraints (SQLite does NOT enforce them by default)
     conn.execute_batch("PRAGMA foreign_keys = ON;")?;
 
@@ -31,25 +43,362 @@ pub fn init_db(app: &tauri::App) -> Result<Connection, Box<dyn std::error::Error
             amount INTEGER NOT NULL,
             amount_in_ars INTEGER NOT NULL,
             currency TEXT NOT NULL CHECK(currency IN ('ARS', 'USD')),
-            exchange_rate REAL,
+            exchange_rate INTEGER,
             category_id INTEGER NOT NULL,
             date TEXT NOT NULL,
             type TEXT NOT NULL CHECK(type IN ('income', 'expense')),
-            created_at TEXT NOT NULL DEFAULT (datetime('now')),
-            updated_at TEXT NOT NULL DEFAULT (datetime('now')),
+            account TEXT,
+            external_id TEXT,
+            fingerprint TEXT,
+            import_batch_id INTEGER REFERENCES import_batches(id),
+            duplicate_of INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
             FOREIGN KEY (category_id) REFERENCES categories(id)
         );
 
-        CREATE INDEX IF NOT EXISTS idx_transactions_date ON transactions(date);",
+        CREATE INDEX IF NOT EXISTS idx_transactions_date ON transactions(date);
+
+        CREATE TABLE IF NOT EXISTS import_batches (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            source TEXT NOT NULL,
+            file_name TEXT,
+            imported INTEGER NOT NULL DEFAULT 0,
+            skipped INTEGER NOT NULL DEFAULT 0,
+            flagged INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS transaction_splits (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            category_id INTEGER NOT NULL REFERENCES categories(id),
+            amount INTEGER NOT NULL,
+            amount_in_ars INTEGER NOT NULL,
+            note TEXT
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_transaction_splits_transaction
+            ON transaction_splits(transaction_id);
+        CREATE INDEX IF NOT EXISTS idx_transaction_splits_category
+            ON transaction_splits(category_id);
+
+        CREATE TABLE IF NOT EXISTS tags (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS transaction_tags (
+            transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (transaction_id, tag_id)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_transaction_tags_tag ON transaction_tags(tag_id);
+
+        CREATE TABLE IF NOT EXISTS attachments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            transaction_id INTEGER NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+            file_name TEXT NOT NULL,
+            mime_type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            hash TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_attachments_transaction ON attachments(transaction_id);
+        CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(hash);
+
+        CREATE TABLE IF NOT EXISTS payees (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL UNIQUE COLLATE NOCASE,
+            default_category_id INTEGER REFERENCES categories(id),
+            notes TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS payee_aliases (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            payee_id INTEGER NOT NULL REFERENCES payees(id) ON DELETE CASCADE,
+            alias TEXT NOT NULL,
+            key TEXT NOT NULL UNIQUE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_payee_aliases_payee ON payee_aliases(payee_id);
+
+        CREATE TABLE IF NOT EXISTS rules (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            priority INTEGER NOT NULL DEFAULT 0,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            pattern TEXT,
+            pattern_kind TEXT NOT NULL DEFAULT 'contains',
+            min_amount INTEGER,
+            max_amount INTEGER,
+            currency TEXT,
+            account TEXT,
+            category_id INTEGER REFERENCES categories(id) ON DELETE SET NULL,
+            payee_id INTEGER REFERENCES payees(id) ON DELETE SET NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS rule_tags (
+            rule_id INTEGER NOT NULL REFERENCES rules(id) ON DELETE CASCADE,
+            tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+            PRIMARY KEY (rule_id, tag_id)
+        );
+
+        CREATE TABLE IF NOT EXISTS settings (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            entity TEXT NOT NULL,
+            entity_id INTEGER NOT NULL,
+            action TEXT NOT NULL,
+            origin TEXT NOT NULL,
+            before TEXT,
+            after TEXT,
+            created_at TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS idx_audit_log_entity ON audit_log(entity, entity_id);
+
+        CREATE TABLE IF NOT EXISTS undo_stack (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            label TEXT NOT NULL,
+            first_entry INTEGER NOT NULL,
+            last_entry INTEGER NOT NULL,
+            undone INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL
+        );",
+    )?;
+
+    // Columns added after the first release; CREATE TABLE IF NOT EXISTS
+    // leaves existing databases untouched, so add them explicitly.
+    add_column_if_missing(conn, "transactions", "account", "TEXT")?;
+    add_column_if_missing(conn, "transactions", "external_id", "TEXT")?;
+    add_column_if_missing(conn, "transactions", "fingerprint", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "transactions",
+        "import_batch_id",
+        "INTEGER REFERENCES import_batches(id)",
+    )?;
+    add_column_if_missing(conn, "transactions", "duplicate_of", "INTEGER")?;
+    add_column_if_missing(
+        conn,
+        "transactions",
+        "payee_id",
+        "INTEGER REFERENCES payees(id)",
+    )?;
+    add_column_if_missing(conn, "transactions", "invoice_number", "TEXT")?;
+    add_column_if_missing(conn, "transactions", "issuer_cuit", "TEXT")?;
+    add_column_if_missing(conn, "transactions", "deleted_at", "TEXT")?;
+    add_column_if_missing(conn, "categories", "deleted_at", "TEXT")?;
+    add_column_if_missing(
+        conn,
+        "transactions",
+        "version",
+        "INTEGER NOT NULL DEFAULT 1",
+    )?;
+    add_column_if_missing(conn, "categories", "version", "INTEGER NOT NULL DEFAULT 1")?;
+
+    conn.execute_batch(
+        "CREATE INDEX IF NOT EXISTS idx_transactions_external_id ON transactions(external_id);
+        CREATE INDEX IF NOT EXISTS idx_transactions_fingerprint ON transactions(fingerprint);
+        CREATE INDEX IF NOT EXISTS idx_transactions_import_batch ON transactions(import_batch_id);
+        CREATE INDEX IF NOT EXISTS idx_transactions_payee ON transactions(payee_id);
+        CREATE INDEX IF NOT EXISTS idx_transactions_issuer_cuit ON transactions(issuer_cuit);
+        CREATE INDEX IF NOT EXISTS idx_transactions_deleted_at ON transactions(deleted_at);",
     )?;
 
+    store_rates_as_millionths(conn)?;
+    drop_peso_rates(conn)?;
+    normalize_dates(conn)?;
+    normalize_timestamps(conn)?;
+    backfill_fingerprints(conn)?;
+
     // Seed default categories if table is empty
     let count: i64 = conn.query_row("SELECT COUNT(*) FROM categories", [], |row| row.get(0))?;
     if count == 0 {
-        seed_default_categories(&conn)?;
+        seed_default_categories(conn)?;
     }
 
-    Ok(conn)
+    Ok(())
+}
+
+/// Turn the `REAL` exchange rates of older versions into the `INTEGER`
+/// millionths of `money::Rate`. SQLite cannot change a column's type, and a
+/// `REAL` column would keep converting integers back to floats, so the
+/// column is replaced.
+fn store_rates_as_millionths(conn: &Connection) -> Result<()> {
+    let declared: String = conn.query_row(
+        "SELECT type FROM pragma_table_info('transactions') WHERE name = 'exchange_rate'",
+        [],
+        |row| row.get(0),
+    )?;
+    if declared.eq_ignore_ascii_case("INTEGER") {
+        return Ok(());
+    }
+
+    conn.execute_batch(
+        "BEGIN;
+        ALTER TABLE transactions ADD COLUMN exchange_rate_millionths INTEGER;
+        UPDATE transactions
+            SET exchange_rate_millionths = CAST(ROUND(exchange_rate * 1000000) AS INTEGER)
+            WHERE exchange_rate IS NOT NULL;
+        ALTER TABLE transactions DROP COLUMN exchange_rate;
+        ALTER TABLE transactions RENAME COLUMN exchange_rate_millionths TO exchange_rate;
+        COMMIT;",
+    )
+}
+
+/// Clear the exchange rate older versions kept on some peso transactions,
+/// which would otherwise fail validation on every edit or undo.
+fn drop_peso_rates(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "UPDATE transactions SET exchange_rate = NULL
+         WHERE currency = 'ARS' AND exchange_rate IS NOT NULL",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Rewrite transaction dates that are not a real `YYYY-MM-DD` day. Older
+/// versions stored any string the frontend sent, usually a UTC timestamp,
+/// which becomes its local day; what cannot be read takes the day the row
+/// was created.
+fn normalize_dates(conn: &Connection) -> Result<()> {
+    // SQLite's date() rewrites anything but a valid day (2026-02-30 becomes
+    // 2026-03-02), so this finds every malformed row
+    let mut stmt =
+        conn.prepare("SELECT id, date, created_at FROM transactions WHERE date(date) IS NOT date")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (id, date, created_at) in rows {
+        let day = calendar_date(&date)
+            .or_else(|| created_at.as_deref().and_then(calendar_date))
+            .unwrap_or_else(|| chrono::Local::now().date_naive());
+        conn.execute(
+            "UPDATE transactions SET date = ?1 WHERE id = ?2",
+            rusqlite::params![day, id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Every column holding a moment, in the format of `models::timestamp`.
+const TIMESTAMP_COLUMNS: [(&str, &str); 13] = [
+    ("transactions", "created_at"),
+    ("transactions", "updated_at"),
+    ("transactions", "deleted_at"),
+    ("categories", "deleted_at"),
+    ("import_batches", "created_at"),
+    ("tags", "created_at"),
+    ("attachments", "created_at"),
+    ("payees", "created_at"),
+    ("payees", "updated_at"),
+    ("rules", "created_at"),
+    ("rules", "updated_at"),
+    ("audit_log", "created_at"),
+    ("undo_stack", "created_at"),
+];
+
+/// Rewrite timestamps stored by older versions, which mixed SQLite's
+/// `datetime('now')` with RFC 3339 in other offsets and precisions, so they
+/// compare correctly as text. Unreadable values are left as they are.
+fn normalize_timestamps(conn: &Connection) -> Result<()> {
+    for (table, column) in TIMESTAMP_COLUMNS {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT rowid, {column} FROM {table}
+             WHERE {column} IS NOT NULL AND {column} NOT GLOB
+               '[0-9][0-9][0-9][0-9]-[0-9][0-9]-[0-9][0-9]T[0-9][0-9]:[0-9][0-9]:[0-9][0-9].[0-9][0-9][0-9]Z'"
+        ))?;
+        let rows = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<Result<Vec<_>>>()?;
+
+        for (rowid, raw) in rows {
+            if let Some(normalized) = normalize_timestamp(&raw) {
+                conn.execute(
+                    &format!("UPDATE {table} SET {column} = ?1 WHERE rowid = ?2"),
+                    rusqlite::params![normalized, rowid],
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Compute fingerprints for rows stored before they existed.
+fn backfill_fingerprints(conn: &Connection) -> Result<()> {
+    let mut stmt = conn.prepare(
+        "SELECT id, date, amount, currency, description FROM transactions
+         WHERE fingerprint IS NULL",
+    )?;
+    let rows = stmt
+        .query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let date: NaiveDate = row.get(1)?;
+            let amount: i64 = row.get(2)?;
+            let currency: Currency = row.get(3)?;
+            let description: String = row.get(4)?;
+            Ok((id, dedup::fingerprint(date, amount, currency, &description)))
+        })?
+        .collect::<Result<Vec<_>>>()?;
+
+    for (id, fingerprint) in rows {
+        conn.execute(
+            "UPDATE transactions SET fingerprint = ?1 WHERE id = ?2",
+            rusqlite::params![fingerprint, id],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Add `column` to `table` unless it already exists.
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    let exists: bool = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1",
+            table
+        ),
+        [column],
+        |row| row.get(0),
+    )?;
+
+    if !exists {
+        conn.execute_batch(&format!(
+            "ALTER TABLE {} ADD COLUMN {} {};",
+            table, column, definition
+        ))?;
+    }
+
+    Ok(())
 }
 
 fn seed_default_categories(conn: &Connection) -> Result<()> {
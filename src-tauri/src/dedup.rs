@@ -0,0 +1,297 @@
+//! Duplicate detection helpers shared by the importers and manual entry.
+//!
+//! A fingerprint identifies "the same movement" regardless of where it came
+//! from: calendar day, amount in centavos, currency and the set of words in
+//! the description (lowercased, without accents, numbers or punctuation, so
+//! "COTO 1234" and "Coto" match).
+
+use crate::import::normalize_text;
+use crate::models::{Period, TransactionType};
+use crate::money::Currency;
+use chrono::{Duration, NaiveDate};
+use rusqlite::Connection;
+use std::collections::HashMap;
+
+/// Days apart two movements can be and still count as a probable duplicate
+/// (bank posting dates often lag the purchase by a day or two).
+pub const PROBABLE_DUPLICATE_DAYS: i64 = 3;
+
+/// The same window for a transaction entered by hand, whose date is the
+/// day the user chose rather than a posting date.
+pub const MANUAL_DUPLICATE_DAYS: i64 = 1;
+
+/// Minimum description similarity for a probable duplicate.
+pub const PROBABLE_DUPLICATE_SIMILARITY: f64 = 0.3;
+
+/// Distinct, sorted words of a description that are meaningful for matching.
+pub fn description_tokens(description: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = normalize_text(description)
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 2 && !t.chars().all(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .collect();
+    tokens.sort();
+    tokens.dedup();
+    tokens
+}
+
+/// Normalized key of a movement; equal fingerprints are exact duplicates.
+pub fn fingerprint(
+    date: NaiveDate,
+    amount_cents: i64,
+    currency: Currency,
+    description: &str,
+) -> String {
+    format!(
+        "{}|{}|{}|{}",
+        date,
+        amount_cents,
+        currency,
+        description_tokens(description).join(" ")
+    )
+}
+
+/// Jaccard similarity of the description words, from 0.0 to 1.0. Two empty
+/// descriptions are considered identical.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let a = description_tokens(a);
+    let b = description_tokens(b);
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    let shared = a.iter().filter(|t| b.contains(t)).count();
+    let total = a.len() + b.len() - shared;
+    shared as f64 / total as f64
+}
+
+/// The fields of an incoming movement needed to look for duplicates.
+pub struct DuplicateProbe<'a> {
+    pub external_id: Option<&'a str>,
+    pub account: Option<&'a str>,
+    pub date: NaiveDate,
+    pub amount_cents: i64,
+    pub currency: Currency,
+    pub r#type: TransactionType,
+    pub description: &'a str,
+    /// Import the movement belongs to; rows of the same batch are never
+    /// compared with each other, since repeated rows in one file are real
+    pub batch_id: Option<i64>,
+}
+
+/// How an incoming movement relates to the stored transactions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateCheck {
+    New,
+    /// Same external id, or same fingerprint as a row outside this batch
+    Exact,
+    /// Resembles this stored transaction closely enough to need review
+    Probable(i64),
+}
+
+/// Classify an incoming movement against what is already stored.
+pub fn check(db: &Connection, probe: &DuplicateProbe) -> Result<DuplicateCheck, String> {
+    if let Some(external_id) = probe.external_id {
+        let exists: bool = db
+            .query_row(
+                "SELECT COUNT(*) > 0 FROM transactions
+                 WHERE external_id = ?1 AND IFNULL(account, '') = IFNULL(?2, '')
+                   AND deleted_at IS NULL",
+                rusqlite::params![external_id, probe.account],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        if exists {
+            return Ok(DuplicateCheck::Exact);
+        }
+    }
+
+    let key = fingerprint(
+        probe.date,
+        probe.amount_cents,
+        probe.currency,
+        probe.description,
+    );
+    let exact: bool = db
+        .query_row(
+            "SELECT COUNT(*) > 0 FROM transactions
+             WHERE fingerprint = ?1 AND deleted_at IS NULL
+               AND (?2 IS NULL OR import_batch_id IS NULL OR import_batch_id != ?2)",
+            rusqlite::params![key, probe.batch_id],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+    if exact {
+        return Ok(DuplicateCheck::Exact);
+    }
+
+    Ok(find_similar(db, probe, PROBABLE_DUPLICATE_DAYS)?
+        .first()
+        .map_or(DuplicateCheck::New, |&(id, _)| DuplicateCheck::Probable(id)))
+}
+
+/// Stored transactions with the same amount, currency and type, dated at
+/// most `max_days` apart from the probe and with a similar description,
+/// best match first.
+pub fn find_similar(
+    db: &Connection,
+    probe: &DuplicateProbe,
+    max_days: i64,
+) -> Result<Vec<(i64, f64)>, String> {
+    let window = Duration::days(max_days);
+    let from = probe
+        .date
+        .checked_sub_signed(window)
+        .unwrap_or(NaiveDate::MIN);
+    let to = probe
+        .date
+        .checked_add_signed(window)
+        .unwrap_or(NaiveDate::MAX);
+
+    let mut stmt = db
+        .prepare(
+            "SELECT id, description FROM transactions
+             WHERE amount = ?1 AND currency = ?2 AND type = ?3 AND deleted_at IS NULL
+               AND date BETWEEN ?4 AND ?5
+               AND (?6 IS NULL OR import_batch_id IS NULL OR import_batch_id != ?6)",
+        )
+        .map_err(|e| e.to_string())?;
+    let candidates = stmt
+        .query_map(
+            rusqlite::params![
+                probe.amount_cents,
+                probe.currency,
+                probe.r#type,
+                from,
+                to,
+                probe.batch_id
+            ],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?)),
+        )
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut matches: Vec<(i64, f64)> = candidates
+        .into_iter()
+        .map(|(id, description)| (id, similarity(probe.description, &description)))
+        .filter(|&(_, score)| score >= PROBABLE_DUPLICATE_SIMILARITY)
+        .collect();
+    matches.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+
+    Ok(matches)
+}
+
+/// A stored row as seen by `sweep`.
+struct SweepRow {
+    id: i64,
+    day: NaiveDate,
+    description: String,
+    batch_id: Option<i64>,
+}
+
+/// Pairs of stored transactions dated within `period` that look like the
+/// same movement, by the same rules as `find_similar`. Returns
+/// `(original id, duplicate id, similarity)` with the older row first; rows
+/// of the same import are never paired.
+pub fn sweep(db: &Connection, period: &Period) -> Result<Vec<(i64, i64, f64)>, String> {
+    let mut stmt = db
+        .prepare(
+            "SELECT id, date, amount, currency, type, description, import_batch_id
+             FROM transactions WHERE deleted_at IS NULL",
+        )
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, NaiveDate>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, Currency>(3)?,
+                row.get::<_, TransactionType>(4)?,
+                row.get::<_, String>(5)?,
+                row.get::<_, Option<i64>>(6)?,
+            ))
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| e.to_string())?;
+
+    // Only movements with the same amount, currency and type can match
+    let mut groups: HashMap<(i64, Currency, TransactionType), Vec<SweepRow>> = HashMap::new();
+    for (id, day, amount, currency, r#type, description, batch_id) in rows {
+        if !period.contains(day) {
+            continue;
+        }
+        groups
+            .entry((amount, currency, r#type))
+            .or_default()
+            .push(SweepRow {
+                id,
+                day,
+                description,
+                batch_id,
+            });
+    }
+
+    let mut pairs = Vec::new();
+    for group in groups.values_mut() {
+        group.sort_by_key(|r| (r.day, r.id));
+        for (i, a) in group.iter().enumerate() {
+            for b in &group[i + 1..] {
+                if (b.day - a.day).num_days() > PROBABLE_DUPLICATE_DAYS {
+                    break;
+                }
+                if a.batch_id.is_some() && a.batch_id == b.batch_id {
+                    continue;
+                }
+                let score = similarity(&a.description, &b.description);
+                if score >= PROBABLE_DUPLICATE_SIMILARITY {
+                    pairs.push((a.id.min(b.id), a.id.max(b.id), score));
+                }
+            }
+        }
+    }
+    pairs.sort_by_key(|&(original, duplicate, _)| (original, duplicate));
+
+    Ok(pairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_ignore_case_accents_numbers_and_order() {
+        assert_eq!(
+            description_tokens("COTO 1234 - Café, café y Más"),
+            ["cafe", "coto", "mas"]
+        );
+    }
+
+    #[test]
+    fn fingerprint_matches_the_same_movement() {
+        let date = NaiveDate::from_ymd_opt(2026, 5, 3).unwrap();
+        let key = fingerprint(date, 150_050, Currency::Ars, "COTO 1234 Palermo");
+        assert_eq!(key, "2026-05-03|150050|ARS|coto palermo");
+        assert_eq!(
+            key,
+            fingerprint(date, 150_050, Currency::Ars, "palermo, Coto")
+        );
+        assert_ne!(
+            key,
+            fingerprint(date, 150_050, Currency::Usd, "COTO 1234 Palermo")
+        );
+    }
+
+    #[test]
+    fn similarity_is_jaccard_of_the_words() {
+        assert_eq!(similarity("Café Martínez", "cafe martinez"), 1.0);
+        assert_eq!(
+            similarity("cafe martinez centro", "Café Martínez"),
+            2.0 / 3.0
+        );
+        assert_eq!(similarity("Uber", "Cabify"), 0.0);
+        assert_eq!(similarity("", "1234"), 1.0);
+        assert_eq!(similarity("", "uber"), 0.0);
+    }
+}
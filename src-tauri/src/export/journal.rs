@@ -0,0 +1,307 @@
+//! Plain-text accounting journals for ledger-cli/hledger and beancount.
+//!
+//! Categories become `Expenses:<name>` / `Income:<name>` accounts (one
+//! posting per line for split transactions) and the money side of every entry goes to `Assets:<account>` (or `Assets:Efectivo`
+//! when the transaction has no account). Entries are posted in their original
+//! currency and each USD exchange rate becomes a price directive, so the tools
+//! can value everything in ARS. Payees become the beancount payee string and
+//! a `Payee:` tag in ledger. Attachments are listed as comments with
+//! their file name and hash, which locates them in the backup or the
+//! attachment folder. Output depends only on the data: entries are
+//! sorted by date and id and no timestamps are written, so successive exports
+//! diff cleanly.
+
+use crate::models::{Attachment, Category, JournalFormat, Payee, Transaction, TransactionType};
+use crate::money::{format_cents, Currency, Rate};
+use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+
+const DEFAULT_ASSET_ACCOUNT: &str = "Efectivo";
+
+/// One journal entry, already resolved to account names.
+struct Entry<'a> {
+    date: NaiveDate,
+    transaction: &'a Transaction,
+    /// Category account and amount in centavos, one per split line
+    category_postings: Vec<(String, i64)>,
+    asset_account: String,
+    payee: Option<&'a str>,
+}
+
+/// Render the journal.
+pub fn render(
+    format: JournalFormat,
+    categories: &[Category],
+    payees: &[Payee],
+    transactions: &[Transaction],
+) -> String {
+    let category_accounts: HashMap<i64, String> = categories
+        .iter()
+        .map(|c| (c.id, category_account(c)))
+        .collect();
+    let payee_names: HashMap<i64, &str> = payees.iter().map(|p| (p.id, p.name.as_str())).collect();
+
+    let mut entries: Vec<Entry> = Vec::new();
+    for t in transactions {
+        let account_for = |category_id: i64| {
+            category_accounts
+                .get(&category_id)
+                .cloned()
+                .unwrap_or_else(|| top_level(t.r#type).to_string() + ":Sin-categoria")
+        };
+        let category_postings = if t.splits.is_empty() {
+            vec![(account_for(t.category_id), t.amount.cents)]
+        } else {
+            t.splits
+                .iter()
+                .map(|s| (account_for(s.category_id), s.amount.cents))
+                .collect()
+        };
+        let asset_account = format!(
+            "Assets:{}",
+            account_component(t.account.as_deref().unwrap_or(DEFAULT_ASSET_ACCOUNT))
+        );
+        entries.push(Entry {
+            date: t.date,
+            transaction: t,
+            category_postings,
+            asset_account,
+            payee: t.payee_id.and_then(|id| payee_names.get(&id).copied()),
+        });
+    }
+    entries.sort_by_key(|e| (e.date, e.transaction.id));
+
+    // Distinct (date, rate) pairs of foreign-currency transactions
+    let prices: BTreeSet<(NaiveDate, String, String)> = entries
+        .iter()
+        .filter(|e| e.transaction.currency() != Currency::Ars)
+        .filter_map(|e| {
+            let rate = e.transaction.exchange_rate?;
+            Some((
+                e.date,
+                e.transaction.currency().to_string(),
+                format_rate(rate),
+            ))
+        })
+        .collect();
+
+    let mut out = String::new();
+    match format {
+        JournalFormat::Ledger => write_ledger(&mut out, &entries, &prices),
+        JournalFormat::Beancount => write_beancount(&mut out, &entries, &prices),
+    }
+
+    out
+}
+
+fn write_ledger(
+    out: &mut String,
+    entries: &[Entry],
+    prices: &BTreeSet<(NaiveDate, String, String)>,
+) {
+    let _ = writeln!(out, "; Exportado desde TuBalance");
+    if !prices.is_empty() {
+        let _ = writeln!(out);
+    }
+    for (date, currency, rate) in prices {
+        let _ = writeln!(
+            out,
+            "P {} {} {} ARS",
+            date.format("%Y-%m-%d"),
+            currency,
+            rate
+        );
+    }
+
+    for e in entries {
+        let t = e.transaction;
+        let sign = category_sign(t);
+        let _ = writeln!(out);
+        let _ = writeln!(
+            out,
+            "{} {}",
+            e.date.format("%Y-%m-%d"),
+            single_line(&t.description)
+        );
+        let _ = writeln!(out, "    ; tubalance_id: {}", t.id);
+        if let Some(payee) = e.payee {
+            let _ = writeln!(out, "    ; Payee: {}", single_line(payee));
+        }
+        for a in &t.attachments {
+            let _ = writeln!(out, "    ; {}", attachment_comment(a));
+        }
+        for (account, cents) in &e.category_postings {
+            let _ = writeln!(
+                out,
+                "    {}  {} {}",
+                account,
+                format_cents(sign * cents),
+                t.currency()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "    {}  {} {}",
+            e.asset_account,
+            format_cents(-sign * t.amount.cents),
+            t.currency()
+        );
+    }
+}
+
+fn write_beancount(
+    out: &mut String,
+    entries: &[Entry],
+    prices: &BTreeSet<(NaiveDate, String, String)>,
+) {
+    let _ = writeln!(out, "; Exportado desde TuBalance");
+    let _ = writeln!(out, "option \"operating_currency\" \"ARS\"");
+
+    // Beancount requires every account to be opened before its first use
+    let mut opened: BTreeMap<&str, NaiveDate> = BTreeMap::new();
+    for e in entries {
+        let accounts = e.category_postings.iter().map(|(account, _)| account);
+        for account in accounts.chain([&e.asset_account]) {
+            opened
+                .entry(account.as_str())
+                .and_modify(|d| *d = (*d).min(e.date))
+                .or_insert(e.date);
+        }
+    }
+    if !opened.is_empty() {
+        let _ = writeln!(out);
+    }
+    for (account, date) in &opened {
+        let _ = writeln!(out, "{} open {}", date.format("%Y-%m-%d"), account);
+    }
+
+    if !prices.is_empty() {
+        let _ = writeln!(out);
+    }
+    for (date, currency, rate) in prices {
+        let _ = writeln!(
+            out,
+            "{} price {} {} ARS",
+            date.format("%Y-%m-%d"),
+            currency,
+            rate
+        );
+    }
+
+    for e in entries {
+        let t = e.transaction;
+        let sign = category_sign(t);
+        let _ = writeln!(out);
+        let payee = match e.payee {
+            Some(payee) => format!("\"{}\" ", beancount_string(payee)),
+            None => String::new(),
+        };
+        let _ = writeln!(
+            out,
+            "{} * {}\"{}\"",
+            e.date.format("%Y-%m-%d"),
+            payee,
+            beancount_string(&t.description)
+        );
+        let _ = writeln!(out, "  tubalance_id: {}", t.id);
+        for a in &t.attachments {
+            let _ = writeln!(out, "  ; {}", attachment_comment(a));
+        }
+        for (account, cents) in &e.category_postings {
+            let _ = writeln!(
+                out,
+                "  {}  {} {}",
+                account,
+                format_cents(sign * cents),
+                t.currency()
+            );
+        }
+        let _ = writeln!(
+            out,
+            "  {}  {} {}",
+            e.asset_account,
+            format_cents(-sign * t.amount.cents),
+            t.currency()
+        );
+    }
+}
+
+/// Sign of the category postings; the asset posting takes the opposite.
+/// Expenses increase the expense account and drain the asset; income is
+/// negative on the income account, as double-entry tools expect.
+fn category_sign(t: &Transaction) -> i64 {
+    if t.r#type == TransactionType::Income {
+        -1
+    } else {
+        1
+    }
+}
+
+/// Shortest decimal representation, so the same rate always prints the same.
+fn format_rate(rate: Rate) -> String {
+    let text = rate.to_string();
+    if text.contains('.') {
+        text
+    } else {
+        format!("{}.00", text)
+    }
+}
+
+fn top_level(r#type: TransactionType) -> &'static str {
+    if r#type == TransactionType::Income {
+        "Income"
+    } else {
+        "Expenses"
+    }
+}
+
+fn category_account(category: &Category) -> String {
+    format!(
+        "{}:{}",
+        top_level(category.r#type),
+        account_component(&category.name)
+    )
+}
+
+/// Account components must start with an uppercase letter or digit and only
+/// contain letters, digits and dashes (beancount's rule, also safe for ledger).
+fn account_component(name: &str) -> String {
+    let mut component = String::new();
+    for word in name.split(|c: char| !c.is_alphanumeric()) {
+        if word.is_empty() {
+            continue;
+        }
+        if !component.is_empty() {
+            component.push('-');
+        }
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            component.extend(first.to_uppercase());
+            component.push_str(chars.as_str());
+        }
+    }
+
+    if component.is_empty() {
+        "Sin-nombre".into()
+    } else {
+        component
+    }
+}
+
+fn attachment_comment(a: &Attachment) -> String {
+    format!(
+        "attachment: {} (sha256:{})",
+        single_line(&a.file_name),
+        a.hash
+    )
+}
+
+/// Contents of a beancount string literal.
+fn beancount_string(text: &str) -> String {
+    single_line(text).replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,3 @@
+//! Writers that turn app data into formats other tools understand.
+
+pub mod journal;
@@ -0,0 +1,247 @@
+//! Data exported from the original browser-only version of the app, which
+//! kept `transactions` and `categories` in localStorage as camelCase JSON
+//! with float amounts (see `prompt.txt`).
+
+use crate::models::TransactionType;
+use crate::money::Rate;
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyCategory {
+    pub id: Value,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LegacyTransaction {
+    pub id: Value,
+    pub description: String,
+    /// Missing in some rows; such a row is skipped rather than the whole file
+    pub amount: Option<f64>,
+    #[serde(rename = "amountInARS", alias = "amountInArs")]
+    pub amount_in_ars: Option<f64>,
+    pub currency: Option<String>,
+    pub exchange_rate: Option<f64>,
+    /// Id (or, in some versions, name) of the legacy category
+    #[serde(alias = "categoryId")]
+    pub category: Option<Value>,
+    pub date: String,
+    #[serde(rename = "type")]
+    pub r#type: String,
+}
+
+impl LegacyTransaction {
+    /// Pesos per unit of a foreign currency row. Rows saved without a rate
+    /// keep the one their `amountInARS` implies, when there is an amount to
+    /// divide by.
+    pub fn implied_rate(&self) -> Option<Rate> {
+        let implied = || match (self.amount_in_ars, self.amount) {
+            (Some(ars), Some(amount)) if amount != 0.0 => Some(ars / amount),
+            _ => None,
+        };
+        self.exchange_rate
+            .or_else(implied)
+            .and_then(Rate::from_decimal)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct LegacyDump {
+    pub categories: Vec<LegacyCategory>,
+    pub transactions: Vec<LegacyTransaction>,
+}
+
+impl LegacyDump {
+    /// Name of the legacy category a transaction points to. Falls back to the
+    /// raw value when it is not a known id, since some versions stored names.
+    pub fn category_name(&self, t: &LegacyTransaction) -> Option<String> {
+        let key = value_to_string(t.category.as_ref()?)?;
+        Some(
+            self.categories
+                .iter()
+                .find(|c| value_to_string(&c.id).as_deref() == Some(key.as_str()))
+                .map(|c| c.name.clone())
+                .unwrap_or(key),
+        )
+    }
+
+    pub fn category_by_name(&self, name: &str, r#type: TransactionType) -> Option<&LegacyCategory> {
+        self.categories
+            .iter()
+            .find(|c| c.name == name && normalize_type(&c.r#type) == Some(r#type))
+    }
+}
+
+/// Parse a dump of localStorage. Accepts `{ "transactions": [...],
+/// "categories": [...] }` where each value may also be the raw JSON string
+/// localStorage holds, or a bare array of transactions.
+pub fn parse(content: &str) -> Result<LegacyDump, String> {
+    let root: Value = serde_json::from_str(content).map_err(|e| format!("JSON inválido: {}", e))?;
+
+    if root.is_array() {
+        return Ok(LegacyDump {
+            categories: Vec::new(),
+            transactions: from_value(root, "transactions")?,
+        });
+    }
+
+    let Value::Object(mut map) = root else {
+        return Err("El archivo no contiene datos de la versión anterior".into());
+    };
+
+    let mut take = |key: &str| -> Result<Value, String> {
+        match map.remove(key) {
+            // localStorage values are strings holding JSON
+            Some(Value::String(raw)) => serde_json::from_str(&raw)
+                .map_err(|e| format!("'{}' no contiene JSON válido: {}", key, e)),
+            Some(value) => Ok(value),
+            None => Ok(Value::Array(Vec::new())),
+        }
+    };
+
+    let dump = LegacyDump {
+        categories: from_value(take("categories")?, "categories")?,
+        transactions: from_value(take("transactions")?, "transactions")?,
+    };
+    if dump.transactions.is_empty() && dump.categories.is_empty() {
+        return Err("El archivo no contiene transacciones ni categorías".into());
+    }
+
+    Ok(dump)
+}
+
+fn from_value<T: for<'de> Deserialize<'de>>(value: Value, key: &str) -> Result<Vec<T>, String> {
+    serde_json::from_value(value).map_err(|e| format!("'{}' inválido: {}", key, e))
+}
+
+/// Legacy ids were numbers or strings depending on the version.
+pub fn value_to_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The old app used Spanish type names in some versions.
+pub fn normalize_type(raw: &str) -> Option<TransactionType> {
+    match raw.trim().to_lowercase().as_str() {
+        "income" | "ingreso" => Some(TransactionType::Income),
+        "expense" | "gasto" => Some(TransactionType::Expense),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_localstorage_strings() {
+        let dump = parse(
+            r#"{
+                "categories": "[{\"id\":1,\"name\":\"Comida\",\"type\":\"gasto\",\"icon\":\"Utensils\"}]",
+                "transactions": "[{\"id\":\"a1\",\"description\":\"Coto\",\"amount\":1500.5,\"category\":1,\"date\":\"2024-03-05T15:00:00.000Z\",\"type\":\"gasto\"},{\"id\":2,\"description\":\"Cena\",\"amount\":20,\"amountInArs\":19000,\"currency\":\"USD\",\"categoryId\":\"Salidas\",\"date\":\"2024-03-06\",\"type\":\"expense\"}]"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(dump.categories.len(), 1);
+        assert_eq!(dump.categories[0].color, None);
+
+        let [coto, dinner] = &dump.transactions[..] else {
+            panic!("{:?}", dump.transactions);
+        };
+        assert_eq!(value_to_string(&coto.id).as_deref(), Some("a1"));
+        assert_eq!(coto.amount, Some(1500.5));
+        assert_eq!(dump.category_name(coto).as_deref(), Some("Comida"));
+        assert_eq!(normalize_type(&coto.r#type), Some(TransactionType::Expense));
+        assert!(dump
+            .category_by_name("Comida", TransactionType::Expense)
+            .is_some());
+        assert!(dump
+            .category_by_name("Comida", TransactionType::Income)
+            .is_none());
+
+        // Names stored in place of ids are kept as they are
+        assert_eq!(value_to_string(&dinner.id).as_deref(), Some("2"));
+        assert_eq!(dump.category_name(dinner).as_deref(), Some("Salidas"));
+        assert_eq!(dinner.amount_in_ars, Some(19000.0));
+    }
+
+    #[test]
+    fn reads_bare_transaction_arrays() {
+        let dump = parse(
+            r#"[{"id":1,"description":"Sueldo","amount":500000,"category":"Sueldo",
+                "date":"2024-03-01","type":"ingreso"},
+               {"id":2,"description":"Cena","amountInARS":19000,"category":"Salidas",
+                "date":"2024-03-06","type":"gasto"}]"#,
+        )
+        .unwrap();
+        assert!(dump.categories.is_empty());
+        assert_eq!(
+            normalize_type(&dump.transactions[0].r#type),
+            Some(TransactionType::Income)
+        );
+        // A row without an amount is left for the importer to skip
+        assert_eq!(dump.transactions[1].amount, None);
+        assert_eq!(dump.transactions[1].amount_in_ars, Some(19000.0));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        for (content, expected) in [
+            ("no es json", "JSON inválido"),
+            ("42", "versión anterior"),
+            ("{}", "ni categorías"),
+            (
+                r#"{"transactions": "[{"}"#,
+                "'transactions' no contiene JSON",
+            ),
+            (
+                r#"{"transactions": [{"id": 1}]}"#,
+                "'transactions' inválido",
+            ),
+        ] {
+            let e = parse(content).unwrap_err();
+            assert!(e.contains(expected), "{}: {}", content, e);
+        }
+    }
+
+    #[test]
+    fn rates_are_implied_by_the_pesos() {
+        let row = |amount: Option<f64>, amount_in_ars: Option<f64>, exchange_rate: Option<f64>| {
+            LegacyTransaction {
+                id: Value::Null,
+                description: "Cena".into(),
+                amount,
+                amount_in_ars,
+                currency: Some("USD".into()),
+                exchange_rate,
+                category: None,
+                date: "2024-03-06".into(),
+                r#type: "expense".into(),
+            }
+        };
+        let rate = |text: &str| Some(Rate::parse(text).unwrap());
+
+        assert_eq!(
+            row(Some(20.0), Some(19000.0), None).implied_rate(),
+            rate("950")
+        );
+        assert_eq!(
+            row(Some(20.0), Some(19000.0), Some(1000.0)).implied_rate(),
+            rate("1000")
+        );
+        // Nothing to divide by: no rate, rather than an infinite one
+        assert_eq!(row(Some(0.0), Some(19000.0), None).implied_rate(), None);
+        assert_eq!(row(None, Some(19000.0), None).implied_rate(), None);
+        assert_eq!(row(Some(20.0), None, None).implied_rate(), None);
+    }
+}
@@ -0,0 +1,360 @@
+//! Mercado Pago account activity reports ("Reporte de actividad" /
+//! `account_statement`), exported as CSV or XLSX.
+//!
+//! The report has one row per movement with its release date, operation type,
+//! operation id and net amount. Some exports start with a summary block
+//! (initial balance, credits, debits); everything above the movement header is
+//! ignored.
+
+use super::{
+    normalize_text, parse_amount_cents, parse_date, DateOrder, ParsedRow, ParsedStatement,
+};
+use crate::money::Currency;
+use std::collections::HashMap;
+
+const DATE_HEADERS: [&str; 5] = [
+    "release_date",
+    "date",
+    "fecha",
+    "fecha de liberacion",
+    "fecha de origen",
+];
+const TYPE_HEADERS: [&str; 5] = [
+    "transaction_type",
+    "description",
+    "tipo de operacion",
+    "descripcion",
+    "detalle",
+];
+const ID_HEADERS: [&str; 6] = [
+    "reference_id",
+    "source_id",
+    "operation_id",
+    "id de operacion",
+    "numero de operacion",
+    "id de la operacion",
+];
+const AMOUNT_HEADERS: [&str; 5] = [
+    "transaction_net_amount",
+    "net_amount",
+    "monto neto",
+    "monto",
+    "valor",
+];
+const CREDIT_HEADERS: [&str; 2] = ["net_credit_amount", "credito"];
+const DEBIT_HEADERS: [&str; 2] = ["net_debit_amount", "debito"];
+
+/// How a Mercado Pago operation type maps onto the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    /// Purchases, QR and card payments, service payments, collections
+    Payment,
+    /// Transfers to or from third parties
+    Transfer,
+    /// Daily yield of the money kept in the account
+    Yield,
+    /// Refunds and chargebacks of a previous payment
+    Refund,
+    /// Movements between the user's own balances (reserves, withdrawals to
+    /// the same account). They do not change net worth and are skipped.
+    Internal,
+}
+
+impl Operation {
+    fn classify(description: &str) -> Self {
+        let text = normalize_text(description);
+        // Whole phrases: "reserva" alone is also a hotel or a court booking
+        if text.contains("dinero reservado")
+            || text.contains("reserva de dinero")
+            || text.contains("retiro de reserva")
+            || text.contains("cuenta propia")
+            || text.contains("cuentas propias")
+            || text.contains("movimiento interno")
+            || text.contains("retiro de dinero")
+        {
+            Operation::Internal
+        } else if text.contains("rendimiento") {
+            Operation::Yield
+        } else if text.contains("devolucion")
+            || text.contains("reembolso")
+            || text.contains("contracargo")
+            || text.contains("cancelacion")
+        {
+            Operation::Refund
+        } else if text.contains("transferencia") {
+            Operation::Transfer
+        } else {
+            Operation::Payment
+        }
+    }
+
+    /// Key exposed in `ParsedRow::operation`
+    fn key(self) -> &'static str {
+        match self {
+            Operation::Payment => "payment",
+            Operation::Transfer => "transfer",
+            Operation::Yield => "yield",
+            Operation::Refund => "refund",
+            Operation::Internal => "internal",
+        }
+    }
+
+    /// Label used when the report has no description of its own
+    fn label(self) -> &'static str {
+        match self {
+            Operation::Payment => "Pago Mercado Pago",
+            Operation::Transfer => "Transferencia Mercado Pago",
+            Operation::Yield => "Rendimientos Mercado Pago",
+            Operation::Refund => "Devolución Mercado Pago",
+            Operation::Internal => "Movimiento interno Mercado Pago",
+        }
+    }
+}
+
+/// Column positions found in the header row.
+struct Columns {
+    date: usize,
+    kind: Option<usize>,
+    id: Option<usize>,
+    amount: AmountColumns,
+}
+
+enum AmountColumns {
+    Net(usize),
+    CreditDebit(usize, usize),
+}
+
+fn find_column(headers: &[String], names: &[&str]) -> Option<usize> {
+    headers.iter().position(|h| names.contains(&h.as_str()))
+}
+
+fn find_columns(row: &[String]) -> Option<Columns> {
+    let headers: Vec<String> = row.iter().map(|h| normalize_text(h)).collect();
+
+    let date = find_column(&headers, &DATE_HEADERS)?;
+    let amount = match find_column(&headers, &AMOUNT_HEADERS) {
+        Some(net) => AmountColumns::Net(net),
+        None => AmountColumns::CreditDebit(
+            find_column(&headers, &CREDIT_HEADERS)?,
+            find_column(&headers, &DEBIT_HEADERS)?,
+        ),
+    };
+
+    Some(Columns {
+        date,
+        kind: find_column(&headers, &TYPE_HEADERS),
+        id: find_column(&headers, &ID_HEADERS),
+        amount,
+    })
+}
+
+/// Parse the rows of an activity report. Incoming movements become income and
+/// outgoing ones expenses; internal movements are counted but not returned.
+pub fn parse(table: Vec<Vec<String>>) -> Result<ParsedStatement, String> {
+    let header_index = table
+        .iter()
+        .position(|row| find_columns(row).is_some())
+        .ok_or("El archivo no parece un reporte de actividad de Mercado Pago")?;
+    let columns = find_columns(&table[header_index]).ok_or("Encabezado inválido")?;
+
+    let mut statement = ParsedStatement {
+        account_id: Some("Mercado Pago".into()),
+        currency: Some(Currency::Ars),
+        ..Default::default()
+    };
+    // The same operation id can appear more than once (e.g. a payment and its
+    // refund); suffix repeats so each row keeps a stable, unique external id.
+    let mut seen_ids: HashMap<String, usize> = HashMap::new();
+
+    for (offset, row) in table.iter().enumerate().skip(header_index + 1) {
+        let cell = |i: usize| row.get(i).map(|s| s.trim()).unwrap_or("");
+        if row.iter().all(|c| c.trim().is_empty()) {
+            continue;
+        }
+        let line = offset + 1;
+
+        let Some(date) = parse_date(cell(columns.date), DateOrder::Dmy) else {
+            statement.warnings.push(format!(
+                "Fila {}: fecha inválida '{}'",
+                line,
+                cell(columns.date)
+            ));
+            continue;
+        };
+
+        let cents = match columns.amount {
+            AmountColumns::Net(i) => parse_amount_cents(cell(i)),
+            AmountColumns::CreditDebit(credit, debit) => {
+                let credit = parse_amount_cents(cell(credit)).unwrap_or(0);
+                let debit = parse_amount_cents(cell(debit)).unwrap_or(0);
+                Some(credit - debit.abs())
+            }
+        };
+        let Some(cents) = cents else {
+            statement
+                .warnings
+                .push(format!("Fila {}: monto inválido", line));
+            continue;
+        };
+        if cents == 0 {
+            continue;
+        }
+
+        let description = columns.kind.map(cell).unwrap_or("");
+        let operation = Operation::classify(description);
+        if operation == Operation::Internal {
+            statement.skipped_internal += 1;
+            continue;
+        }
+
+        let external_id = columns.id.map(cell).filter(|id| !id.is_empty()).map(|id| {
+            let count = seen_ids.entry(id.to_string()).or_insert(0);
+            *count += 1;
+            if *count == 1 {
+                id.to_string()
+            } else {
+                format!("{}-{}", id, count)
+            }
+        });
+
+        let description = if description.is_empty() {
+            operation.label().to_string()
+        } else {
+            description.to_string()
+        };
+
+        let mut parsed = ParsedRow::new(external_id, date, description, cents);
+        parsed.operation = Some(operation.key().into());
+        statement.rows.push(parsed);
+    }
+
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+    use chrono::NaiveDate;
+
+    fn table(rows: &[&[&str]]) -> Vec<Vec<String>> {
+        rows.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn classifies_operations() {
+        let cases = [
+            ("Pago con QR Café Martínez", Operation::Payment),
+            ("Transferencia enviada", Operation::Transfer),
+            ("Rendimientos", Operation::Yield),
+            ("Devolución de dinero", Operation::Refund),
+            ("Cancelación de transferencia", Operation::Refund),
+            ("Dinero reservado", Operation::Internal),
+            ("Reserva de dinero", Operation::Internal),
+            ("Retiro de reserva", Operation::Internal),
+            ("Reserva hotel", Operation::Payment),
+            ("Pago reserva cancha", Operation::Payment),
+            ("Transferencia entre cuentas propias", Operation::Internal),
+            ("Retiro de dinero", Operation::Internal),
+            ("", Operation::Payment),
+        ];
+        for (description, operation) in cases {
+            assert_eq!(
+                Operation::classify(description),
+                operation,
+                "{}",
+                description
+            );
+        }
+    }
+
+    #[test]
+    fn finds_columns_in_either_language() {
+        let columns = find_columns(
+            &table(&[&[
+                "RELEASE_DATE",
+                "TRANSACTION_TYPE",
+                "REFERENCE_ID",
+                "TRANSACTION_NET_AMOUNT",
+            ]])[0],
+        )
+        .unwrap();
+        assert_eq!(
+            (columns.date, columns.kind, columns.id),
+            (0, Some(1), Some(2))
+        );
+        assert!(matches!(columns.amount, AmountColumns::Net(3)));
+
+        let columns =
+            find_columns(&table(&[&["Fecha de liberación", "Crédito", "Débito"]])[0]).unwrap();
+        assert_eq!((columns.kind, columns.id), (None, None));
+        assert!(matches!(columns.amount, AmountColumns::CreditDebit(1, 2)));
+
+        assert!(find_columns(&table(&[&["Fecha", "Detalle"]])[0]).is_none());
+    }
+
+    #[test]
+    fn reads_movements_below_the_summary() {
+        let statement = parse(table(&[
+            &["Saldo inicial", "1.000,00"],
+            &[],
+            &["Fecha", "Descripción", "ID de operación", "Monto"],
+            &["03/05/2026", "Pago con QR Coto", "111", "-1.500,50"],
+            &["04/05/2026", "Devolución de dinero", "111", "1.500,50"],
+            &["05/05/2026", "Rendimientos", "", "12,34"],
+            &["05/05/2026", "Dinero reservado", "222", "-5.000"],
+            &["06/05/2026", "", "333", "0"],
+            &["ayer", "Pago", "444", "-1"],
+            &["07/05/2026", "Pago", "555", "mucho"],
+        ]))
+        .unwrap();
+
+        assert_eq!(statement.currency, Some(Currency::Ars));
+        assert_eq!(statement.skipped_internal, 1);
+        assert_eq!(
+            statement.warnings,
+            ["Fila 9: fecha inválida 'ayer'", "Fila 10: monto inválido"]
+        );
+
+        let rows = &statement.rows;
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].date, NaiveDate::from_ymd_opt(2026, 5, 3).unwrap());
+        assert_eq!(rows[0].amount_cents, 150_050);
+        assert_eq!(rows[0].r#type, TransactionType::Expense);
+        assert_eq!(rows[0].operation.as_deref(), Some("payment"));
+        // A repeated operation id gets a suffix
+        assert_eq!(rows[0].external_id.as_deref(), Some("111"));
+        assert_eq!(rows[1].external_id.as_deref(), Some("111-2"));
+        assert_eq!(rows[1].r#type, TransactionType::Income);
+        assert_eq!(rows[1].operation.as_deref(), Some("refund"));
+        assert_eq!(rows[2].external_id, None);
+        assert_eq!(rows[2].amount_cents, 1_234);
+    }
+
+    #[test]
+    fn nets_credit_and_debit_columns() {
+        let statement = parse(table(&[
+            &[
+                "date",
+                "description",
+                "net_credit_amount",
+                "net_debit_amount",
+            ],
+            &["2026-05-03", "Transferencia recibida", "2000", ""],
+            &["2026-05-04", "", "", "300,5"],
+        ]))
+        .unwrap();
+        assert_eq!(statement.rows[0].amount_cents, 200_000);
+        assert_eq!(statement.rows[0].r#type, TransactionType::Income);
+        assert_eq!(statement.rows[1].amount_cents, 30_050);
+        assert_eq!(statement.rows[1].r#type, TransactionType::Expense);
+        assert_eq!(statement.rows[1].description, "Pago Mercado Pago");
+    }
+
+    #[test]
+    fn rejects_other_reports() {
+        assert!(parse(table(&[&["Nombre", "Apellido"], &["Juan", "Pérez"]])).is_err());
+    }
+}
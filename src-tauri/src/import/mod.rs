@@ -0,0 +1,275 @@
+//! Parsers for files produced by other tools (bank statements, exports).
+//!
+//! Parsers only turn raw contents into `ParsedRow`s; validation against the
+//! database and persistence live in `commands::import`.
+
+pub mod legacy;
+pub mod mercadopago;
+pub mod ofx;
+pub mod qif;
+pub mod table;
+
+use crate::models::TransactionType;
+use crate::money::Currency;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+/// A single movement read from an external file.
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedRow {
+    /// Identifier assigned by the source, used to skip rows already imported
+    pub external_id: Option<String>,
+    pub date: NaiveDate,
+    pub description: String,
+    /// Absolute amount in centavos of the statement's currency; the sign is
+    /// carried by `r#type`
+    pub amount_cents: i64,
+    pub r#type: TransactionType,
+    /// Source-specific kind of movement (e.g. "yield" for Mercado Pago)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+}
+
+impl ParsedRow {
+    /// Build a row from a signed amount: positive is income, negative expense.
+    pub fn new(
+        external_id: Option<String>,
+        date: NaiveDate,
+        description: String,
+        signed_cents: i64,
+    ) -> Self {
+        let amount_cents = signed_cents.abs();
+        ParsedRow {
+            external_id,
+            date,
+            description,
+            amount_cents,
+            r#type: if signed_cents < 0 {
+                TransactionType::Expense
+            } else {
+                TransactionType::Income
+            },
+            operation: None,
+        }
+    }
+}
+
+/// Everything read from one statement file.
+#[derive(Debug, Default, Serialize)]
+pub struct ParsedStatement {
+    /// Account identifier declared in the file, if any
+    pub account_id: Option<String>,
+    /// Currency declared in the file, if any
+    pub currency: Option<Currency>,
+    pub rows: Vec<ParsedRow>,
+    /// Movements deliberately left out (e.g. transfers between own accounts)
+    pub skipped_internal: usize,
+    /// Entries that could not be read, as user-facing messages
+    pub warnings: Vec<String>,
+}
+
+/// Order of day and month in numeric dates (QIF files do not declare it).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateOrder {
+    /// 31/12/2026, the usual format for Argentine banks
+    #[default]
+    Dmy,
+    /// 12/31/2026
+    Mdy,
+}
+
+/// Parse dates such as `31/12/2026`, `12/31'26`, `31-12-26` or ISO
+/// `2026-12-31` (optionally followed by a time, which is ignored).
+/// Two-digit years are taken as 20xx.
+pub fn parse_date(raw: &str, order: DateOrder) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    let day_part = match raw.find(['T', ' ']) {
+        Some(i) if raw[i..].contains(':') => &raw[..i],
+        _ => raw,
+    };
+    let parts: Vec<&str> = day_part
+        .split(['/', '-', '.', '\''])
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    if parts.len() != 3 {
+        return None;
+    }
+
+    let numbers: Vec<u32> = parts
+        .iter()
+        .map(|p| p.parse().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    // ISO order (2026-12-31) is unambiguous
+    if parts[0].len() == 4 {
+        return NaiveDate::from_ymd_opt(numbers[0] as i32, numbers[1], numbers[2]);
+    }
+
+    let (day, month) = match order {
+        DateOrder::Dmy => (numbers[0], numbers[1]),
+        DateOrder::Mdy => (numbers[1], numbers[0]),
+    };
+    let year = if numbers[2] < 100 {
+        2000 + numbers[2]
+    } else {
+        numbers[2]
+    };
+
+    NaiveDate::from_ymd_opt(year as i32, month, day)
+}
+
+/// Parse a decimal amount into signed centavos.
+///
+/// Accepts both `1,234.56` and `1.234,56`: whichever separator appears last
+/// is taken as the decimal one. A single separator followed by exactly three
+/// digits (`1.500`) is read as a thousands separator, as es-AR writes it.
+pub fn parse_amount_cents(raw: &str) -> Option<i64> {
+    let mut s: String = raw
+        .trim()
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '$')
+        .collect();
+
+    let negative = if s.starts_with('(') && s.ends_with(')') {
+        s = s[1..s.len() - 1].to_string();
+        true
+    } else if let Some(rest) = s.strip_prefix('-') {
+        s = rest.to_string();
+        true
+    } else {
+        if let Some(rest) = s.strip_prefix('+') {
+            s = rest.to_string();
+        }
+        false
+    };
+
+    if s.is_empty()
+        || !s
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+
+    let decimal_pos = match (s.rfind('.'), s.rfind(',')) {
+        (Some(d), Some(c)) => Some(d.max(c)),
+        (Some(p), None) | (None, Some(p)) => {
+            let sep = s.as_bytes()[p];
+            let occurrences = s.bytes().filter(|&b| b == sep).count();
+            let decimals = s.len() - p - 1;
+            if occurrences > 1 || decimals == 3 {
+                None
+            } else {
+                Some(p)
+            }
+        }
+        (None, None) => None,
+    };
+
+    let (int_part, frac_part) = match decimal_pos {
+        Some(p) => (&s[..p], &s[p + 1..]),
+        None => (s.as_str(), ""),
+    };
+
+    let int_digits: String = int_part.chars().filter(|c| c.is_ascii_digit()).collect();
+    let units: i64 = if int_digits.is_empty() {
+        0
+    } else {
+        int_digits.parse().ok()?
+    };
+    let mut cents: i64 = format!("{:0<2}", frac_part.get(..2).unwrap_or(frac_part))
+        .parse()
+        .ok()?;
+    // More precision than centavos: round half away from zero
+    if frac_part.as_bytes().get(2).is_some_and(|&d| d >= b'5') {
+        cents += 1;
+    }
+
+    let total = units.checked_mul(100)?.checked_add(cents)?;
+    Some(if negative { -total } else { total })
+}
+
+/// Lowercase and strip Spanish accents, for matching free text such as
+/// column headers or operation descriptions.
+pub fn normalize_text(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'á' | 'à' | 'ä' | 'â' => 'a',
+            'é' | 'è' | 'ë' | 'ê' => 'e',
+            'í' | 'ì' | 'ï' | 'î' => 'i',
+            'ó' | 'ò' | 'ö' | 'ô' => 'o',
+            'ú' | 'ù' | 'ü' | 'û' => 'u',
+            'ñ' => 'n',
+            _ => c,
+        })
+        .collect()
+}
+
+/// Decode file contents as UTF-8, falling back to Latin-1, which many
+/// Argentine banks still use for their downloads.
+pub fn decode_text(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) => s.trim_start_matches('\u{feff}').to_string(),
+        Err(_) => bytes.iter().map(|&b| b as char).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_in_either_locale() {
+        assert_eq!(parse_amount_cents("1.234,56"), Some(123_456));
+        assert_eq!(parse_amount_cents("1,234.56"), Some(123_456));
+        assert_eq!(parse_amount_cents("-1500.5"), Some(-150_050));
+        assert_eq!(parse_amount_cents("(12,30)"), Some(-1_230));
+        assert_eq!(parse_amount_cents("$ +850 000"), Some(85_000_000));
+        assert_eq!(parse_amount_cents(",5"), Some(50));
+    }
+
+    #[test]
+    fn three_digits_after_a_lone_separator_are_thousands() {
+        assert_eq!(parse_amount_cents("1.500"), Some(150_000));
+        assert_eq!(parse_amount_cents("1,500"), Some(150_000));
+        assert_eq!(parse_amount_cents("1.500.000"), Some(150_000_000));
+        assert_eq!(parse_amount_cents("1.50"), Some(150));
+        assert_eq!(parse_amount_cents("1.500,75"), Some(150_075));
+    }
+
+    #[test]
+    fn extra_decimals_round_half_away_from_zero() {
+        assert_eq!(parse_amount_cents("1.000,125"), Some(100_013));
+        assert_eq!(parse_amount_cents("-1,234.565"), Some(-123_457));
+        assert_eq!(parse_amount_cents("1,2345"), Some(123));
+    }
+
+    #[test]
+    fn rejects_what_is_not_an_amount() {
+        for raw in ["", "-", "abc", "12a", "1e5", "99999999999999999999"] {
+            assert_eq!(parse_amount_cents(raw), None, "{}", raw);
+        }
+    }
+
+    #[test]
+    fn dates_in_both_orders() {
+        let day = NaiveDate::from_ymd_opt(2026, 12, 31);
+        assert_eq!(parse_date("31/12/2026", DateOrder::Dmy), day);
+        assert_eq!(parse_date("12/31'26", DateOrder::Mdy), day);
+        assert_eq!(parse_date("31-12-26", DateOrder::Dmy), day);
+        assert_eq!(parse_date("2026-12-31 10:30:00", DateOrder::Mdy), day);
+        assert_eq!(parse_date("31/12/2026", DateOrder::Mdy), None);
+        assert_eq!(parse_date("31/12", DateOrder::Dmy), None);
+    }
+
+    #[test]
+    fn text_without_accents() {
+        assert_eq!(normalize_text("  Cotización AÑO "), "cotizacion ano");
+        assert_eq!(decode_text(b"\xefu\xf1a"), "\u{ef}u\u{f1}a");
+        assert_eq!(decode_text("\u{feff}hola".as_bytes()), "hola");
+    }
+}
@@ -0,0 +1,204 @@
+//! OFX bank statements, both 1.x (SGML, unclosed tags) and 2.x (XML).
+
+use super::{ParsedRow, ParsedStatement};
+use crate::money::{parse_fixed, Currency};
+use chrono::NaiveDate;
+
+/// Parse the statement transactions (`<STMTTRN>`) of an OFX file.
+pub fn parse(content: &str) -> Result<ParsedStatement, String> {
+    let tokens = tokenize(content);
+    if !tokens.iter().any(|(tag, _)| tag == "OFX") {
+        return Err("El archivo no es un extracto OFX válido".into());
+    }
+
+    let mut statement = ParsedStatement::default();
+    let mut current: Option<Vec<(String, String)>> = None;
+
+    for (tag, value) in tokens {
+        match tag.as_str() {
+            "STMTTRN" => current = Some(Vec::new()),
+            "/STMTTRN" => {
+                if let Some(fields) = current.take() {
+                    match parse_entry(&fields) {
+                        Ok(Some(row)) => statement.rows.push(row),
+                        Ok(None) => {}
+                        Err(e) => statement.warnings.push(e),
+                    }
+                }
+            }
+            "ACCTID" if statement.account_id.is_none() && !value.is_empty() => {
+                statement.account_id = Some(value);
+            }
+            "CURDEF" if statement.currency.is_none() && !value.is_empty() => {
+                let currency = Currency::parse(&value.to_uppercase())
+                    .map_err(|_| format!("Moneda del extracto no soportada: '{}'", value))?;
+                statement.currency = Some(currency);
+            }
+            _ => {
+                if let Some(fields) = current.as_mut() {
+                    if !tag.starts_with('/') {
+                        fields.push((tag, value));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(statement)
+}
+
+/// Split the body into `(TAG, text)` pairs. Closing tags keep their leading
+/// slash; the text is whatever follows the tag up to the next one, which
+/// covers SGML files where leaf elements are never closed.
+fn tokenize(content: &str) -> Vec<(String, String)> {
+    let mut tokens = Vec::new();
+    let mut rest = match content.find('<') {
+        Some(start) => &content[start..],
+        None => return tokens,
+    };
+
+    while let Some(open) = rest.find('<') {
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('>') else {
+            break;
+        };
+        let tag = after[..close].trim().to_uppercase();
+        let body = &after[close + 1..];
+        let text_end = body.find('<').unwrap_or(body.len());
+        let text = decode_entities(body[..text_end].trim());
+
+        // Skip XML declarations and processing instructions
+        if !tag.starts_with('?') && !tag.starts_with('!') {
+            tokens.push((tag, text));
+        }
+        rest = &body[text_end..];
+    }
+
+    tokens
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Turn the fields of one `<STMTTRN>` into a row. Zero-amount entries (some
+/// banks emit them for informational lines) are skipped.
+fn parse_entry(fields: &[(String, String)]) -> Result<Option<ParsedRow>, String> {
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(tag, value)| tag == name && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    };
+
+    let fitid = field("FITID").map(str::to_string);
+    let label = fitid.as_deref().unwrap_or("sin FITID");
+
+    let date = field("DTPOSTED")
+        .and_then(parse_ofx_date)
+        .ok_or_else(|| format!("Movimiento {}: fecha inválida", label))?;
+
+    // A plain decimal, never grouped; the spec allows a comma as the point
+    let cents = field("TRNAMT")
+        .and_then(|amount| parse_fixed(&amount.replace(',', "."), 2))
+        .ok_or_else(|| format!("Movimiento {}: monto inválido", label))?;
+    if cents == 0 {
+        return Ok(None);
+    }
+
+    let description = match (field("NAME").or(field("PAYEE")), field("MEMO")) {
+        (Some(name), Some(memo)) if !name.eq_ignore_ascii_case(memo) => {
+            format!("{} - {}", name, memo)
+        }
+        (Some(name), _) => name.to_string(),
+        (None, Some(memo)) => memo.to_string(),
+        (None, None) => field("TRNTYPE").unwrap_or("Movimiento").to_string(),
+    };
+
+    Ok(Some(ParsedRow::new(fitid, date, description, cents)))
+}
+
+/// OFX dates look like `20260503`, `20260503120000` or
+/// `20260503120000.000[-3:ART]`; only the calendar day matters here.
+fn parse_ofx_date(raw: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(raw.get(..8)?, "%Y%m%d").ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+
+    const SGML: &str = "OFXHEADER:100\nDATA:OFXSGML\n\n<OFX><BANKMSGSRSV1><STMTTRNRS><STMTRS>
+<CURDEF>ars<BANKACCTFROM><ACCTID>0170-123</BANKACCTFROM><BANKTRANLIST>
+<STMTTRN><TRNTYPE>DEBIT<DTPOSTED>20260503120000.000[-3:ART]<TRNAMT>-1500.50<FITID>A1<NAME>COTO PALERMO<MEMO>Compra</STMTTRN>
+<STMTTRN><TRNTYPE>CREDIT<DTPOSTED>20260505<TRNAMT>850000<FITID>A2<MEMO>Sueldo</STMTTRN>
+<STMTTRN><TRNTYPE>OTHER<DTPOSTED>20260506<TRNAMT>0<FITID>A3</STMTTRN>
+<STMTTRN><DTPOSTED>2026<TRNAMT>10<FITID>A4</STMTTRN>
+</BANKTRANLIST></STMTRS></STMTTRNRS></BANKMSGSRSV1></OFX>";
+
+    #[test]
+    fn reads_sgml_statements() {
+        let statement = parse(SGML).unwrap();
+        assert_eq!(statement.account_id.as_deref(), Some("0170-123"));
+        assert_eq!(statement.currency, Some(Currency::Ars));
+        assert_eq!(statement.rows.len(), 2);
+
+        let purchase = &statement.rows[0];
+        assert_eq!(purchase.external_id.as_deref(), Some("A1"));
+        assert_eq!(purchase.date, NaiveDate::from_ymd_opt(2026, 5, 3).unwrap());
+        assert_eq!(purchase.description, "COTO PALERMO - Compra");
+        assert_eq!(purchase.amount_cents, 150_050);
+        assert_eq!(purchase.r#type, TransactionType::Expense);
+
+        let salary = &statement.rows[1];
+        assert_eq!(salary.description, "Sueldo");
+        assert_eq!(salary.amount_cents, 85_000_000);
+        assert_eq!(salary.r#type, TransactionType::Income);
+
+        // The zero-amount line is skipped, the undated one reported
+        assert_eq!(statement.warnings, ["Movimiento A4: fecha inválida"]);
+    }
+
+    #[test]
+    fn reads_xml_statements() {
+        let xml = r#"<?xml version="1.0"?><?OFX OFXHEADER="200"?>
+<OFX><CURDEF>USD</CURDEF><STMTTRN><DTPOSTED>20260503</DTPOSTED><TRNAMT>-12.00</TRNAMT>
+<FITID>X1</FITID><NAME>Tom &amp; Jerry</NAME></STMTTRN></OFX>"#;
+        let statement = parse(xml).unwrap();
+        assert_eq!(statement.currency, Some(Currency::Usd));
+        assert_eq!(statement.rows[0].description, "Tom & Jerry");
+        assert_eq!(statement.rows[0].amount_cents, 1_200);
+    }
+
+    #[test]
+    fn amounts_are_plain_decimals() {
+        let amount = |raw: &str| {
+            let content = format!(
+                "<OFX><STMTTRN><DTPOSTED>20260503<TRNAMT>{}<FITID>A1</STMTTRN></OFX>",
+                raw
+            );
+            parse(&content)
+                .unwrap()
+                .rows
+                .first()
+                .map(|row| (row.amount_cents, row.r#type))
+        };
+        let expense = |cents| Some((cents, TransactionType::Expense));
+        assert_eq!(amount("-12.345"), expense(1_235));
+        assert_eq!(amount("-1500,5"), expense(150_050));
+        assert_eq!(amount("1500"), Some((150_000, TransactionType::Income)));
+        assert_eq!(amount("1.500,50"), None);
+    }
+
+    #[test]
+    fn rejects_other_files_and_currencies() {
+        assert!(parse("fecha,monto\n2026-05-03,10").is_err());
+        let e = parse("<OFX><CURDEF>EUR<STMTTRN></STMTTRN></OFX>").unwrap_err();
+        assert!(e.contains("EUR"), "{}", e);
+    }
+}
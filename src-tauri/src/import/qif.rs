@@ -0,0 +1,150 @@
+//! QIF (Quicken Interchange Format) bank statements.
+
+use super::{parse_amount_cents, parse_date, DateOrder, ParsedRow, ParsedStatement};
+
+/// Parse the bank/cash entries of a QIF file. QIF has no transaction ids and
+/// no currency, so those are left for the caller to supply.
+pub fn parse(content: &str, order: DateOrder) -> Result<ParsedStatement, String> {
+    if !content
+        .lines()
+        .any(|l| l.trim_start().starts_with("!Type:"))
+    {
+        return Err("El archivo no es un extracto QIF válido".into());
+    }
+
+    let mut statement = ParsedStatement::default();
+    let mut section = String::new();
+    let mut fields: Vec<(char, String)> = Vec::new();
+    let mut entry_number = 0;
+
+    for line in content.lines() {
+        let line = line.trim_end();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('!') {
+            section = header.trim().to_lowercase();
+            fields.clear();
+            continue;
+        }
+
+        if line.starts_with('^') {
+            let entry = std::mem::take(&mut fields);
+            if section == "account" {
+                // Account block: remember the name of the first account
+                if statement.account_id.is_none() {
+                    statement.account_id = entry
+                        .iter()
+                        .find(|(code, _)| *code == 'N')
+                        .map(|(_, value)| value.clone());
+                }
+            } else if is_transaction_section(&section) {
+                entry_number += 1;
+                match parse_entry(&entry, order) {
+                    Ok(Some(row)) => statement.rows.push(row),
+                    Ok(None) => {}
+                    Err(e) => statement
+                        .warnings
+                        .push(format!("Movimiento {}: {}", entry_number, e)),
+                }
+            }
+            continue;
+        }
+
+        let mut chars = line.chars();
+        if let Some(code) = chars.next() {
+            fields.push((code, chars.as_str().trim().to_string()));
+        }
+    }
+
+    Ok(statement)
+}
+
+fn is_transaction_section(section: &str) -> bool {
+    matches!(
+        section,
+        "type:bank" | "type:cash" | "type:ccard" | "type:oth a" | "type:oth l"
+    )
+}
+
+fn parse_entry(fields: &[(char, String)], order: DateOrder) -> Result<Option<ParsedRow>, String> {
+    let field = |code: char| {
+        fields
+            .iter()
+            .find(|(c, value)| *c == code && !value.is_empty())
+            .map(|(_, value)| value.as_str())
+    };
+
+    let date = field('D')
+        .and_then(|raw| parse_date(raw, order))
+        .ok_or("fecha inválida")?;
+
+    let cents = field('T')
+        .or(field('U'))
+        .and_then(parse_amount_cents)
+        .ok_or("monto inválido")?;
+    if cents == 0 {
+        return Ok(None);
+    }
+
+    let description = match (field('P'), field('M')) {
+        (Some(payee), Some(memo)) if !payee.eq_ignore_ascii_case(memo) => {
+            format!("{} - {}", payee, memo)
+        }
+        (Some(payee), _) => payee.to_string(),
+        (None, Some(memo)) => memo.to_string(),
+        (None, None) => "Movimiento".to_string(),
+    };
+
+    Ok(Some(ParsedRow::new(None, date, description, cents)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionType;
+    use chrono::NaiveDate;
+
+    const QIF: &str = "!Account\nNCaja de ahorro\nTBank\n^\n!Type:Bank\n\
+D03/05/2026\nT-1.500,50\nPCoto\nMCompra\n^\n\
+D05/05'26\nU850000\nMSueldo\n^\n\
+D06/05/2026\nT0\n^\n\
+D31/02/2026\nT10\n^\n";
+
+    #[test]
+    fn reads_bank_entries() {
+        let statement = parse(QIF, DateOrder::Dmy).unwrap();
+        assert_eq!(statement.account_id.as_deref(), Some("Caja de ahorro"));
+        assert_eq!(statement.currency, None);
+        assert_eq!(statement.rows.len(), 2);
+
+        let purchase = &statement.rows[0];
+        assert_eq!(purchase.external_id, None);
+        assert_eq!(purchase.date, NaiveDate::from_ymd_opt(2026, 5, 3).unwrap());
+        assert_eq!(purchase.description, "Coto - Compra");
+        assert_eq!(purchase.amount_cents, 150_050);
+        assert_eq!(purchase.r#type, TransactionType::Expense);
+
+        let salary = &statement.rows[1];
+        assert_eq!(salary.date, NaiveDate::from_ymd_opt(2026, 5, 5).unwrap());
+        assert_eq!(salary.description, "Sueldo");
+        assert_eq!(salary.r#type, TransactionType::Income);
+
+        assert_eq!(statement.warnings, ["Movimiento 4: fecha inválida"]);
+    }
+
+    #[test]
+    fn follows_the_date_order() {
+        let statement = parse("!Type:CCard\nD05/03/2026\nT-10\n^\n", DateOrder::Mdy).unwrap();
+        assert_eq!(
+            statement.rows[0].date,
+            NaiveDate::from_ymd_opt(2026, 5, 3).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse("D03/05/2026\nT-10\n^\n", DateOrder::Dmy).is_err());
+    }
+}
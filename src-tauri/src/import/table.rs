@@ -0,0 +1,64 @@
+//! Spreadsheet-like files (CSV and XLSX) read into rows of plain strings, so
+//! report parsers do not need to care about the container format.
+
+use calamine::{open_workbook_auto_from_rs, Data, DataType, Reader};
+use std::io::Cursor;
+
+/// Read every row of a CSV file. The delimiter (`;` or `,`) is guessed from
+/// the first non-empty line.
+pub fn read_csv(content: &str) -> Result<Vec<Vec<String>>, String> {
+    let first_line = content.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+    let delimiter = if first_line.matches(';').count() > first_line.matches(',').count() {
+        b';'
+    } else {
+        b','
+    };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+
+    reader
+        .records()
+        .map(|record| {
+            record
+                .map(|r| r.iter().map(|cell| cell.trim().to_string()).collect())
+                .map_err(|e| format!("CSV inválido: {}", e))
+        })
+        .collect()
+}
+
+/// Read the first worksheet of an XLSX/XLS/ODS file. Dates become
+/// `YYYY-MM-DD` and fractional numbers keep two decimals with a dot separator.
+pub fn read_workbook(bytes: Vec<u8>) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))
+        .map_err(|e| format!("No se pudo abrir la planilla: {}", e))?;
+
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or("La planilla no tiene hojas")?
+        .map_err(|e| format!("No se pudo leer la planilla: {}", e))?;
+
+    Ok(range
+        .rows()
+        .map(|row| row.iter().map(cell_to_string).collect())
+        .collect())
+}
+
+fn cell_to_string(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => s.trim().to_string(),
+        // Whole numbers are usually ids; keep them free of decimals
+        Data::Float(f) if f.fract() == 0.0 => format!("{}", *f as i64),
+        Data::Float(f) => format!("{:.2}", f),
+        Data::Int(i) => i.to_string(),
+        Data::Bool(b) => b.to_string(),
+        Data::DateTime(_) => cell
+            .as_datetime()
+            .map(|dt| dt.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+    }
+}
@@ -1,12 +1,27 @@
+use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::Manager;
 
+mod afip;
+mod attachments;
+mod classifier;
 mod commands;
 mod db;
+mod dedup;
+mod export;
+mod import;
 mod models;
+mod money;
+mod payees;
+mod quick_entry;
+mod rules;
 
 pub struct AppState {
     pub db: Mutex<rusqlite::Connection>,
+    /// Content-addressed store of attachment files (see `attachments`)
+    pub attachments_dir: PathBuf,
+    /// Learned category suggestions; lock after `db` when both are needed
+    pub classifier: Mutex<classifier::Classifier>,
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -14,10 +29,19 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .setup(|app| {
-            let conn = db::init_db(app).expect("Error al inicializar la base de datos");
+            let mut conn = db::init_db(app).expect("Error al inicializar la base de datos");
+            let attachments_dir = app.path().app_data_dir()?.join("attachments");
+
+            // Rows past the trash retention, then the files left behind by
+            // deleted transactions. Both are retried on every launch, so a
+            // failure is not worth refusing to start over.
+            let _ = commands::trash::purge_expired(&mut conn);
+            let _ = attachments::remove_orphans(&conn, &attachments_dir);
 
             app.manage(AppState {
                 db: Mutex::new(conn),
+                attachments_dir,
+                classifier: Mutex::new(classifier::Classifier::default()),
             });
 
             Ok(())
@@ -26,13 +50,80 @@ pub fn run() {
             // Transactions
             commands::transactions::get_transactions,
             commands::transactions::add_transaction,
+            commands::transactions::add_transactions,
             commands::transactions::delete_transaction,
             commands::transactions::update_transaction,
+            commands::transactions::patch_transaction,
+            commands::transactions::find_duplicates,
+            commands::transactions::repair_amounts_in_ars,
+            commands::bulk::bulk_edit,
+            commands::quick_entry::parse_quick_entry,
+            commands::quick_entry::parse_invoice_qr,
             // Categories
             commands::categories::get_categories,
             commands::categories::add_category,
             commands::categories::update_category,
+            commands::categories::patch_category,
             commands::categories::delete_category,
+            commands::categories::suggest_category,
+            // Audit
+            commands::audit::get_history,
+            commands::audit::get_activity,
+            // Undo
+            commands::undo::undo,
+            commands::undo::redo,
+            commands::undo::get_undo_stack,
+            // Trash
+            commands::trash::list_trash,
+            commands::trash::restore,
+            commands::trash::purge_trash,
+            commands::trash::get_trash_retention,
+            commands::trash::set_trash_retention,
+            // Tags
+            commands::tags::get_tags,
+            commands::tags::create_tag,
+            commands::tags::rename_tag,
+            commands::tags::merge_tags,
+            commands::tags::delete_tag,
+            commands::tags::assign_tags,
+            commands::tags::unassign_tags,
+            // Payees
+            commands::payees::get_payees,
+            commands::payees::add_payee,
+            commands::payees::update_payee,
+            commands::payees::delete_payee,
+            commands::payees::merge_payees,
+            commands::payees::assign_payee,
+            commands::payees::suggest_payees,
+            // Rules
+            commands::rules::get_rules,
+            commands::rules::add_rule,
+            commands::rules::update_rule,
+            commands::rules::delete_rule,
+            commands::rules::dry_run_rule,
+            commands::rules::apply_rule,
+            // Attachments
+            commands::attachments::add_attachment,
+            commands::attachments::list_attachments,
+            commands::attachments::open_attachment,
+            commands::attachments::remove_attachment,
+            // Import
+            commands::import::preview_statement,
+            commands::import::import_statement,
+            commands::import::import_legacy_data,
+            commands::import::list_import_batches,
+            commands::import::rollback_import,
+            commands::import::list_flagged_duplicates,
+            commands::import::resolve_duplicate,
+            // Backup
+            commands::backup::export_backup,
+            commands::backup::import_backup,
+            // Reports
+            commands::reports::get_category_totals,
+            commands::reports::get_tag_totals,
+            commands::reports::get_payee_totals,
+            // Export
+            commands::export::export_journal,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,29 +1,88 @@
+use crate::import::{DateOrder, ParsedRow};
+use crate::money::{cents_from_decimal, format_cents, Currency, Money, Rate};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, SecondsFormat, Utc};
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
-const MAX_DESCRIPTION_LEN: usize = 255;
+pub(crate) const MAX_DESCRIPTION_LEN: usize = 255;
 const MAX_NAME_LEN: usize = 100;
 const MAX_ICON_LEN: usize = 50;
-const VALID_TYPES: [&str; 2] = ["income", "expense"];
-const VALID_CURRENCIES: [&str; 2] = ["ARS", "USD"];
+const MAX_TAG_LEN: usize = 50;
+const MAX_NOTES_LEN: usize = 1000;
 const HEX_COLOR_LEN: usize = 7; // "#rrggbb"
 
+/// Whether money comes in or goes out; shared by transactions and their
+/// categories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", try_from = "String")]
+pub enum TransactionType {
+    Income,
+    Expense,
+}
+
+impl TransactionType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TransactionType::Income => "income",
+            TransactionType::Expense => "expense",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "income" => Ok(TransactionType::Income),
+            "expense" => Ok(TransactionType::Expense),
+            _ => Err(format!(
+                "Tipo inválido: '{}'. Debe ser 'income' o 'expense'",
+                value
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for TransactionType {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        TransactionType::parse(&value)
+    }
+}
+
+impl ToSql for TransactionType {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for TransactionType {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        TransactionType::parse(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
 // --- Category ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Category {
     pub id: i64,
     pub name: String,
-    pub r#type: String,
+    pub r#type: TransactionType,
     pub icon: String,
     pub color: String,
     pub is_default: bool,
+    /// Bumped on every write; `update_category` and `delete_category` take
+    /// it back to detect concurrent edits
+    #[serde(default)]
+    pub version: i64,
 }
 
 /// Input for creating a new category (no id, no is_default)
 #[derive(Debug, Deserialize)]
 pub struct CategoryInput {
     pub name: String,
-    pub r#type: String,
+    pub r#type: TransactionType,
     pub icon: String,
     pub color: String,
 }
@@ -31,7 +90,6 @@ pub struct CategoryInput {
 impl CategoryInput {
     pub fn validate(&self) -> Result<(), String> {
         validate_name(&self.name)?;
-        validate_type(&self.r#type)?;
         validate_icon(&self.icon)?;
         validate_color(&self.color)?;
         Ok(())
@@ -39,10 +97,10 @@ impl CategoryInput {
 }
 
 /// Input for updating an existing category
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CategoryUpdate {
     pub name: String,
-    pub r#type: String,
+    pub r#type: TransactionType,
     pub icon: String,
     pub color: String,
 }
@@ -50,41 +108,546 @@ pub struct CategoryUpdate {
 impl CategoryUpdate {
     pub fn validate(&self) -> Result<(), String> {
         validate_name(&self.name)?;
-        validate_type(&self.r#type)?;
         validate_icon(&self.icon)?;
         validate_color(&self.color)?;
         Ok(())
     }
 }
 
+/// Fields to change in a category; the rest keep their value
+#[derive(Debug, Default, Deserialize)]
+pub struct CategoryPatch {
+    pub name: Option<String>,
+    pub r#type: Option<TransactionType>,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+}
+
+impl CategoryPatch {
+    /// `current` with the fields of the patch, validated as a whole.
+    pub fn merge(self, current: &Category) -> Result<CategoryUpdate, String> {
+        let merged = CategoryUpdate {
+            name: self.name.unwrap_or_else(|| current.name.clone()),
+            r#type: self.r#type.unwrap_or(current.r#type),
+            icon: self.icon.unwrap_or_else(|| current.icon.clone()),
+            color: self.color.unwrap_or_else(|| current.color.clone()),
+        };
+        merged.validate()?;
+        Ok(merged)
+    }
+}
+
+/// A likely category for a new movement, learned from the history
+#[derive(Debug, Serialize)]
+pub struct CategorySuggestion {
+    pub category_id: i64,
+    /// Probability from 0.0 to 1.0 among the categories of the same type
+    pub confidence: f64,
+}
+
+// --- Quick entry ---
+
+/// A part of a quick entry draft
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DraftField {
+    Description,
+    Amount,
+    Currency,
+    ExchangeRate,
+    Date,
+    Type,
+    Category,
+    Payee,
+}
+
+/// A transaction read from one line of text, for the user to confirm
+#[derive(Debug, Serialize)]
+pub struct QuickEntryDraft {
+    pub input: TransactionInput,
+    /// Parts the text did not state and were filled in by default or
+    /// guessed from the history; the UI should ask to confirm them
+    pub inferred: Vec<DraftField>,
+    /// Parts that could not be worked out and must be completed
+    pub missing: Vec<DraftField>,
+    /// Other likely categories, best first
+    pub suggestions: Vec<CategorySuggestion>,
+}
+
+// --- Tag ---
+
+/// Free-form label that can be attached to any number of transactions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: i64,
+    pub name: String,
+}
+
+/// Tag names are compared case-insensitively; surrounding spaces are dropped.
+pub fn validate_tag_name(name: &str) -> Result<(), String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("El nombre de la etiqueta no puede estar vacío".into());
+    }
+    if name.chars().count() > MAX_TAG_LEN {
+        return Err(format!(
+            "El nombre de la etiqueta no puede superar {} caracteres",
+            MAX_TAG_LEN
+        ));
+    }
+    Ok(())
+}
+
+// --- Payee ---
+
+/// Who a transaction was paid to or received from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Payee {
+    pub id: i64,
+    pub name: String,
+    /// Other spellings found in descriptions ("COTO 1234", "Coto Palermo")
+    pub aliases: Vec<String>,
+    /// Category used for this payee's imported movements
+    pub default_category_id: Option<i64>,
+    pub notes: Option<String>,
+    /// Linked transactions (informational; ignored when restoring backups)
+    #[serde(default)]
+    pub transaction_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Input for creating or updating a payee
+#[derive(Debug, Deserialize)]
+pub struct PayeeInput {
+    pub name: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub default_category_id: Option<i64>,
+    pub notes: Option<String>,
+}
+
+impl PayeeInput {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)?;
+        for alias in &self.aliases {
+            if alias.trim().is_empty() {
+                return Err("Los alias no pueden estar vacíos".into());
+            }
+            if alias.trim().len() > MAX_DESCRIPTION_LEN {
+                return Err(format!(
+                    "Los alias no pueden superar {} caracteres",
+                    MAX_DESCRIPTION_LEN
+                ));
+            }
+        }
+        if self.default_category_id.is_some_and(|id| id <= 0) {
+            return Err("Debe seleccionar una categoría válida".into());
+        }
+        if self
+            .notes
+            .as_ref()
+            .is_some_and(|n| n.trim().len() > MAX_NOTES_LEN)
+        {
+            return Err(format!(
+                "Las notas no pueden superar {} caracteres",
+                MAX_NOTES_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A group of unassigned descriptions that probably name the same payee
+#[derive(Debug, Serialize)]
+pub struct PayeeSuggestion {
+    /// Proposed payee name
+    pub name: String,
+    /// Set when the group matches a payee that already exists
+    pub existing_payee_id: Option<i64>,
+    /// Distinct descriptions in the group, to be added as aliases
+    pub descriptions: Vec<String>,
+    pub transaction_ids: Vec<i64>,
+    pub total_in_ars: Money,
+}
+
+// --- Rule ---
+
+/// How a rule's pattern is compared with the description
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternKind {
+    /// Anywhere in the description, ignoring case and accents
+    #[default]
+    Contains,
+    /// At the start of the description, ignoring case and accents
+    StartsWith,
+    /// Regular expression on the description, ignoring case (and accents
+    /// when the pattern has none)
+    Regex,
+}
+
+impl PatternKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PatternKind::Contains => "contains",
+            PatternKind::StartsWith => "starts_with",
+            PatternKind::Regex => "regex",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "contains" => Some(PatternKind::Contains),
+            "starts_with" => Some(PatternKind::StartsWith),
+            "regex" => Some(PatternKind::Regex),
+            _ => None,
+        }
+    }
+}
+
+/// A user-defined categorization rule. Every condition that is set must
+/// hold for the rule to match; its actions then set the category, payee
+/// and tags of the transaction.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    pub id: i64,
+    pub name: String,
+    /// Rules run from the lowest priority number up
+    pub priority: i64,
+    pub enabled: bool,
+    // Conditions
+    pub pattern: Option<String>,
+    pub pattern_kind: PatternKind,
+    /// Inclusive bounds on the amount, in centavos of the transaction's
+    /// currency
+    pub min_amount_cents: Option<i64>,
+    pub max_amount_cents: Option<i64>,
+    pub currency: Option<Currency>,
+    pub account: Option<String>,
+    // Actions
+    pub category_id: Option<i64>,
+    pub payee_id: Option<i64>,
+    pub tag_ids: Vec<i64>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Input for creating, updating or trying out a rule
+#[derive(Debug, Deserialize)]
+pub struct RuleInput {
+    pub name: String,
+    #[serde(default)]
+    pub priority: i64,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+    pub pattern: Option<String>,
+    #[serde(default)]
+    pub pattern_kind: PatternKind,
+    pub min_amount_cents: Option<i64>,
+    pub max_amount_cents: Option<i64>,
+    pub currency: Option<Currency>,
+    pub account: Option<String>,
+    pub category_id: Option<i64>,
+    pub payee_id: Option<i64>,
+    #[serde(default)]
+    pub tag_ids: Vec<i64>,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+impl RuleInput {
+    pub fn validate(&self) -> Result<(), String> {
+        validate_name(&self.name)?;
+
+        // Conditions
+        if let Some(pattern) = &self.pattern {
+            if pattern.trim().is_empty() {
+                return Err("El patrón no puede estar vacío".into());
+            }
+            if pattern.len() > MAX_DESCRIPTION_LEN {
+                return Err(format!(
+                    "El patrón no puede superar {} caracteres",
+                    MAX_DESCRIPTION_LEN
+                ));
+            }
+            if self.pattern_kind == PatternKind::Regex {
+                crate::rules::compile_regex(pattern)?;
+            }
+        }
+        for cents in [self.min_amount_cents, self.max_amount_cents]
+            .into_iter()
+            .flatten()
+        {
+            if cents < 0 {
+                return Err("Los montos de la regla deben ser números positivos".into());
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_amount_cents, self.max_amount_cents) {
+            if min > max {
+                return Err("El monto mínimo no puede superar al máximo".into());
+            }
+        }
+        if self.account.as_ref().is_some_and(|a| a.trim().is_empty()) {
+            return Err("La cuenta no puede estar vacía".into());
+        }
+        if self.pattern.is_none()
+            && self.min_amount_cents.is_none()
+            && self.max_amount_cents.is_none()
+            && self.currency.is_none()
+            && self.account.is_none()
+        {
+            return Err("La regla necesita al menos una condición".into());
+        }
+
+        // Actions
+        if self.category_id.is_some_and(|id| id <= 0) {
+            return Err("Debe seleccionar una categoría válida".into());
+        }
+        if self.payee_id.is_some_and(|id| id <= 0) {
+            return Err("Debe seleccionar un beneficiario válido".into());
+        }
+        if self.tag_ids.iter().any(|&id| id <= 0) {
+            return Err("Debe seleccionar etiquetas válidas".into());
+        }
+        if self.category_id.is_none() && self.payee_id.is_none() && self.tag_ids.is_empty() {
+            return Err("La regla debe asignar una categoría, un beneficiario o etiquetas".into());
+        }
+
+        Ok(())
+    }
+}
+
+impl From<&Rule> for RuleInput {
+    fn from(rule: &Rule) -> Self {
+        RuleInput {
+            name: rule.name.clone(),
+            priority: rule.priority,
+            enabled: rule.enabled,
+            pattern: rule.pattern.clone(),
+            pattern_kind: rule.pattern_kind,
+            min_amount_cents: rule.min_amount_cents,
+            max_amount_cents: rule.max_amount_cents,
+            currency: rule.currency,
+            account: rule.account.clone(),
+            category_id: rule.category_id,
+            payee_id: rule.payee_id,
+            tag_ids: rule.tag_ids.clone(),
+        }
+    }
+}
+
+/// What a rule would change on one stored transaction. Only the fields that
+/// change are set.
+#[derive(Debug, Serialize)]
+pub struct RuleChange {
+    pub transaction: Transaction,
+    pub category_id: Option<i64>,
+    pub payee_id: Option<i64>,
+    /// Tags the transaction does not have yet
+    pub added_tag_ids: Vec<i64>,
+}
+
 // --- Transaction ---
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Transaction {
     pub id: i64,
     pub description: String,
-    pub amount: f64,
-    pub amount_in_ars: f64,
-    pub currency: String,
-    pub exchange_rate: Option<f64>,
+    /// In the currency of the transaction
+    pub amount: Money,
+    pub amount_in_ars: Money,
+    /// Pesos per unit of `amount`'s currency; `None` for pesos
+    pub exchange_rate: Option<Rate>,
     pub category_id: i64,
-    pub date: String,
-    pub r#type: String,
+    #[serde(deserialize_with = "calendar_day")]
+    pub date: NaiveDate,
+    pub r#type: TransactionType,
+    pub account: Option<String>,
+    pub external_id: Option<String>,
+    /// Import that created this row, if any (see `rollback_import`)
+    pub import_batch_id: Option<i64>,
+    /// Set when an import flagged this row as a probable duplicate of another
+    pub duplicate_of: Option<i64>,
+    #[serde(default)]
+    pub payee_id: Option<i64>,
+    /// Set when entered from an electronic invoice QR
+    #[serde(default)]
+    pub invoice: Option<Invoice>,
+    /// Breakdown across categories; when present it replaces `category_id`
+    /// in every per-category figure
+    #[serde(default)]
+    pub splits: Vec<TransactionSplit>,
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    #[serde(default)]
+    pub attachments: Vec<Attachment>,
+    /// Bumped on every write; `update_transaction` and `delete_transaction`
+    /// take it back to detect concurrent edits
+    #[serde(default)]
+    pub version: i64,
     pub created_at: String,
     pub updated_at: String,
 }
 
+/// One line of a split transaction, in the transaction's currency
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionSplit {
+    pub id: i64,
+    pub category_id: i64,
+    pub amount: Money,
+    /// Share of the transaction's `amount_in_ars`, prorated by `amount`
+    pub amount_in_ars: Money,
+    pub note: Option<String>,
+}
+
+impl Transaction {
+    pub fn currency(&self) -> Currency {
+        self.amount.currency
+    }
+}
+
+/// Calendar day of a date as older versions stored it. The frontend used
+/// to store `Date.toISOString()`, which is UTC, so full timestamps are
+/// shifted to local time first; bare `YYYY-MM-DD` dates are taken as they
+/// are.
+pub fn calendar_date(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(dt.with_timezone(&Local).date_naive());
+    }
+    NaiveDate::parse_from_str(raw.get(..10)?, "%Y-%m-%d").ok()
+}
+
+/// A transaction date as clients send it: a real `YYYY-MM-DD` day, or a
+/// full RFC 3339 timestamp as found in older backups and history, taken as
+/// its local day.
+pub fn parse_day(raw: &str) -> Option<NaiveDate> {
+    let raw = raw.trim();
+    NaiveDate::parse_from_str(raw, "%Y-%m-%d").ok().or_else(|| {
+        DateTime::parse_from_rfc3339(raw)
+            .ok()
+            .map(|dt| dt.with_timezone(&Local).date_naive())
+    })
+}
+
+fn calendar_day<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let raw = String::deserialize(deserializer)?;
+    parse_day(&raw)
+        .ok_or_else(|| serde::de::Error::custom(format!("Fecha inválida: '{}'", raw.trim())))
+}
+
+fn optional_calendar_day<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    calendar_day(deserializer).map(Some)
+}
+
+/// A moment as every `*_at` column stores it: RFC 3339 in UTC with
+/// milliseconds, `2026-05-03T15:04:05.000Z`, like `Date.toISOString()`.
+/// Sharing one format keeps them comparable as text.
+pub fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+pub fn timestamp_now() -> String {
+    timestamp(Utc::now())
+}
+
+/// A stored timestamp in the format of `timestamp`. Besides RFC 3339 it
+/// reads SQLite's `datetime('now')`, which is UTC without a zone.
+pub fn normalize_timestamp(raw: &str) -> Option<String> {
+    let raw = raw.trim();
+    if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+        return Some(timestamp(dt.with_timezone(&Utc)));
+    }
+    NaiveDateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|dt| timestamp(dt.and_utc()))
+}
+
 /// Input for creating a new transaction (no id, no timestamps)
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionInput {
     pub description: String,
-    pub amount: f64,
-    pub amount_in_ars: f64,
-    pub currency: String,
-    pub exchange_rate: Option<f64>,
+    pub amount: Money,
+    /// Derived from `amount` and `exchange_rate` (see
+    /// `derived_amount_in_ars`); when sent anyway it must match
+    #[serde(default)]
+    pub amount_in_ars: Option<Money>,
+    pub exchange_rate: Option<Rate>,
+    pub category_id: i64,
+    #[serde(deserialize_with = "calendar_day")]
+    pub date: NaiveDate,
+    pub r#type: TransactionType,
+    #[serde(default)]
+    pub payee_id: Option<i64>,
+    #[serde(default)]
+    pub invoice: Option<Invoice>,
+    /// Optional breakdown across categories; amounts must add up to `amount`
+    #[serde(default)]
+    pub splits: Vec<SplitInput>,
+    /// Tags to attach. On update, `None` leaves the current tags untouched
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag_ids: Option<Vec<i64>>,
+}
+
+/// The electronic invoice a transaction was entered from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Invoice {
+    /// As printed, e.g. "Factura B 00003-00012345"
+    pub number: String,
+    /// Issuer CUIT, `30-71234567-9`
+    pub issuer_cuit: String,
+}
+
+impl Invoice {
+    pub fn validate(&self) -> Result<(), String> {
+        let number = self.number.trim();
+        if number.is_empty() {
+            return Err("El número de comprobante no puede estar vacío".into());
+        }
+        if number.len() > MAX_NAME_LEN {
+            return Err(format!(
+                "El número de comprobante no puede superar {} caracteres",
+                MAX_NAME_LEN
+            ));
+        }
+        if !crate::afip::cuit_is_valid(self.issuer_cuit.trim()) {
+            return Err(format!("El CUIT {} no es válido", self.issuer_cuit.trim()));
+        }
+        Ok(())
+    }
+}
+
+/// A receipt or invoice file attached to a transaction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Attachment {
+    pub id: i64,
+    pub transaction_id: i64,
+    /// Name of the file the user picked
+    pub file_name: String,
+    pub mime_type: String,
+    /// Size in bytes
+    pub size: i64,
+    /// SHA-256 of the content, which is also its storage key
+    pub hash: String,
+    pub created_at: String,
+}
+
+/// A split line as sent by the client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitInput {
     pub category_id: i64,
-    pub date: String,
-    pub r#type: String,
+    /// In the transaction's currency
+    pub amount: Money,
+    pub note: Option<String>,
 }
 
 impl TransactionInput {
@@ -102,64 +665,1090 @@ impl TransactionInput {
         }
 
         // Amount
-        if self.amount <= 0.0 {
+        if self.amount.cents <= 0 {
             return Err("El monto debe ser mayor a 0".into());
         }
-        if !self.amount.is_finite() {
-            return Err("El monto no es un número válido".into());
+
+        // Exchange rate
+        match self.exchange_rate {
+            Some(_) if self.amount.currency == Currency::Ars => {
+                return Err("Una transacción en pesos no lleva cotización".into());
+            }
+            Some(rate) => validate_rate(rate)?,
+            None => {}
         }
 
         // Amount in ARS
-        if self.amount_in_ars <= 0.0 {
+        let amount_in_ars = self.derived_amount_in_ars()?;
+        if amount_in_ars.cents <= 0 {
             return Err("El monto en ARS debe ser mayor a 0".into());
         }
-        if !self.amount_in_ars.is_finite() {
-            return Err("El monto en ARS no es un número válido".into());
+        if let Some(sent) = &self.amount_in_ars {
+            if *sent != amount_in_ars {
+                return Err(format!(
+                    "El monto en ARS no coincide con el monto y la cotización: es {}, no {}",
+                    amount_in_ars, sent
+                ));
+            }
+        }
+
+        // Category ID
+        if self.category_id <= 0 {
+            return Err("Debe seleccionar una categoría válida".into());
+        }
+
+        // Payee
+        if self.payee_id.is_some_and(|id| id <= 0) {
+            return Err("Debe seleccionar un beneficiario válido".into());
+        }
+
+        // Invoice
+        if let Some(invoice) = &self.invoice {
+            invoice.validate()?;
+        }
+
+        // Tags
+        if self
+            .tag_ids
+            .as_ref()
+            .is_some_and(|ids| ids.iter().any(|&id| id <= 0))
+        {
+            return Err("Debe seleccionar etiquetas válidas".into());
+        }
+
+        // Splits
+        if !self.splits.is_empty() {
+            self.validate_splits()?;
+        }
+
+        Ok(())
+    }
+
+    /// `amount` in pesos: the amount itself for pesos, otherwise converted
+    /// at `exchange_rate` (see `Money::to_ars` for the rounding).
+    pub fn derived_amount_in_ars(&self) -> Result<Money, String> {
+        if self.amount.currency == Currency::Ars {
+            return Ok(self.amount.clone());
+        }
+        match self.exchange_rate {
+            Some(rate) => self.amount.to_ars(rate),
+            None => Err(format!(
+                "La cotización es obligatoria para transacciones en {}",
+                self.amount.currency
+            )),
+        }
+    }
+
+    fn validate_splits(&self) -> Result<(), String> {
+        if self.splits.len() < 2 {
+            return Err("Una transacción dividida necesita al menos dos partes".into());
+        }
+        for (i, split) in self.splits.iter().enumerate() {
+            if split.category_id <= 0 {
+                return Err(format!(
+                    "Parte {}: debe seleccionar una categoría válida",
+                    i + 1
+                ));
+            }
+            if split.amount.cents <= 0 {
+                return Err(format!("Parte {}: el monto debe ser mayor a 0", i + 1));
+            }
+            if split.amount.currency != self.amount.currency {
+                return Err(format!(
+                    "Parte {}: debe estar en {}, como la transacción",
+                    i + 1,
+                    self.amount.currency
+                ));
+            }
+            if let Some(note) = &split.note {
+                if note.trim().len() > MAX_DESCRIPTION_LEN {
+                    return Err(format!(
+                        "Parte {}: la nota no puede superar {} caracteres",
+                        i + 1,
+                        MAX_DESCRIPTION_LEN
+                    ));
+                }
+            }
         }
 
-        // Currency
-        if !VALID_CURRENCIES.contains(&self.currency.as_str()) {
+        let total: i64 = self.splits.iter().map(|s| s.amount.cents).sum();
+        if total != self.amount.cents {
             return Err(format!(
-                "Moneda inválida: '{}'. Debe ser ARS o USD",
-                self.currency
+                "Las partes suman {} pero el monto es {}",
+                format_cents(total),
+                format_cents(self.amount.cents)
             ));
         }
 
-        // Exchange rate
-        if self.currency == "USD" {
-            match self.exchange_rate {
-                Some(rate) if rate <= 0.0 => {
-                    return Err("La cotización debe ser mayor a 0".into());
-                }
-                Some(rate) if !rate.is_finite() => {
-                    return Err("La cotización no es un número válido".into());
+        Ok(())
+    }
+}
+
+/// Update uses the same fields and validation as Input
+pub type TransactionUpdate = TransactionInput;
+
+/// Tell a field sent as `null` (`Some(None)`) from one left out (`None`).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Fields to change in a transaction; the rest keep their value. Sending
+/// `null` clears `exchange_rate`, `payee_id` or `invoice`. `amount_in_ars`
+/// always follows the merged amount and rate.
+#[derive(Debug, Default, Deserialize)]
+pub struct TransactionPatch {
+    pub description: Option<String>,
+    pub amount: Option<Money>,
+    pub amount_in_ars: Option<Money>,
+    #[serde(default, deserialize_with = "present")]
+    pub exchange_rate: Option<Option<Rate>>,
+    pub category_id: Option<i64>,
+    #[serde(default, deserialize_with = "optional_calendar_day")]
+    pub date: Option<NaiveDate>,
+    pub r#type: Option<TransactionType>,
+    #[serde(default, deserialize_with = "present")]
+    pub payee_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    pub invoice: Option<Option<Invoice>>,
+    pub splits: Option<Vec<SplitInput>>,
+    pub tag_ids: Option<Vec<i64>>,
+}
+
+impl TransactionPatch {
+    /// `current` with the fields of the patch, validated as a whole.
+    pub fn merge(self, current: &Transaction) -> Result<TransactionInput, String> {
+        let mut merged = TransactionInput::from(current);
+        if let Some(description) = self.description {
+            merged.description = description;
+        }
+        if let Some(amount) = self.amount {
+            merged.amount = amount;
+        }
+        merged.amount_in_ars = self.amount_in_ars;
+        if let Some(exchange_rate) = self.exchange_rate {
+            merged.exchange_rate = exchange_rate;
+        }
+        if let Some(category_id) = self.category_id {
+            merged.category_id = category_id;
+        }
+        if let Some(date) = self.date {
+            merged.date = date;
+        }
+        if let Some(r#type) = self.r#type {
+            merged.r#type = r#type;
+        }
+        if let Some(payee_id) = self.payee_id {
+            merged.payee_id = payee_id;
+        }
+        if let Some(invoice) = self.invoice {
+            merged.invoice = invoice;
+        }
+        if let Some(splits) = self.splits {
+            merged.splits = splits;
+        }
+        if let Some(tag_ids) = self.tag_ids {
+            merged.tag_ids = Some(tag_ids);
+        }
+        merged.validate()?;
+        Ok(merged)
+    }
+}
+
+impl From<&Transaction> for TransactionInput {
+    fn from(t: &Transaction) -> Self {
+        TransactionInput {
+            description: t.description.clone(),
+            amount: t.amount.clone(),
+            // Derived again on write
+            amount_in_ars: None,
+            // Older snapshots may carry one on pesos, which no longer validates
+            exchange_rate: t
+                .exchange_rate
+                .filter(|_| t.amount.currency != Currency::Ars),
+            category_id: t.category_id,
+            date: t.date,
+            r#type: t.r#type,
+            payee_id: t.payee_id,
+            invoice: t.invoice.clone(),
+            splits: t
+                .splits
+                .iter()
+                .map(|s| SplitInput {
+                    category_id: s.category_id,
+                    amount: s.amount.clone(),
+                    note: s.note.clone(),
+                })
+                .collect(),
+            tag_ids: Some(t.tags.iter().map(|tag| tag.id).collect()),
+        }
+    }
+}
+
+/// Bring a transaction serialized before amounts were `Money` (decimal
+/// `amount` and `amount_in_ars` next to a `currency` field), as found in
+/// older backups and audit entries, to the current layout. Amounts are
+/// rounded to centavos; anything else is left as it is.
+pub fn upgrade_amounts(transaction: &mut Value) {
+    let Some(t) = transaction.as_object_mut() else {
+        return;
+    };
+    if !t.get("amount").is_some_and(Value::is_number) {
+        return;
+    }
+    let Some(Value::String(currency)) = t.remove("currency") else {
+        return;
+    };
+
+    legacy_money(t, "amount", &currency);
+    legacy_money(t, "amount_in_ars", Currency::Ars.as_str());
+    if let Some(splits) = t.get_mut("splits").and_then(Value::as_array_mut) {
+        for split in splits.iter_mut().filter_map(Value::as_object_mut) {
+            legacy_money(split, "amount", &currency);
+            legacy_money(split, "amount_in_ars", Currency::Ars.as_str());
+        }
+    }
+}
+
+fn legacy_money(object: &mut Map<String, Value>, key: &str, currency: &str) {
+    if let Some(cents) = object
+        .get(key)
+        .and_then(Value::as_f64)
+        .and_then(cents_from_decimal)
+    {
+        object.insert(key.into(), json!({ "cents": cents, "currency": currency }));
+    }
+}
+
+/// Rewrite every `*_at` string in `value`, at any depth, in the format of
+/// `timestamp`. Unreadable ones are left as they are.
+pub fn upgrade_timestamps(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, field) in object.iter_mut() {
+                match field {
+                    Value::String(raw) if key.ends_with("_at") => {
+                        if let Some(normalized) = normalize_timestamp(raw) {
+                            *raw = normalized;
+                        }
+                    }
+                    _ => upgrade_timestamps(field),
                 }
-                None => {
-                    return Err("La cotización es obligatoria para transacciones en USD".into());
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(upgrade_timestamps),
+        _ => {}
+    }
+}
+
+/// Which transactions a query returns. Every criterion is optional and
+/// they are combined with AND.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TransactionFilter {
+    #[serde(default)]
+    pub period: Period,
+    /// Matches the main category or any split line
+    pub category_id: Option<i64>,
+    pub payee_id: Option<i64>,
+    #[serde(default)]
+    pub tag_ids: Vec<i64>,
+    #[serde(default)]
+    pub tag_match: TagMatch,
+}
+
+impl TransactionFilter {
+    pub fn validate(&self) -> Result<(), String> {
+        self.period.validate()
+    }
+}
+
+/// How `TransactionFilter::tag_ids` is applied
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// At least one of the tags
+    #[default]
+    Any,
+    /// Every one of the tags
+    All,
+}
+
+/// Where a transaction came from. Set once on insert and never touched by
+/// `update_transaction`.
+#[derive(Debug, Default)]
+pub struct TransactionOrigin {
+    /// Account label (e.g. the bank account the statement belongs to)
+    pub account: Option<String>,
+    /// Identifier assigned by the source (OFX FITID, operation id, ...)
+    pub external_id: Option<String>,
+    pub import_batch_id: Option<i64>,
+    /// Existing transaction this one probably duplicates, pending review
+    pub duplicate_of: Option<i64>,
+}
+
+/// Outcome of `add_transaction`. When the input looks like something already
+/// stored nothing is inserted and the matches come back for the user to
+/// confirm; resending with `allow_duplicate` stores it anyway.
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AddTransactionResult {
+    Created { transaction: Box<Transaction> },
+    PossibleDuplicates { duplicates: Vec<DuplicateMatch> },
+}
+
+/// Outcome of `add_transactions`: every row stored, or none and the reason
+/// for each row that failed
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum AddTransactionsResult {
+    Created { transactions: Vec<Transaction> },
+    Invalid { errors: Vec<InputError> },
+}
+
+/// Why the input at `index` of a batch was rejected
+#[derive(Debug, Serialize)]
+pub struct InputError {
+    pub index: usize,
+    pub error: String,
+}
+
+/// Error of a write guarded by a row version. Plain failures carry just
+/// a message, like any other command error.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WriteError<T> {
+    /// The row changed since the client read it; nothing was written
+    Conflict {
+        message: String,
+        current: Box<T>,
+    },
+    Failed {
+        message: String,
+    },
+}
+
+impl<T> From<String> for WriteError<T> {
+    fn from(message: String) -> Self {
+        WriteError::Failed { message }
+    }
+}
+
+/// A stored transaction resembling a new one
+#[derive(Debug, Serialize)]
+pub struct DuplicateMatch {
+    pub transaction: Transaction,
+    /// Description similarity, from 0.0 to 1.0
+    pub similarity: f64,
+}
+
+/// Two stored transactions that look like the same movement
+#[derive(Debug, Serialize)]
+pub struct DuplicatePair {
+    /// The one entered first
+    pub original: Transaction,
+    pub duplicate: Transaction,
+    pub similarity: f64,
+}
+
+/// Inclusive range of calendar days; open ends are unbounded
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Period {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl Period {
+    pub fn validate(&self) -> Result<(), String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err("La fecha de inicio no puede ser posterior a la fecha de fin".into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+}
+
+// --- Import ---
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFormat {
+    Ofx,
+    Qif,
+    /// Mercado Pago activity report, CSV or XLSX
+    MercadoPago,
+}
+
+impl StatementFormat {
+    /// Name recorded on import batches
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatementFormat::Ofx => "ofx",
+            StatementFormat::Qif => "qif",
+            StatementFormat::MercadoPago => "mercadopago",
+        }
+    }
+}
+
+/// Options for committing a bank statement after previewing it
+#[derive(Debug, Deserialize)]
+pub struct StatementImportOptions {
+    pub format: StatementFormat,
+    #[serde(default)]
+    pub date_order: DateOrder,
+    /// Account label stored on every row; defaults to the account id in the file
+    pub account: Option<String>,
+    /// Currency for files that do not declare one (QIF); defaults to ARS
+    pub currency: Option<Currency>,
+    /// Required when the statement is not in ARS
+    pub exchange_rate: Option<Rate>,
+    pub income_category_id: i64,
+    pub expense_category_id: i64,
+    /// Category overrides keyed by `ParsedRow::operation` (e.g. "yield")
+    #[serde(default)]
+    pub operation_categories: HashMap<String, i64>,
+    /// Indices of preview rows the user chose not to import
+    #[serde(default)]
+    pub skip_rows: Vec<usize>,
+}
+
+impl StatementImportOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(rate) = self.exchange_rate {
+            validate_rate(rate)?;
+        }
+        if self.income_category_id <= 0 || self.expense_category_id <= 0 {
+            return Err("Debe seleccionar categorías válidas para ingresos y gastos".into());
+        }
+        if self.operation_categories.values().any(|&id| id <= 0) {
+            return Err("Debe seleccionar una categoría válida para cada tipo de operación".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PreviewRow {
+    #[serde(flatten)]
+    pub row: ParsedRow,
+    /// Exact duplicate of a stored transaction; it will be skipped
+    pub already_imported: bool,
+    /// Stored transaction this row resembles; it will be imported flagged
+    pub probable_duplicate_of: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StatementPreview {
+    pub account_id: Option<String>,
+    pub currency: Option<Currency>,
+    pub rows: Vec<PreviewRow>,
+    /// Movements left out on purpose (transfers between own balances)
+    pub skipped_internal: usize,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    /// Batch to pass to `rollback_import` to undo this import
+    pub batch_id: i64,
+    pub imported: usize,
+    pub skipped_duplicates: usize,
+    /// Imported rows that look like an existing transaction and need review
+    pub flagged_duplicates: usize,
+    pub warnings: Vec<String>,
+}
+
+/// One run of an importer
+#[derive(Debug, Serialize)]
+pub struct ImportBatch {
+    pub id: i64,
+    pub source: String,
+    pub file_name: Option<String>,
+    pub imported: i64,
+    pub skipped: i64,
+    pub flagged: i64,
+    pub created_at: String,
+}
+
+/// An imported transaction flagged as a probable duplicate, with the
+/// transaction it resembles
+#[derive(Debug, Serialize)]
+pub struct FlaggedDuplicate {
+    pub transaction: Transaction,
+    pub original: Transaction,
+}
+
+/// Outcome of importing data from the localStorage version of the app
+#[derive(Debug, Default, Serialize)]
+pub struct LegacyImportReport {
+    pub batch_id: i64,
+    pub imported: usize,
+    /// Transactions already imported by a previous run
+    pub skipped_duplicates: usize,
+    pub flagged_duplicates: usize,
+    /// Categories created because no existing one matched by name
+    pub categories_created: Vec<String>,
+    /// Legacy categories with no match; their transactions were not imported
+    pub unmapped_categories: Vec<String>,
+    /// Transactions that could not be converted, with the reason
+    pub skipped: Vec<String>,
+}
+
+// --- Reports ---
+
+/// Spending or income of one category over a period
+#[derive(Debug, Serialize)]
+pub struct CategoryTotal {
+    pub category_id: i64,
+    pub r#type: TransactionType,
+    pub total_in_ars: Money,
+    /// Transactions contributing to the total (a split counts once)
+    pub transaction_count: usize,
+}
+
+/// Income and spending of one tag over a period. A transaction with
+/// several tags counts towards each of them.
+#[derive(Debug, Serialize)]
+pub struct TagTotal {
+    pub tag: Tag,
+    pub income_in_ars: Money,
+    pub expense_in_ars: Money,
+    pub transaction_count: usize,
+}
+
+/// Income and spending with one payee over a period, month by month
+#[derive(Debug, Serialize)]
+pub struct PayeeTotal {
+    pub payee_id: i64,
+    pub name: String,
+    pub income_in_ars: Money,
+    pub expense_in_ars: Money,
+    pub transaction_count: usize,
+    /// Oldest month first
+    pub months: Vec<MonthTotal>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MonthTotal {
+    /// `YYYY-MM`
+    pub month: String,
+    pub income_in_ars: Money,
+    pub expense_in_ars: Money,
+}
+
+// --- Export ---
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalFormat {
+    /// ledger-cli / hledger
+    Ledger,
+    Beancount,
+}
+
+// --- Backup ---
+
+/// Bumped whenever the backup layout changes. Older versions must stay
+/// importable; newer ones are rejected.
+pub const BACKUP_SCHEMA_VERSION: u32 = 9;
+
+/// Portable, human-readable snapshot of all user data
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Backup {
+    pub schema_version: u32,
+    pub app: String,
+    pub exported_at: String,
+    pub categories: Vec<Category>,
+    /// Added in schema version 3
+    #[serde(default)]
+    pub tags: Vec<Tag>,
+    /// Added in schema version 5
+    #[serde(default)]
+    pub payees: Vec<Payee>,
+    /// Added in schema version 6
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+    /// Invoice details on transactions were added in schema version 7;
+    /// amounts became `Money` in schema version 8 (see `upgrade_amounts`);
+    /// dates became calendar days in schema version 9, and earlier
+    /// timestamps are read as their local day
+    pub transactions: Vec<Transaction>,
+    /// Attachment contents, base64-encoded and keyed by hash. Added in
+    /// schema version 4
+    #[serde(default)]
+    pub files: BTreeMap<String, String>,
+}
+
+impl Backup {
+    /// Parse a backup file, bringing the amounts of schema versions before
+    /// 8 and the timestamps of those before 9 to the current layout.
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let mut value: Value =
+            serde_json::from_str(content).map_err(|e| format!("Respaldo inválido: {}", e))?;
+        if value["schema_version"].as_u64().is_some_and(|v| v < 8) {
+            if let Some(transactions) = value.get_mut("transactions").and_then(Value::as_array_mut)
+            {
+                transactions.iter_mut().for_each(upgrade_amounts);
+            }
+            if let Some(rules) = value.get_mut("rules").and_then(Value::as_array_mut) {
+                for rule in rules.iter_mut().filter_map(Value::as_object_mut) {
+                    for (old, new) in [
+                        ("min_amount", "min_amount_cents"),
+                        ("max_amount", "max_amount_cents"),
+                    ] {
+                        let cents = rule
+                            .remove(old)
+                            .and_then(|amount| amount.as_f64())
+                            .and_then(cents_from_decimal);
+                        rule.insert(new.into(), json!(cents));
+                    }
                 }
-                _ => {}
             }
         }
+        if value["schema_version"].as_u64().is_some_and(|v| v < 9) {
+            upgrade_timestamps(&mut value);
+        }
+        serde_json::from_value(value).map_err(|e| format!("Respaldo inválido: {}", e))
+    }
 
-        // Type
-        validate_type(&self.r#type)?;
+    /// Check the whole document before touching the database: known schema,
+    /// unique ids, valid fields and no dangling category references.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.schema_version == 0 || self.schema_version > BACKUP_SCHEMA_VERSION {
+            return Err(format!(
+                "Versión de respaldo no soportada: {} (máxima soportada: {})",
+                self.schema_version, BACKUP_SCHEMA_VERSION
+            ));
+        }
 
-        // Date (basic ISO 8601 check)
-        if self.date.trim().is_empty() {
-            return Err("La fecha no puede estar vacía".into());
+        let mut category_ids = HashMap::new();
+        for category in &self.categories {
+            if category_ids.insert(category.id, &category.r#type).is_some() {
+                return Err(format!(
+                    "Categoría duplicada en el respaldo: id {}",
+                    category.id
+                ));
+            }
+            validate_name(&category.name)
+                .and_then(|_| validate_icon(&category.icon))
+                .and_then(|_| validate_color(&category.color))
+                .map_err(|e| format!("Categoría '{}': {}", category.name, e))?;
         }
 
-        // Category ID
-        if self.category_id <= 0 {
-            return Err("Debe seleccionar una categoría válida".into());
+        let mut tag_ids = HashSet::new();
+        for tag in &self.tags {
+            if !tag_ids.insert(tag.id) {
+                return Err(format!("Etiqueta duplicada en el respaldo: id {}", tag.id));
+            }
+            validate_tag_name(&tag.name).map_err(|e| format!("Etiqueta {}: {}", tag.id, e))?;
+        }
+
+        let mut payee_ids = HashSet::new();
+        for payee in &self.payees {
+            if !payee_ids.insert(payee.id) {
+                return Err(format!(
+                    "Beneficiario duplicado en el respaldo: id {}",
+                    payee.id
+                ));
+            }
+            validate_name(&payee.name)
+                .map_err(|e| format!("Beneficiario '{}': {}", payee.name, e))?;
+            if let Some(category_id) = payee.default_category_id {
+                if !category_ids.contains_key(&category_id) {
+                    return Err(format!(
+                        "Beneficiario '{}': la categoría {} no está en el respaldo",
+                        payee.name, category_id
+                    ));
+                }
+            }
+        }
+
+        let mut rule_ids = HashSet::new();
+        for rule in &self.rules {
+            if !rule_ids.insert(rule.id) {
+                return Err(format!("Regla duplicada en el respaldo: id {}", rule.id));
+            }
+            RuleInput::from(rule)
+                .validate()
+                .map_err(|e| format!("Regla '{}': {}", rule.name, e))?;
+            if let Some(category_id) = rule.category_id {
+                if !category_ids.contains_key(&category_id) {
+                    return Err(format!(
+                        "Regla '{}': la categoría {} no está en el respaldo",
+                        rule.name, category_id
+                    ));
+                }
+            }
+            if let Some(payee_id) = rule.payee_id {
+                if !payee_ids.contains(&payee_id) {
+                    return Err(format!(
+                        "Regla '{}': el beneficiario {} no está en el respaldo",
+                        rule.name, payee_id
+                    ));
+                }
+            }
+            if let Some(tag_id) = rule.tag_ids.iter().find(|id| !tag_ids.contains(id)) {
+                return Err(format!(
+                    "Regla '{}': la etiqueta {} no está en el respaldo",
+                    rule.name, tag_id
+                ));
+            }
+        }
+
+        let mut transaction_ids = HashSet::new();
+        for t in &self.transactions {
+            if !transaction_ids.insert(t.id) {
+                return Err(format!("Transacción duplicada en el respaldo: id {}", t.id));
+            }
+            TransactionInput::from(t)
+                .validate()
+                .map_err(|e| format!("Transacción {}: {}", t.id, e))?;
+            for category_id in
+                std::iter::once(t.category_id).chain(t.splits.iter().map(|s| s.category_id))
+            {
+                if !category_ids.contains_key(&category_id) {
+                    return Err(format!(
+                        "Transacción {}: la categoría {} no está en el respaldo",
+                        t.id, category_id
+                    ));
+                }
+            }
+            if let Some(payee_id) = t.payee_id.filter(|id| !payee_ids.contains(id)) {
+                return Err(format!(
+                    "Transacción {}: el beneficiario {} no está en el respaldo",
+                    t.id, payee_id
+                ));
+            }
+            if let Some(tag) = t.tags.iter().find(|tag| !tag_ids.contains(&tag.id)) {
+                return Err(format!(
+                    "Transacción {}: la etiqueta {} no está en el respaldo",
+                    t.id, tag.id
+                ));
+            }
+            if let Some(a) = t
+                .attachments
+                .iter()
+                .find(|a| !self.files.contains_key(&a.hash))
+            {
+                return Err(format!(
+                    "Transacción {}: falta el contenido del adjunto '{}'",
+                    t.id, a.file_name
+                ));
+            }
         }
 
         Ok(())
     }
 }
 
-/// Update uses the same fields and validation as Input
-pub type TransactionUpdate = TransactionInput;
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupImportMode {
+    /// Wipe current data and load the backup
+    Replace,
+    /// Keep current data, reuse categories with the same name and type and
+    /// tags and payees with the same name, and skip transactions that
+    /// already exist
+    Merge,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct BackupImportSummary {
+    pub categories_created: usize,
+    pub categories_matched: usize,
+    pub tags_created: usize,
+    pub payees_created: usize,
+    pub rules_created: usize,
+    pub transactions_imported: usize,
+    pub transactions_skipped: usize,
+    pub attachments_restored: usize,
+}
+
+// --- Bulk ---
+
+/// Transactions a bulk command acts on: `{"ids": [...]}` or
+/// `{"filter": {...}}`
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BulkSelection {
+    Ids(Vec<i64>),
+    Filter(TransactionFilter),
+}
+
+impl BulkSelection {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            BulkSelection::Ids(ids) if ids.is_empty() => {
+                Err("Seleccioná al menos una transacción".into())
+            }
+            BulkSelection::Ids(_) => Ok(()),
+            BulkSelection::Filter(filter) => filter.validate(),
+        }
+    }
+}
+
+/// Change applied by `bulk_edit` to every selected transaction
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum BulkOperation {
+    /// Move to the trash
+    Delete,
+    SetCategory {
+        category_id: i64,
+    },
+    SetDate {
+        #[serde(deserialize_with = "calendar_day")]
+        date: NaiveDate,
+    },
+    AddTags {
+        tag_ids: Vec<i64>,
+    },
+    RemoveTags {
+        tag_ids: Vec<i64>,
+    },
+    /// Also recomputes `amount_in_ars`; only for foreign currency rows
+    SetExchangeRate {
+        exchange_rate: Rate,
+    },
+}
+
+impl BulkOperation {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            BulkOperation::Delete => Ok(()),
+            BulkOperation::SetCategory { category_id } if *category_id <= 0 => {
+                Err("Debe seleccionar una categoría válida".into())
+            }
+            BulkOperation::SetCategory { .. } => Ok(()),
+            BulkOperation::SetDate { .. } => Ok(()),
+            BulkOperation::AddTags { tag_ids } | BulkOperation::RemoveTags { tag_ids }
+                if tag_ids.is_empty() =>
+            {
+                Err("Seleccioná al menos una etiqueta".into())
+            }
+            BulkOperation::AddTags { .. } | BulkOperation::RemoveTags { .. } => Ok(()),
+            BulkOperation::SetExchangeRate { exchange_rate } => validate_rate(*exchange_rate),
+        }
+    }
+}
+
+/// A transaction whose stored `amount_in_ars` did not match its amount and
+/// exchange rate, as found by `repair_amounts_in_ars`
+#[derive(Debug, Serialize)]
+pub struct AmountRepair {
+    pub id: i64,
+    pub description: String,
+    pub amount: Money,
+    pub exchange_rate: Option<Rate>,
+    /// What was stored
+    pub stored: Money,
+    /// What it was recomputed to; `None` when it cannot be derived, as for
+    /// a foreign currency without a rate, and the row was left as it was
+    pub amount_in_ars: Option<Money>,
+    /// Why it could not be repaired
+    pub error: Option<String>,
+}
+
+/// Outcome of a bulk command for one transaction
+#[derive(Debug, Serialize)]
+pub struct BulkItemResult {
+    pub id: i64,
+    /// Why this transaction was left as it was; `None` when it was changed
+    pub error: Option<String>,
+}
+
+// --- Audit ---
+
+/// Kinds of rows that are audited and go to the trash when deleted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Entity {
+    Transaction,
+    Category,
+}
+
+impl Entity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Entity::Transaction => "transaction",
+            Entity::Category => "category",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "transaction" => Some(Entity::Transaction),
+            "category" => Some(Entity::Category),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    /// Moved to the trash
+    Delete,
+    /// Taken out of the trash
+    Restore,
+    /// Deleted for good (purged from the trash or rolled back)
+    Purge,
+}
+
+impl AuditAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+            AuditAction::Restore => "restore",
+            AuditAction::Purge => "purge",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(AuditAction::Create),
+            "update" => Some(AuditAction::Update),
+            "delete" => Some(AuditAction::Delete),
+            "restore" => Some(AuditAction::Restore),
+            "purge" => Some(AuditAction::Purge),
+            _ => None,
+        }
+    }
+}
+
+/// What caused a change
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOrigin {
+    /// The user, from the app
+    Manual,
+    /// A statement or legacy data import, or its rollback
+    Import,
+    /// `apply_rule` over existing transactions
+    Rule,
+    /// Restoring a backup
+    Backup,
+    /// Automatic purge of the trash after the retention
+    Retention,
+    Undo,
+    Redo,
+}
+
+impl AuditOrigin {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AuditOrigin::Manual => "manual",
+            AuditOrigin::Import => "import",
+            AuditOrigin::Rule => "rule",
+            AuditOrigin::Backup => "backup",
+            AuditOrigin::Retention => "retention",
+            AuditOrigin::Undo => "undo",
+            AuditOrigin::Redo => "redo",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "manual" => Some(AuditOrigin::Manual),
+            "import" => Some(AuditOrigin::Import),
+            "rule" => Some(AuditOrigin::Rule),
+            "backup" => Some(AuditOrigin::Backup),
+            "retention" => Some(AuditOrigin::Retention),
+            "undo" => Some(AuditOrigin::Undo),
+            "redo" => Some(AuditOrigin::Redo),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded change, with the row as it was before and after it
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub entity: Entity,
+    pub entity_id: i64,
+    pub action: AuditAction,
+    pub origin: AuditOrigin,
+    /// `None` for creations and restores
+    pub before: Option<serde_json::Value>,
+    /// `None` for deletions and purges
+    pub after: Option<serde_json::Value>,
+    pub created_at: String,
+}
+
+// --- Undo ---
+
+/// A command whose changes can be undone as a whole
+#[derive(Debug, Serialize)]
+pub struct UndoStep {
+    pub id: i64,
+    /// What the user did, e.g. "Editar transacción"
+    pub label: String,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UndoStack {
+    /// Newest first
+    pub undo: Vec<UndoStep>,
+    /// Next to redo first
+    pub redo: Vec<UndoStep>,
+}
+
+// --- Trash ---
+
+/// Longest automatic retention accepted for the trash, in days
+const MAX_TRASH_RETENTION_DAYS: u32 = 3650;
+
+/// A row in the trash, with the time it was deleted
+#[derive(Debug, Serialize)]
+pub struct Trashed<T> {
+    #[serde(flatten)]
+    pub item: T,
+    pub deleted_at: String,
+}
+
+/// Everything in the trash, most recently deleted first
+#[derive(Debug, Serialize)]
+pub struct Trash {
+    pub transactions: Vec<Trashed<Transaction>>,
+    pub categories: Vec<Trashed<Category>>,
+    /// Days rows stay in the trash before being purged; 0 keeps them until
+    /// purged by hand
+    pub retention_days: u32,
+}
+
+/// Rows permanently deleted by a purge
+#[derive(Debug, Default, Serialize)]
+pub struct PurgeSummary {
+    pub transactions: usize,
+    /// Trashed categories still used by a trashed transaction wait for it
+    pub categories: usize,
+    /// Problems that did not stop the purge, such as attachment files that
+    /// could not be removed
+    pub warnings: Vec<String>,
+}
+
+pub fn validate_trash_retention(days: u32) -> Result<(), String> {
+    if days > MAX_TRASH_RETENTION_DAYS {
+        return Err(format!(
+            "La retención de la papelera no puede superar {} días",
+            MAX_TRASH_RETENTION_DAYS
+        ));
+    }
+    Ok(())
+}
 
 // --- Shared validation helpers ---
 
@@ -177,12 +1766,9 @@ fn validate_name(name: &str) -> Result<(), String> {
     Ok(())
 }
 
-fn validate_type(t: &str) -> Result<(), String> {
-    if !VALID_TYPES.contains(&t) {
-        return Err(format!(
-            "Tipo inválido: '{}'. Debe ser 'income' o 'expense'",
-            t
-        ));
+fn validate_rate(rate: Rate) -> Result<(), String> {
+    if rate.millionths() <= 0 {
+        return Err("La cotización debe ser mayor a 0".into());
     }
     Ok(())
 }
@@ -221,3 +1807,40 @@ fn validate_color(color: &str) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(cents: i64, splits: &[i64]) -> TransactionInput {
+        TransactionInput {
+            description: "Súper".into(),
+            amount: Money::ars(cents),
+            amount_in_ars: None,
+            exchange_rate: None,
+            category_id: 1,
+            date: NaiveDate::from_ymd_opt(2026, 5, 3).unwrap(),
+            r#type: TransactionType::Expense,
+            payee_id: None,
+            invoice: None,
+            splits: splits
+                .iter()
+                .map(|&cents| SplitInput {
+                    category_id: 1,
+                    amount: Money::ars(cents),
+                    note: None,
+                })
+                .collect(),
+            tag_ids: None,
+        }
+    }
+
+    #[test]
+    fn split_amounts_must_add_up() {
+        assert!(input(10_000, &[6_000, 4_000]).validate().is_ok());
+        let e = input(10_000, &[6_000, 3_999]).validate().unwrap_err();
+        assert_eq!(e, "Las partes suman 99.99 pero el monto es 100.00");
+        assert!(input(10_000, &[10_000]).validate().is_err());
+        assert!(input(10_000, &[10_001, -1]).validate().is_err());
+    }
+}
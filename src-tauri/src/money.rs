@@ -0,0 +1,364 @@
+//! Money amounts in whole centavos and exchange rates with fixed decimals.
+//!
+//! Nothing is stored or computed in floating point. The rounding rules are:
+//!
+//! - A decimal amount from outside the app (a legacy export, an invoice QR)
+//!   is rounded to the nearest centavo as written in decimal, halves away
+//!   from zero: `10.005` is `10.01` and `-10.005` is `-10.01`.
+//! - A rate keeps `RATE_DECIMALS` decimals; extra ones are rounded the same
+//!   way.
+//! - Converting to pesos multiplies in integers and rounds the product to
+//!   the nearest centavo, halves away from zero.
+//! - Sharing an amount among parts (split lines) rounds each share the same
+//!   way and gives the remainder to the last part, so shares always add up.
+
+use rusqlite::types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef};
+use serde::de::{self, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// Decimals kept by `Rate`
+pub const RATE_DECIMALS: u32 = 6;
+const RATE_SCALE: i64 = 1_000_000;
+
+/// Currencies the app keeps amounts in. Pesos are the one every report
+/// and `amount_in_ars` is expressed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE", try_from = "String")]
+pub enum Currency {
+    Ars,
+    Usd,
+}
+
+impl Currency {
+    /// ISO 4217 code, as stored
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Currency::Ars => "ARS",
+            Currency::Usd => "USD",
+        }
+    }
+
+    pub fn parse(code: &str) -> Result<Self, String> {
+        match code {
+            "ARS" => Ok(Currency::Ars),
+            "USD" => Ok(Currency::Usd),
+            _ => Err(format!("Moneda inválida: '{}'. Debe ser ARS o USD", code)),
+        }
+    }
+}
+
+impl TryFrom<String> for Currency {
+    type Error = String;
+
+    fn try_from(code: String) -> Result<Self, String> {
+        Currency::parse(&code)
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl ToSql for Currency {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Currency {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        Currency::parse(value.as_str()?).map_err(|e| FromSqlError::Other(e.into()))
+    }
+}
+
+/// An amount of money: whole centavos (minor units) of a currency
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Money {
+    pub cents: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn new(cents: i64, currency: Currency) -> Self {
+        Money { cents, currency }
+    }
+
+    pub fn ars(cents: i64) -> Self {
+        Money::new(cents, Currency::Ars)
+    }
+
+    /// Round a decimal amount to whole centavos. `None` when it is not a
+    /// finite number or does not fit.
+    pub fn from_decimal(amount: f64, currency: Currency) -> Option<Self> {
+        cents_from_decimal(amount).map(|cents| Money::new(cents, currency))
+    }
+
+    /// The amount in pesos at `rate` pesos per unit of its currency.
+    pub fn to_ars(&self, rate: Rate) -> Result<Money, String> {
+        let cents = div_round(self.cents as i128 * rate.0 as i128, RATE_SCALE as i128);
+        i64::try_from(cents)
+            .map(Money::ars)
+            .map_err(|_| "El monto en pesos es demasiado grande".to_string())
+    }
+}
+
+impl fmt::Display for Money {
+    /// `1234.50 USD`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", format_cents(self.cents), self.currency)
+    }
+}
+
+/// A decimal amount rounded to whole centavos; `None` when it is not a
+/// finite number or does not fit.
+pub fn cents_from_decimal(amount: f64) -> Option<i64> {
+    if !amount.is_finite() {
+        return None;
+    }
+    parse_fixed(&amount.to_string(), 2)
+}
+
+/// Centavos as a decimal amount with two decimals, e.g. `-1234.50`.
+pub fn format_cents(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    format!(
+        "{}{}.{:02}",
+        sign,
+        cents.unsigned_abs() / 100,
+        cents.unsigned_abs() % 100
+    )
+}
+
+/// The share of `amount` that `part` is of `total`, rounded to the nearest
+/// centavo. `total` must be positive.
+pub fn prorate(amount: i64, part: i64, total: i64) -> i64 {
+    div_round(amount as i128 * part as i128, total as i128) as i64
+}
+
+/// Pesos per unit of a foreign currency, with `RATE_DECIMALS` fixed
+/// decimals. Serialized as a decimal string such as `"1050.25"`; numbers
+/// are accepted too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Rate(i64);
+
+impl Rate {
+    /// A rate from its value in millionths.
+    pub fn from_millionths(millionths: i64) -> Self {
+        Rate(millionths)
+    }
+
+    pub fn millionths(self) -> i64 {
+        self.0
+    }
+
+    /// Parse a decimal such as `1050.25`, rounding past `RATE_DECIMALS`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        parse_fixed(text.trim(), RATE_DECIMALS)
+            .map(Rate)
+            .ok_or_else(|| format!("Cotización inválida: '{}'", text.trim()))
+    }
+
+    /// `None` when `rate` is not a finite number or does not fit.
+    pub fn from_decimal(rate: f64) -> Option<Self> {
+        if !rate.is_finite() {
+            return None;
+        }
+        parse_fixed(&rate.to_string(), RATE_DECIMALS).map(Rate)
+    }
+}
+
+impl fmt::Display for Rate {
+    /// Shortest decimal form: `1050.25`, `1000`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let fraction = format!("{:06}", abs % RATE_SCALE as u64);
+        let fraction = fraction.trim_end_matches('0');
+        if fraction.is_empty() {
+            write!(f, "{}{}", sign, abs / RATE_SCALE as u64)
+        } else {
+            write!(f, "{}{}.{}", sign, abs / RATE_SCALE as u64, fraction)
+        }
+    }
+}
+
+impl Serialize for Rate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rate {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RateVisitor;
+
+        impl Visitor<'_> for RateVisitor {
+            type Value = Rate;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("una cotización decimal")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<Rate, E> {
+                Rate::parse(text).map_err(E::custom)
+            }
+
+            fn visit_f64<E: de::Error>(self, rate: f64) -> Result<Rate, E> {
+                Rate::from_decimal(rate).ok_or_else(|| E::custom("Cotización inválida"))
+            }
+
+            fn visit_i64<E: de::Error>(self, rate: i64) -> Result<Rate, E> {
+                rate.checked_mul(RATE_SCALE)
+                    .map(Rate)
+                    .ok_or_else(|| E::custom("Cotización inválida"))
+            }
+
+            fn visit_u64<E: de::Error>(self, rate: u64) -> Result<Rate, E> {
+                i64::try_from(rate)
+                    .map_err(|_| E::custom("Cotización inválida"))
+                    .and_then(|rate| self.visit_i64(rate))
+            }
+        }
+
+        deserializer.deserialize_any(RateVisitor)
+    }
+}
+
+// Stored as an `INTEGER` number of millionths, so it round-trips exactly
+impl ToSql for Rate {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.0))
+    }
+}
+
+impl FromSql for Rate {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value.as_i64().map(Rate)
+    }
+}
+
+/// `text` as an integer number of `10^-decimals` units, rounding extra
+/// decimals half away from zero. `None` unless it is a plain decimal that
+/// fits.
+pub(crate) fn parse_fixed(text: &str, decimals: u32) -> Option<i64> {
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let mut units: i128 = 0;
+    for c in whole.chars() {
+        units = units.checked_mul(10)? + c.to_digit(10)? as i128;
+        if units > i64::MAX as i128 {
+            return None;
+        }
+    }
+    let mut kept = fraction.chars();
+    for _ in 0..decimals {
+        let digit = kept.next().and_then(|c| c.to_digit(10)).unwrap_or(0);
+        units = units * 10 + digit as i128;
+    }
+    if kept.next().is_some_and(|c| c >= '5') {
+        units += 1;
+    }
+
+    let units = if negative { -units } else { units };
+    i64::try_from(units).ok()
+}
+
+/// `n / d` rounded to the nearest integer, halves away from zero. `d` must
+/// be positive.
+fn div_round(n: i128, d: i128) -> i128 {
+    let quotient = n / d;
+    let remainder = n % d;
+    if remainder.abs() * 2 >= d {
+        quotient + n.signum()
+    } else {
+        quotient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn div_round_halves_away_from_zero() {
+        assert_eq!(div_round(10, 4), 3);
+        assert_eq!(div_round(9, 4), 2);
+        assert_eq!(div_round(-10, 4), -3);
+        assert_eq!(div_round(-9, 4), -2);
+        assert_eq!(div_round(7, 7), 1);
+        assert_eq!(div_round(0, 3), 0);
+    }
+
+    #[test]
+    fn prorated_shares() {
+        assert_eq!(prorate(1_000, 1, 3), 333);
+        assert_eq!(prorate(1_000, 2, 3), 667);
+        assert_eq!(prorate(-1_001, 1, 2), -501);
+        // Large amounts do not overflow before dividing
+        assert_eq!(prorate(i64::MAX, 2, 4), i64::MAX / 2 + 1);
+    }
+
+    #[test]
+    fn parses_fixed_decimals() {
+        assert_eq!(parse_fixed("1050.25", 6), Some(1_050_250_000));
+        assert_eq!(parse_fixed("+3", 2), Some(300));
+        assert_eq!(parse_fixed(".5", 2), Some(50));
+        assert_eq!(parse_fixed("7.", 2), Some(700));
+        assert_eq!(parse_fixed("10.005", 2), Some(1_001));
+        assert_eq!(parse_fixed("-10.005", 2), Some(-1_001));
+        assert_eq!(parse_fixed("10.0049", 2), Some(1_000));
+        assert_eq!(parse_fixed("92233720368547758.07", 2), Some(i64::MAX));
+        for text in ["", "-", ".", "1,5", "1e3", "1.2.3", "92233720368547758.08"] {
+            assert_eq!(parse_fixed(text, 2), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn decimal_amounts_round_as_written() {
+        assert_eq!(cents_from_decimal(10.005), Some(1_001));
+        assert_eq!(cents_from_decimal(-0.125), Some(-13));
+        assert_eq!(cents_from_decimal(f64::NAN), None);
+        assert_eq!(format_cents(-123_450), "-1234.50");
+        assert_eq!(format_cents(5), "0.05");
+    }
+
+    #[test]
+    fn converts_at_a_rate() {
+        let rate = Rate::parse("1180.5").unwrap();
+        assert_eq!(rate.to_string(), "1180.5");
+        assert_eq!(Rate::parse("1000").unwrap().to_string(), "1000");
+        assert_eq!(
+            Money::new(1_234, Currency::Usd).to_ars(rate).unwrap(),
+            Money::ars(1_456_737)
+        );
+        assert!(Money::new(i64::MAX, Currency::Usd).to_ars(rate).is_err());
+        assert!(Rate::parse("mil").is_err());
+    }
+
+    #[test]
+    fn rates_round_trip_as_integers() {
+        let db = rusqlite::Connection::open_in_memory().unwrap();
+        let rate = Rate::parse("1180.123456").unwrap();
+        let (stored, kind): (Rate, String) = db
+            .query_row("SELECT ?1, typeof(?1)", [rate], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((stored, kind.as_str()), (rate, "integer"));
+    }
+}
@@ -0,0 +1,67 @@
+//! Matching free-text descriptions to payees.
+//!
+//! A payee is found through the alias key of a description: its distinct
+//! words, as used for duplicate fingerprints, so "COTO 1234" and "coto" are
+//! the same alias while "Coto Palermo" needs an alias of its own. To suggest
+//! new payees, descriptions are grouped more loosely by their first
+//! meaningful word ("COTO 1234", "Coto Palermo" and "coto" all give "coto").
+
+use crate::dedup::description_tokens;
+use crate::import::normalize_text;
+
+/// Words that describe how a payment was made rather than who was paid.
+const NOISE_WORDS: [&str; 24] = [
+    "compra",
+    "compras",
+    "pago",
+    "pagos",
+    "debito",
+    "credito",
+    "automatico",
+    "transferencia",
+    "recibida",
+    "enviada",
+    "del",
+    "las",
+    "los",
+    "con",
+    "por",
+    "para",
+    "tarjeta",
+    "visa",
+    "master",
+    "mastercard",
+    "cuota",
+    "cuotas",
+    "mercadopago",
+    "www",
+];
+
+/// Key used to match descriptions against payee names and aliases.
+pub fn alias_key(text: &str) -> String {
+    description_tokens(text).join(" ")
+}
+
+/// First meaningful word of a description, used to group descriptions that
+/// probably name the same merchant. `None` when nothing is left after
+/// dropping numbers, short words and payment jargon.
+pub fn merchant_key(description: &str) -> Option<String> {
+    normalize_text(description)
+        .split(|c: char| !c.is_alphanumeric())
+        .find(|word| {
+            word.chars().count() >= 3
+                && !word.chars().all(|c| c.is_ascii_digit())
+                && !NOISE_WORDS.contains(word)
+        })
+        .map(str::to_string)
+}
+
+/// Payee name proposed for a merchant key: the key with its first letter
+/// uppercased ("coto" -> "Coto").
+pub fn suggested_name(key: &str) -> String {
+    let mut chars = key.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,270 @@
+//! Parsing of one-line entries such as "café 1.500 ayer",
+//! "uber 12 usd a 1180 el 3/5" or "+sueldo 850000".
+//!
+//! Words are read left to right: the first number is the amount (es-AR
+//! format, `1.500,50`), currency words or symbols set the currency, "a",
+//! "@" or "cotización" followed by a number after the amount give the
+//! exchange rate, and date words ("hoy", "ayer", "anteayer", weekdays,
+//! "hace 3 días", "3/5", "3/5/26") the date. A leading `+` marks income and
+//! a leading `-` an expense. Whatever is left is the description.
+
+use crate::import::{normalize_text, parse_amount_cents, parse_date, DateOrder};
+use crate::models::TransactionType;
+use crate::money::{Currency, Rate};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+/// What the text said explicitly; `None` where it said nothing.
+#[derive(Debug, Default)]
+pub struct QuickEntry {
+    pub description: String,
+    /// Positive amount in centavos
+    pub amount_cents: Option<i64>,
+    pub currency: Option<Currency>,
+    pub exchange_rate: Option<Rate>,
+    pub date: Option<NaiveDate>,
+    pub r#type: Option<TransactionType>,
+}
+
+const RATE_WORDS: [&str; 5] = ["a", "@", "cotizacion", "tc", "dolar"];
+
+/// Millionths of a peso in a centavo, to turn an amount into a `Rate`
+const RATE_CENT: i64 = 10_000;
+
+/// Furthest back "hace N días" reaches; larger numbers are not dates
+const MAX_DAYS_AGO: u32 = 3_660;
+
+fn currency_word(word: &str) -> Option<Currency> {
+    match word {
+        "usd" | "u$s" | "us$" | "u$d" | "dolares" | "dls" | "dol" => Some(Currency::Usd),
+        "ars" | "$" | "pesos" | "peso" => Some(Currency::Ars),
+        _ => None,
+    }
+}
+
+fn weekday_word(word: &str) -> Option<Weekday> {
+    match word {
+        "lunes" => Some(Weekday::Mon),
+        "martes" => Some(Weekday::Tue),
+        "miercoles" => Some(Weekday::Wed),
+        "jueves" => Some(Weekday::Thu),
+        "viernes" => Some(Weekday::Fri),
+        "sabado" => Some(Weekday::Sat),
+        "domingo" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// A positive amount, optionally glued to a currency ("12usd", "$1500",
+/// "u$s20").
+fn amount_word(word: &str) -> Option<(i64, Option<Currency>)> {
+    let split = word
+        .find(|c: char| c.is_ascii_digit())
+        .zip(word.rfind(|c: char| c.is_ascii_digit()))?;
+    let (prefix, number, suffix) = (
+        &word[..split.0],
+        &word[split.0..=split.1],
+        &word[split.1 + 1..],
+    );
+    let currency = match (prefix, suffix) {
+        ("", "") => None,
+        (glued, "") | ("", glued) => Some(currency_word(glued)?),
+        _ => return None,
+    };
+
+    parse_amount_cents(number)
+        .filter(|&cents| cents > 0)
+        .map(|cents| (cents, currency))
+}
+
+/// `d/m` (the most recent such day up to `today`) or `d/m/yy[yy]`.
+fn date_word(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    if !word.contains('/') {
+        return None;
+    }
+    if let Some(date) = parse_date(word, DateOrder::Dmy) {
+        return Some(date);
+    }
+
+    let (day, month) = word.split_once('/')?;
+    let (day, month): (u32, u32) = (day.parse().ok()?, month.parse().ok()?);
+    let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if date > today {
+        NaiveDate::from_ymd_opt(today.year() - 1, month, day)
+    } else {
+        Some(date)
+    }
+}
+
+/// Days back from `today` to the last `weekday` (a week ago when today is
+/// that day).
+fn last_weekday(today: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let back = (today.weekday().num_days_from_monday() + 7 - weekday.num_days_from_monday()) % 7;
+    today - Duration::days(if back == 0 { 7 } else { back as i64 })
+}
+
+/// Parse `text` relative to `today`.
+pub fn parse(text: &str, today: NaiveDate) -> QuickEntry {
+    let mut entry = QuickEntry::default();
+    let mut text = text.trim();
+    if let Some(rest) = text.strip_prefix('+') {
+        entry.r#type = Some(TransactionType::Income);
+        text = rest;
+    } else if let Some(rest) = text.strip_prefix('-') {
+        entry.r#type = Some(TransactionType::Expense);
+        text = rest;
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let normalized: Vec<String> = words
+        .iter()
+        .map(|w| normalize_text(w).trim_end_matches([',', ';']).to_string())
+        .collect();
+    let mut description = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = normalized[i].as_str();
+        let next = normalized.get(i + 1).map(String::as_str);
+
+        // Dates
+        let date = match word {
+            "hoy" => Some((today, 1)),
+            "ayer" => Some((today - Duration::days(1), 1)),
+            "anteayer" => Some((today - Duration::days(2), 1)),
+            "el" => next
+                .and_then(|n| {
+                    date_word(n, today).or(weekday_word(n).map(|d| last_weekday(today, d)))
+                })
+                .map(|date| (date, 2)),
+            "hace" => next
+                .and_then(|n| n.parse::<u32>().ok())
+                .filter(|&days| days <= MAX_DAYS_AGO)
+                .filter(|_| {
+                    matches!(
+                        normalized.get(i + 2).map(String::as_str),
+                        Some("dia" | "dias")
+                    )
+                })
+                .and_then(|days| Duration::try_days(days.into()))
+                .and_then(|back| today.checked_sub_signed(back))
+                .map(|date| (date, 3)),
+            _ => date_word(word, today)
+                .or(weekday_word(word).map(|d| last_weekday(today, d)))
+                .map(|date| (date, 1)),
+        };
+        if let Some((date, consumed)) = date.filter(|_| entry.date.is_none()) {
+            entry.date = Some(date);
+            i += consumed;
+            continue;
+        }
+
+        // Exchange rate, only once the amount is known
+        if entry.amount_cents.is_some() && entry.exchange_rate.is_none() {
+            let rate = if let Some(glued) = word.strip_prefix('@').filter(|g| !g.is_empty()) {
+                parse_amount_cents(glued).map(|cents| (cents, 1))
+            } else if RATE_WORDS.contains(&word) {
+                next.and_then(parse_amount_cents).map(|cents| (cents, 2))
+            } else {
+                None
+            };
+            // Read like an amount, so with at most two decimals
+            let rate = rate.and_then(|(cents, consumed)| {
+                let millionths = cents.checked_mul(RATE_CENT)?;
+                Some((Rate::from_millionths(millionths), consumed))
+            });
+            if let Some((rate, consumed)) = rate.filter(|(rate, _)| rate.millionths() > 0) {
+                entry.exchange_rate = Some(rate);
+                i += consumed;
+                continue;
+            }
+        }
+
+        // Currency and amount
+        if let Some(currency) = currency_word(word).filter(|_| entry.currency.is_none()) {
+            entry.currency = Some(currency);
+            i += 1;
+            continue;
+        }
+        if entry.amount_cents.is_none() {
+            if let Some((cents, currency)) = amount_word(word) {
+                entry.amount_cents = Some(cents);
+                if entry.currency.is_none() {
+                    entry.currency = currency;
+                }
+                i += 1;
+                continue;
+            }
+        }
+
+        description.push(words[i]);
+        i += 1;
+    }
+
+    entry.description = description
+        .join(" ")
+        .trim_matches(|c: char| c.is_whitespace() || c == ',' || c == '-')
+        .to_string();
+    entry
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2026, 5, 10).unwrap()
+    }
+
+    #[test]
+    fn amount_and_relative_day() {
+        let entry = parse("café 1.500 ayer", today());
+        assert_eq!(entry.description, "café");
+        assert_eq!(entry.amount_cents, Some(150_000));
+        assert_eq!(entry.currency, None);
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 5, 9));
+        assert_eq!(entry.r#type, None);
+    }
+
+    #[test]
+    fn currency_rate_and_day_of_month() {
+        let entry = parse("uber 12 usd a 1180 el 3/5", today());
+        assert_eq!(entry.description, "uber");
+        assert_eq!(entry.amount_cents, Some(1_200));
+        assert_eq!(entry.currency, Some(Currency::Usd));
+        assert_eq!(
+            entry.exchange_rate,
+            Some(Rate::from_millionths(1_180_000_000))
+        );
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 5, 3));
+    }
+
+    #[test]
+    fn income_sign() {
+        let entry = parse("+sueldo 850000", today());
+        assert_eq!(entry.description, "sueldo");
+        assert_eq!(entry.amount_cents, Some(85_000_000));
+        assert_eq!(entry.r#type, Some(TransactionType::Income));
+        assert_eq!(entry.date, None);
+    }
+
+    #[test]
+    fn day_of_month_ahead_of_today_is_last_year() {
+        let entry = parse("seguro 20000 el 25/12", today());
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2025, 12, 25));
+    }
+
+    #[test]
+    fn days_ago() {
+        let entry = parse("farmacia 3200 hace 3 días", today());
+        assert_eq!(entry.description, "farmacia");
+        assert_eq!(entry.date, NaiveDate::from_ymd_opt(2026, 5, 7));
+    }
+
+    #[test]
+    fn days_ago_out_of_range_is_not_a_date() {
+        for text in ["hace 99999999999999 dias", "hace 4000 dias", "hace -3 dias"] {
+            let entry = parse(text, today());
+            assert_eq!(entry.date, None, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,200 @@
+//! Evaluation of user-defined categorization rules.
+//!
+//! Enabled rules run from the lowest priority number up (older rules first
+//! on ties). For the category and the payee the first matching rule that
+//! sets them wins; tags from every matching rule are added. A rule's
+//! category is only applied to movements of the same type as the category,
+//! and never to split transactions, whose lines carry their own categories.
+
+use crate::import::normalize_text;
+use crate::models::{PatternKind, Rule, Transaction, TransactionInput, TransactionType};
+use crate::money::Currency;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashMap;
+
+/// Compile a rule's regular expression. Matching ignores case.
+pub fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    RegexBuilder::new(pattern.trim())
+        .case_insensitive(true)
+        .size_limit(1 << 20)
+        .build()
+        .map_err(|e| format!("Expresión regular inválida: {}", e))
+}
+
+/// The fields of a movement that rules look at.
+pub struct Subject<'a> {
+    pub description: &'a str,
+    pub amount_cents: i64,
+    pub currency: Currency,
+    pub account: Option<&'a str>,
+    pub r#type: TransactionType,
+}
+
+impl<'a> Subject<'a> {
+    pub fn of_transaction(t: &'a Transaction) -> Self {
+        Subject {
+            description: &t.description,
+            amount_cents: t.amount.cents,
+            currency: t.currency(),
+            account: t.account.as_deref(),
+            r#type: t.r#type,
+        }
+    }
+}
+
+enum Pattern {
+    /// Normalized text to look for
+    Contains(String),
+    StartsWith(String),
+    Regex(Regex),
+}
+
+/// A rule ready to be matched.
+pub struct CompiledRule {
+    pub rule: Rule,
+    pattern: Option<Pattern>,
+    /// Type of the category the rule sets
+    category_type: Option<TransactionType>,
+}
+
+impl CompiledRule {
+    pub fn new(rule: Rule, category_type: Option<TransactionType>) -> Result<Self, String> {
+        let pattern = match &rule.pattern {
+            None => None,
+            Some(pattern) => Some(match rule.pattern_kind {
+                PatternKind::Contains => Pattern::Contains(normalize_text(pattern)),
+                PatternKind::StartsWith => Pattern::StartsWith(normalize_text(pattern)),
+                PatternKind::Regex => Pattern::Regex(
+                    compile_regex(pattern).map_err(|e| format!("Regla '{}': {}", rule.name, e))?,
+                ),
+            }),
+        };
+
+        Ok(CompiledRule {
+            rule,
+            pattern,
+            category_type,
+        })
+    }
+
+    pub fn matches(&self, subject: &Subject) -> bool {
+        let rule = &self.rule;
+        let matches_pattern = match &self.pattern {
+            None => true,
+            Some(Pattern::Contains(text)) => normalize_text(subject.description).contains(text),
+            Some(Pattern::StartsWith(text)) => {
+                normalize_text(subject.description).starts_with(text)
+            }
+            // Also tried without accents, so "netflix" finds "Netflíx"
+            Some(Pattern::Regex(regex)) => {
+                regex.is_match(subject.description)
+                    || regex.is_match(&normalize_text(subject.description))
+            }
+        };
+
+        matches_pattern
+            && rule
+                .min_amount_cents
+                .is_none_or(|min| subject.amount_cents >= min)
+            && rule
+                .max_amount_cents
+                .is_none_or(|max| subject.amount_cents <= max)
+            && rule
+                .currency
+                .is_none_or(|currency| currency == subject.currency)
+            && rule.account.as_ref().is_none_or(|account| {
+                subject
+                    .account
+                    .is_some_and(|a| a.trim().eq_ignore_ascii_case(account.trim()))
+            })
+    }
+
+    /// The rule's category, if it suits a movement of `type`.
+    pub fn category_for(&self, r#type: TransactionType) -> Option<i64> {
+        self.rule
+            .category_id
+            .filter(|_| self.category_type == Some(r#type))
+    }
+}
+
+/// What the matching rules set on a movement.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub category_id: Option<i64>,
+    pub payee_id: Option<i64>,
+    pub tag_ids: Vec<i64>,
+}
+
+/// Every enabled rule, in evaluation order. Loaded once per operation.
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+}
+
+impl RuleSet {
+    /// Build the set from stored rules; `category_types` maps category ids
+    /// to their type.
+    pub fn new(
+        rules: Vec<Rule>,
+        category_types: &HashMap<i64, TransactionType>,
+    ) -> Result<Self, String> {
+        let mut rules = rules
+            .into_iter()
+            .filter(|rule| rule.enabled)
+            .map(|rule| {
+                let category_type = rule
+                    .category_id
+                    .and_then(|id| category_types.get(&id).copied());
+                CompiledRule::new(rule, category_type)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        rules.sort_by_key(|r| (r.rule.priority, r.rule.id));
+
+        Ok(RuleSet { rules })
+    }
+
+    pub fn evaluate(&self, subject: &Subject) -> Outcome {
+        let mut outcome = Outcome::default();
+        for compiled in self.rules.iter().filter(|r| r.matches(subject)) {
+            if outcome.category_id.is_none() {
+                outcome.category_id = compiled.category_for(subject.r#type);
+            }
+            if outcome.payee_id.is_none() {
+                outcome.payee_id = compiled.rule.payee_id;
+            }
+            for &tag_id in &compiled.rule.tag_ids {
+                if !outcome.tag_ids.contains(&tag_id) {
+                    outcome.tag_ids.push(tag_id);
+                }
+            }
+        }
+        outcome
+    }
+
+    /// Apply the matching rules to a movement about to be stored. What the
+    /// rules set replaces the input's category and payee; their tags are
+    /// added to the input's.
+    pub fn apply(&self, input: &mut TransactionInput, account: Option<&str>) {
+        let outcome = self.evaluate(&Subject {
+            description: &input.description,
+            amount_cents: input.amount.cents,
+            currency: input.amount.currency,
+            account,
+            r#type: input.r#type,
+        });
+
+        if let Some(category_id) = outcome.category_id.filter(|_| input.splits.is_empty()) {
+            input.category_id = category_id;
+        }
+        if let Some(payee_id) = outcome.payee_id {
+            input.payee_id = Some(payee_id);
+        }
+        if !outcome.tag_ids.is_empty() {
+            let tag_ids = input.tag_ids.get_or_insert_with(Vec::new);
+            for tag_id in outcome.tag_ids {
+                if !tag_ids.contains(&tag_id) {
+                    tag_ids.push(tag_id);
+                }
+            }
+        }
+    }
+}
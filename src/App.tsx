@@ -6,7 +6,7 @@ import { CategoryManager } from './components/features/categories';
 import { TransactionForm, TransactionList } from './components/features/transactions';
 import { Dashboard } from './components/features/Dashboard';
 import { Tabs, TabsContent, TabsList, TabsTrigger } from './components/ui/tabs';
-import { Transaction } from './types';
+import { TransactionInput } from './types';
 import { LayoutDashboard, Plus, History, Tag, TrendingUp } from 'lucide-react';
 
 function App() {
@@ -28,7 +28,7 @@ function App() {
 
   const [activeTab, setActiveTab] = useState('dashboard');
 
-  const handleAddTransaction = async (transaction: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => {
+  const handleAddTransaction = async (transaction: TransactionInput) => {
     try {
       await addTransaction(transaction);
       toast.success('Transacción agregada correctamente');
@@ -37,7 +37,7 @@ function App() {
     }
   };
 
-  const handleUpdateTransaction = async (id: number, transaction: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => {
+  const handleUpdateTransaction = async (id: number, transaction: TransactionInput) => {
     try {
       await updateTransaction(id, transaction);
       toast.success('Transacción actualizada correctamente');
@@ -10,8 +10,8 @@ import { CategoryFormDialog } from './CategoryFormDialog';
 interface CategoryManagerProps {
     categories: Category[];
     transactions: Transaction[];
-    onAddCategory: (category: Omit<Category, 'id' | 'is_default'>) => void;
-    onUpdateCategory: (id: number, updates: Partial<Omit<Category, 'id' | 'is_default'>>) => void;
+    onAddCategory: (category: Omit<Category, 'id' | 'is_default' | 'version'>) => void;
+    onUpdateCategory: (id: number, updates: Partial<Omit<Category, 'id' | 'is_default' | 'version'>>) => void;
     onDeleteCategory: (id: number) => void;
 }
 
@@ -66,7 +66,8 @@ export function CategoryManager({
     };
 
     const handleDelete = (id: number) => {
-        const hasTransactions = transactions.some(t => t.category_id === id);
+        const hasTransactions = transactions.some(t =>
+            t.category_id === id || t.splits?.some(s => s.category_id === id));
         if (hasTransactions) {
             confirm({
                 title: 'No se puede eliminar',
@@ -1,7 +1,8 @@
 import { useMemo } from 'react';
+import { parseISO } from 'date-fns';
 import { Transaction, Category } from '../../../types';
-import { getCategoryName } from '../../../utils/categoryHelpers';
-import { formatCurrency } from '../../../lib/utils';
+import { getCategoryName, getCategoryShares } from '../../../utils/categoryHelpers';
+import { formatCurrency, fromMoney } from '../../../lib/utils';
 import { Card, CardHeader, CardTitle, CardContent, CardDescription } from '../../ui/card';
 
 interface CashFlowTableProps {
@@ -16,7 +17,7 @@ export function CashFlowTable({ transactions, categories, selectedYear }: CashFl
     const { expenseRows, incomeRows, expenseTotals, incomeTotals, netBalance, accumulatedBalance } = useMemo(() => {
         // Filter transactions for the selected year only (ignores month filter)
         const yearTx = transactions.filter(t =>
-            new Date(t.date).getFullYear().toString() === selectedYear
+            parseISO(t.date).getFullYear().toString() === selectedYear
         );
 
         // Group by type → category → month
@@ -24,9 +25,11 @@ export function CashFlowTable({ transactions, categories, selectedYear }: CashFl
             const catMap: Record<number, number[]> = {};
 
             yearTx.filter(t => t.type === type).forEach(t => {
-                const monthIdx = new Date(t.date).getMonth();
-                if (!catMap[t.category_id]) catMap[t.category_id] = new Array(12).fill(0);
-                catMap[t.category_id][monthIdx] += t.amount_in_ars;
+                const monthIdx = parseISO(t.date).getMonth();
+                getCategoryShares(t).forEach(share => {
+                    if (!catMap[share.category_id]) catMap[share.category_id] = new Array(12).fill(0);
+                    catMap[share.category_id][monthIdx] += fromMoney(share.amount_in_ars);
+                });
             });
 
             return Object.entries(catMap)
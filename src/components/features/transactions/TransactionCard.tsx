@@ -1,7 +1,7 @@
 import { parseISO, format } from 'date-fns';
 import { es } from 'date-fns/locale';
 import { Category, Transaction } from '../../../types';
-import { formatCurrency, cn } from '../../../lib/utils';
+import { formatMoney, fromMoney, cn } from '../../../lib/utils';
 
 import { Button } from '../../ui/button';
 import { Card } from '../../ui/card';
@@ -47,7 +47,7 @@ export function TransactionCard({ transaction, category, onEdit, onDelete }: Tra
                             "font-bold text-lg whitespace-nowrap",
                             isExpense ? "text-destructive" : "text-green-600"
                         )}>
-                            {isExpense ? '-' : '+'}{formatCurrency(transaction.amount_in_ars, 'ARS')}
+                            {isExpense ? '-' : '+'}{formatMoney(transaction.amount_in_ars)}
                         </p>
                     </div>
 
@@ -59,9 +59,9 @@ export function TransactionCard({ transaction, category, onEdit, onDelete }: Tra
                             >
                                 {category?.name ?? 'Sin categoría'}
                             </span>
-                            {transaction.currency === 'USD' && (
+                            {transaction.amount.currency === 'USD' && (
                                 <span className="text-xs bg-yellow-100 text-yellow-800 px-1.5 py-0.5 rounded border border-yellow-200">
-                                    USD {transaction.amount.toFixed(2)} (TC: {new Intl.NumberFormat('es-AR', { minimumFractionDigits: 2, maximumFractionDigits: 2 }).format(transaction.exchange_rate ?? 0)})
+                                    USD {fromMoney(transaction.amount).toFixed(2)} (TC: {new Intl.NumberFormat('es-AR', { minimumFractionDigits: 2, maximumFractionDigits: 2 }).format(Number(transaction.exchange_rate ?? 0))})
                                 </span>
                             )}
                         </div>
@@ -1,5 +1,6 @@
 import { useState } from 'react';
-import { Category, Transaction } from '../../../types';
+import { format, parseISO } from 'date-fns';
+import { Category, Transaction, TransactionInput } from '../../../types';
 import { Input } from '../../ui/input';
 import { Label } from '../../ui/label';
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '../../ui/select';
@@ -9,13 +10,14 @@ import { TypeSelector } from '../shared/TypeSelector';
 import { CategorySelect } from '../shared/CategorySelect';
 import { ExchangeRateField } from '../shared/ExchangeRateField';
 import { DatePickerField } from '../../ui/DatePickerField';
+import { fromMoney, toMoney } from '../../../lib/utils';
 
 interface TransactionEditDialogProps {
     open: boolean;
     onOpenChange: (open: boolean) => void;
     transaction: Transaction | null;
     categories: Category[];
-    onSave: (id: number, data: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => void;
+    onSave: (id: number, data: TransactionInput) => void;
 }
 
 export function TransactionEditDialog({ open, onOpenChange, transaction, categories, onSave }: TransactionEditDialogProps) {
@@ -31,11 +33,11 @@ export function TransactionEditDialog({ open, onOpenChange, transaction, categor
     const handleOpenChange = (isOpen: boolean) => {
         if (isOpen && transaction) {
             setDescription(transaction.description);
-            setAmount(transaction.amount);
-            setCurrency(transaction.currency);
-            setExchangeRate(transaction.exchange_rate ?? undefined);
+            setAmount(fromMoney(transaction.amount));
+            setCurrency(transaction.amount.currency);
+            setExchangeRate(transaction.exchange_rate ? Number(transaction.exchange_rate) : undefined);
             setCategoryId(transaction.category_id);
-            setDate(new Date(transaction.date));
+            setDate(parseISO(transaction.date));
             setType(transaction.type);
         }
         onOpenChange(isOpen);
@@ -44,19 +46,16 @@ export function TransactionEditDialog({ open, onOpenChange, transaction, categor
     const handleSave = () => {
         if (!transaction || !description.trim() || amount <= 0) return;
 
-        const amountInARS = currency === 'USD'
-            ? (amount * (exchangeRate || 1))
-            : amount;
-
         onSave(transaction.id, {
             description,
-            amount,
-            amount_in_ars: amountInARS,
-            currency,
-            exchange_rate: exchangeRate ?? null,
+            amount: toMoney(amount, currency),
+            exchange_rate: currency === 'USD' && exchangeRate ? String(exchangeRate) : null,
             category_id: categoryId,
-            date: date.toISOString(),
+            date: format(date, 'yyyy-MM-dd'),
             type,
+            payee_id: transaction.payee_id,
+            invoice: transaction.invoice,
+            splits: transaction.splits,
         });
 
         onOpenChange(false);
@@ -1,8 +1,9 @@
 import { useState } from 'react';
+import { format } from 'date-fns';
 import { useForm } from 'react-hook-form';
 import { zodResolver } from '@hookform/resolvers/zod';
 import * as z from 'zod';
-import { Category, Transaction } from '../../../types';
+import { Category, TransactionInput } from '../../../types';
 import { Button } from '../../ui/button';
 import { Input } from '../../ui/input';
 import { Label } from '../../ui/label';
@@ -14,6 +15,7 @@ import { TypeSelector } from '../shared/TypeSelector';
 import { CategorySelect } from '../shared/CategorySelect';
 import { ExchangeRateField } from '../shared/ExchangeRateField';
 import { DatePickerField } from '../../ui/DatePickerField';
+import { toMoney } from '../../../lib/utils';
 
 const transactionSchema = z.object({
     description: z.string().min(1, 'La descripción es obligatoria'),
@@ -36,7 +38,7 @@ const transactionSchema = z.object({
 type TransactionFormValues = z.infer<typeof transactionSchema>;
 
 interface TransactionFormProps {
-    onAddTransaction: (transaction: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => void;
+    onAddTransaction: (transaction: TransactionInput) => void;
     categories: Category[];
 }
 
@@ -60,19 +62,18 @@ export function TransactionForm({ onAddTransaction, categories }: TransactionFor
     const selectedCategoryId = watch('categoryId');
 
     const onSubmit = (data: TransactionFormValues) => {
-        const amountInARS = data.currency === 'USD'
-            ? (data.amount * (data.exchangeRate || 1))
-            : data.amount;
+        const exchangeRate = data.currency === 'USD' && data.exchangeRate ? String(data.exchangeRate) : null;
 
         onAddTransaction({
             description: data.description,
-            amount: data.amount,
-            amount_in_ars: amountInARS,
-            currency: data.currency,
-            exchange_rate: data.exchangeRate ?? null,
+            amount: toMoney(data.amount, data.currency),
+            exchange_rate: exchangeRate,
             category_id: data.categoryId,
-            date: data.date.toISOString(),
+            date: format(data.date, 'yyyy-MM-dd'),
             type: data.type,
+            payee_id: null,
+            invoice: null,
+            splits: [],
         });
 
         reset({
@@ -1,5 +1,6 @@
 import { useState, useMemo } from 'react';
-import { Category, Transaction } from '../../../types';
+import { parseISO } from 'date-fns';
+import { Category, Transaction, TransactionInput } from '../../../types';
 import { getAvailableYears } from '../../../utils/dateHelpers';
 import { getCategoryById } from '../../../utils/categoryHelpers';
 import { TransactionFilters } from './TransactionFilters';
@@ -14,7 +15,7 @@ interface TransactionListProps {
     transactions: Transaction[];
     categories: Category[];
     onDeleteTransaction: (id: number) => void;
-    onUpdateTransaction: (id: number, transaction: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => void;
+    onUpdateTransaction: (id: number, transaction: TransactionInput) => void;
 }
 
 export function TransactionList({ transactions, categories, onDeleteTransaction, onUpdateTransaction }: TransactionListProps) {
@@ -35,13 +36,13 @@ export function TransactionList({ transactions, categories, onDeleteTransaction,
     const filteredTransactions = useMemo(() => {
         return transactions
             .filter(t => {
-                const date = new Date(t.date);
+                const date = parseISO(t.date);
                 const matchYear = selectedYear === 'all' || date.getFullYear().toString() === selectedYear;
                 const matchMonth = selectedMonth === 'all' || (date.getMonth() + 1).toString() === selectedMonth;
                 const matchSearch = t.description.toLowerCase().includes(searchTerm.toLowerCase());
                 return matchYear && matchMonth && matchSearch;
             })
-            .sort((a, b) => new Date(b.date).getTime() - new Date(a.date).getTime());
+            .sort((a, b) => b.date.localeCompare(a.date));
     }, [transactions, selectedYear, selectedMonth, searchTerm]);
 
     // Pagination
@@ -74,7 +75,7 @@ export function TransactionList({ transactions, categories, onDeleteTransaction,
         setEditDialogOpen(true);
     };
 
-    const handleEditSave = (id: number, data: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => {
+    const handleEditSave = (id: number, data: TransactionInput) => {
         onUpdateTransaction(id, data);
         setEditingTransaction(null);
     };
@@ -1,6 +1,6 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Category } from '../types';
+import { Category, WriteError } from '../types';
 
 export function useCategories() {
     const [categories, setCategories] = useState<Category[]>([]);
@@ -26,7 +26,7 @@ export function useCategories() {
         fetchCategories();
     }, [fetchCategories]);
 
-    const addCategory = useCallback(async (input: Omit<Category, 'id' | 'is_default'>) => {
+    const addCategory = useCallback(async (input: Omit<Category, 'id' | 'is_default' | 'version'>) => {
         try {
             const created = await invoke<Category>('add_category', { input });
             setCategories(prev => [...prev, created]);
@@ -39,31 +39,42 @@ export function useCategories() {
         }
     }, []);
 
-    const updateCategory = useCallback(async (id: number, updates: Partial<Omit<Category, 'id' | 'is_default'>>) => {
+    // On a conflict, show the category as stored now so the edit can be redone
+    const handleWriteError = useCallback((e: unknown, id: number) => {
+        const err = e as WriteError<Category>;
+        if (err?.kind === 'conflict') {
+            setCategories(prev => prev.map(c => c.id === id ? err.current : c));
+        }
+        return err?.message ?? String(e);
+    }, []);
+
+    const updateCategory = useCallback(async (id: number, updates: Partial<Omit<Category, 'id' | 'is_default' | 'version'>>) => {
         // We need to send the full updates object to the backend
         try {
-            const updated = await invoke<Category>('update_category', { id, updates });
+            const version = categories.find(c => c.id === id)?.version;
+            const updated = await invoke<Category>('update_category', { id, updates, version });
             setCategories(prev => prev.map(c => c.id === id ? updated : c));
             setError(null);
             return updated;
         } catch (e) {
-            setError(String(e));
+            setError(handleWriteError(e, id));
             console.error('Error updating category:', e);
             throw e;
         }
-    }, []);
+    }, [categories, handleWriteError]);
 
     const deleteCategory = useCallback(async (id: number) => {
         try {
-            await invoke('delete_category', { id });
+            const version = categories.find(c => c.id === id)?.version;
+            await invoke('delete_category', { id, version });
             setCategories(prev => prev.filter(c => c.id !== id));
             setError(null);
         } catch (e) {
-            setError(String(e));
+            setError(handleWriteError(e, id));
             console.error('Error deleting category:', e);
             throw e;
         }
-    }, []);
+    }, [categories, handleWriteError]);
 
     return {
         categories,
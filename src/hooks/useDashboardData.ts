@@ -1,6 +1,8 @@
 import { useMemo } from 'react';
+import { parseISO } from 'date-fns';
 import { Transaction, Category } from '../types';
-import { getCategoryName, getCategoryColor } from '../utils/categoryHelpers';
+import { getCategoryName, getCategoryColor, getCategoryShares } from '../utils/categoryHelpers';
+import { fromMoney } from '../lib/utils';
 
 interface DashboardFilters {
     selectedYear: string;
@@ -38,7 +40,7 @@ export function useDashboardData(
 
     const filteredTransactions = useMemo(() => {
         return transactions.filter(t => {
-            const date = new Date(t.date);
+            const date = parseISO(t.date);
             const yearMatch = date.getFullYear().toString() === selectedYear;
             const monthMatch = selectedMonth === 'all' || (date.getMonth() + 1).toString() === selectedMonth;
             return yearMatch && monthMatch;
@@ -48,10 +50,10 @@ export function useDashboardData(
     const summary: DashboardSummary = useMemo(() => {
         const income = filteredTransactions
             .filter(t => t.type === 'income')
-            .reduce((acc, t) => acc + t.amount_in_ars, 0);
+            .reduce((acc, t) => acc + t.amount_in_ars.cents, 0) / 100;
         const expenses = filteredTransactions
             .filter(t => t.type === 'expense')
-            .reduce((acc, t) => acc + t.amount_in_ars, 0);
+            .reduce((acc, t) => acc + t.amount_in_ars.cents, 0) / 100;
         const balance = income - expenses;
         const savingsRate = income > 0 ? ((income - expenses) / income) * 100 : 0;
         return { income, expenses, balance, savingsRate };
@@ -65,11 +67,11 @@ export function useDashboardData(
         });
 
         transactions
-            .filter(t => new Date(t.date).getFullYear().toString() === selectedYear)
+            .filter(t => parseISO(t.date).getFullYear().toString() === selectedYear)
             .forEach(t => {
-                const monthIndex = new Date(t.date).getMonth();
-                if (t.type === 'income') months[monthIndex].Ingresos += t.amount_in_ars;
-                else months[monthIndex].Gastos += t.amount_in_ars;
+                const monthIndex = parseISO(t.date).getMonth();
+                if (t.type === 'income') months[monthIndex].Ingresos += fromMoney(t.amount_in_ars);
+                else months[monthIndex].Gastos += fromMoney(t.amount_in_ars);
             });
 
         return months;
@@ -80,8 +82,10 @@ export function useDashboardData(
         const grouped: Record<number, number> = {};
 
         expenses.forEach(t => {
-            if (!grouped[t.category_id]) grouped[t.category_id] = 0;
-            grouped[t.category_id] += t.amount_in_ars;
+            getCategoryShares(t).forEach(share => {
+                if (!grouped[share.category_id]) grouped[share.category_id] = 0;
+                grouped[share.category_id] += fromMoney(share.amount_in_ars);
+            });
         });
 
         return Object.entries(grouped)
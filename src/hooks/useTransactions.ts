@@ -1,6 +1,7 @@
 import { useState, useEffect, useCallback } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { Transaction } from '../types';
+import { parseISO } from 'date-fns';
+import { AddTransactionResult, Transaction, TransactionInput, WriteError } from '../types';
 
 export function useTransactions() {
     const [transactions, setTransactions] = useState<Transaction[]>([]);
@@ -26,9 +27,24 @@ export function useTransactions() {
         fetchTransactions();
     }, [fetchTransactions]);
 
-    const addTransaction = useCallback(async (input: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => {
+    const addTransaction = useCallback(async (input: TransactionInput) => {
         try {
-            const created = await invoke<Transaction>('add_transaction', { input });
+            let result = await invoke<AddTransactionResult>('add_transaction', { input });
+            if (result.status === 'possible_duplicates') {
+                const [match] = result.duplicates;
+                const confirmed = window.confirm(
+                    `Ya existe una transacción parecida: "${match.transaction.description}" ` +
+                    `del ${parseISO(match.transaction.date).toLocaleDateString('es-AR')}. ¿Agregarla de todos modos?`
+                );
+                if (!confirmed) {
+                    throw new Error('Transacción duplicada descartada');
+                }
+                result = await invoke<AddTransactionResult>('add_transaction', { input, allowDuplicate: true });
+            }
+            if (result.status !== 'created') {
+                throw new Error('No se pudo agregar la transacción');
+            }
+            const created = result.transaction;
             setTransactions(prev => [created, ...prev]);
             setError(null);
             return created;
@@ -39,30 +55,41 @@ export function useTransactions() {
         }
     }, []);
 
+    // On a conflict, show the transaction as stored now so the edit can be redone
+    const handleWriteError = useCallback((e: unknown, id: number) => {
+        const err = e as WriteError<Transaction>;
+        if (err?.kind === 'conflict') {
+            setTransactions(prev => prev.map(t => t.id === id ? err.current : t));
+        }
+        return err?.message ?? String(e);
+    }, []);
+
     const deleteTransaction = useCallback(async (id: number) => {
         try {
-            await invoke('delete_transaction', { id });
+            const version = transactions.find(t => t.id === id)?.version;
+            await invoke('delete_transaction', { id, version });
             setTransactions(prev => prev.filter(t => t.id !== id));
             setError(null);
         } catch (e) {
-            setError(String(e));
+            setError(handleWriteError(e, id));
             console.error('Error deleting transaction:', e);
             throw e;
         }
-    }, []);
+    }, [transactions, handleWriteError]);
 
-    const updateTransaction = useCallback(async (id: number, input: Omit<Transaction, 'id' | 'created_at' | 'updated_at'>) => {
+    const updateTransaction = useCallback(async (id: number, input: TransactionInput) => {
         try {
-            const updated = await invoke<Transaction>('update_transaction', { id, input });
+            const version = transactions.find(t => t.id === id)?.version;
+            const updated = await invoke<Transaction>('update_transaction', { id, input, version });
             setTransactions(prev => prev.map(t => t.id === id ? updated : t));
             setError(null);
             return updated;
         } catch (e) {
-            setError(String(e));
+            setError(handleWriteError(e, id));
             console.error('Error updating transaction:', e);
             throw e;
         }
-    }, []);
+    }, [transactions, handleWriteError]);
 
     return {
         transactions,
@@ -1,11 +1,12 @@
 import { type ClassValue, clsx } from "clsx"
 import { twMerge } from "tailwind-merge"
+import type { Currency, Money } from "../types"
 
 export function cn(...inputs: ClassValue[]) {
     return twMerge(clsx(inputs))
 }
 
-export const formatCurrency = (amount: number, currency: 'ARS' | 'USD' = 'ARS') => {
+export const formatCurrency = (amount: number, currency: Currency = 'ARS') => {
     return new Intl.NumberFormat('es-AR', {
         style: 'currency',
         currency: currency,
@@ -13,3 +14,13 @@ export const formatCurrency = (amount: number, currency: 'ARS' | 'USD' = 'ARS')
         maximumFractionDigits: 2,
     }).format(amount);
 };
+
+// Amounts cross the API as whole centavos; the UI edits and sums decimals
+export const toMoney = (amount: number, currency: Currency = 'ARS'): Money => ({
+    cents: Math.round(amount * 100),
+    currency,
+});
+
+export const fromMoney = (money: Money) => money.cents / 100;
+
+export const formatMoney = (money: Money) => formatCurrency(fromMoney(money), money.currency);
@@ -2,6 +2,15 @@ export type Currency = 'ARS' | 'USD';
 
 export type TransactionType = 'income' | 'expense';
 
+// Whole centavos of a currency; never a float amount
+export interface Money {
+    cents: number;
+    currency: Currency;
+}
+
+// Pesos per unit of a foreign currency as a decimal string, e.g. "1050.25"
+export type Rate = string;
+
 export interface Category {
     id: number;
     name: string;
@@ -9,22 +18,236 @@ export interface Category {
     icon: string; // lucide-react icon name
     color: string;
     is_default: boolean;
+    version: number; // bumped on every write; sent back with updates and deletes
 }
 
+// Fields to change in a category
+export type CategoryPatch = Partial<Pick<Category, 'name' | 'type' | 'icon' | 'color'>>;
+
 export interface Transaction {
     id: number;
     description: string;
-    amount: number;
-    amount_in_ars: number;
-    currency: Currency;
-    exchange_rate: number | null;
+    amount: Money; // in the currency of the transaction
+    amount_in_ars: Money;
+    exchange_rate: Rate | null;
     category_id: number;
-    date: string; // ISO 8601 string
+    date: string; // calendar day, YYYY-MM-DD
     type: TransactionType;
-    created_at: string; // ISO 8601 string
-    updated_at: string; // ISO 8601 string
+    payee_id: number | null;
+    invoice: Invoice | null; // set when entered from an invoice QR
+    splits: TransactionSplit[]; // empty unless split across categories
+    tags: Tag[];
+    attachments: Attachment[];
+    created_at: string; // UTC, like Date.toISOString()
+    updated_at: string; // UTC, like Date.toISOString()
+    version: number; // bumped on every write; sent back with updates and deletes
+}
+
+// What add/update send; omitting tag_ids on update keeps the current tags.
+// amount_in_ars is derived by the backend; if sent it must match
+export type TransactionInput = Omit<Transaction, 'id' | 'amount_in_ars' | 'created_at' | 'updated_at' | 'version' | 'tags' | 'attachments'> & {
+    amount_in_ars?: Money;
+    tag_ids?: number[];
+};
+
+// Fields to change; null clears exchange_rate, payee_id or invoice
+export type TransactionPatch = Partial<TransactionInput>;
+
+// Electronic invoice a transaction was entered from
+export interface Invoice {
+    number: string; // e.g. "Factura B 00003-00012345"
+    issuer_cuit: string; // "30-71234567-9"
+}
+
+export interface Tag {
+    id: number;
+    name: string;
+}
+
+export interface Payee {
+    id: number;
+    name: string;
+    aliases: string[];
+    default_category_id: number | null;
+    notes: string | null;
+    transaction_count: number;
+    created_at: string;
+    updated_at: string;
 }
 
+export type PatternKind = 'contains' | 'starts_with' | 'regex';
+
+// Categorization rule; unset conditions match anything
+export interface Rule {
+    id: number;
+    name: string;
+    priority: number; // lowest runs first
+    enabled: boolean;
+    pattern: string | null;
+    pattern_kind: PatternKind;
+    min_amount_cents: number | null;
+    max_amount_cents: number | null;
+    currency: Currency | null;
+    account: string | null;
+    category_id: number | null;
+    payee_id: number | null;
+    tag_ids: number[];
+    created_at: string;
+    updated_at: string;
+}
+
+export interface CategorySuggestion {
+    category_id: number;
+    confidence: number; // 0..1
+}
+
+export type DraftField =
+    | 'description'
+    | 'amount'
+    | 'currency'
+    | 'exchange_rate'
+    | 'date'
+    | 'type'
+    | 'category'
+    | 'payee';
+
+// Result of parse_quick_entry; nothing is stored until the draft is confirmed
+export interface QuickEntryDraft {
+    input: TransactionInput;
+    inferred: DraftField[];
+    missing: DraftField[];
+    suggestions: CategorySuggestion[];
+}
+
+// Inclusive range of calendar days (YYYY-MM-DD); open ends are unbounded
+export interface Period {
+    from?: string | null;
+    to?: string | null;
+}
+
+export interface TransactionFilter {
+    period?: Period;
+    category_id?: number | null; // main category or any split line
+    payee_id?: number | null;
+    tag_ids?: number[];
+    tag_match?: 'any' | 'all';
+}
+
+// Transactions a bulk command acts on
+export type BulkSelection = { ids: number[] } | { filter: TransactionFilter };
+
+export type BulkOperation =
+    | { op: 'delete' }
+    | { op: 'set_category'; category_id: number }
+    | { op: 'set_date'; date: string } // YYYY-MM-DD
+    | { op: 'add_tags'; tag_ids: number[] }
+    | { op: 'remove_tags'; tag_ids: number[] }
+    | { op: 'set_exchange_rate'; exchange_rate: Rate }; // recomputes amount_in_ars
+
+// Row found by repair_amounts_in_ars whose stored amount_in_ars had drifted
+export interface AmountRepair {
+    id: number;
+    description: string;
+    amount: Money;
+    exchange_rate: Rate | null;
+    stored: Money;
+    amount_in_ars: Money | null; // null when it could not be derived
+    error: string | null;
+}
+
+export interface BulkItemResult {
+    id: number;
+    error: string | null; // null when the transaction was changed
+}
+
+export type Entity = 'transaction' | 'category';
+
+export type AuditAction = 'create' | 'update' | 'delete' | 'restore' | 'purge';
+export type AuditOrigin =
+    | 'manual'
+    | 'import'
+    | 'rule'
+    | 'backup'
+    | 'retention'
+    | 'undo'
+    | 'redo';
+
+// One recorded change, with the row as it was before and after it
+export interface AuditEntry {
+    id: number;
+    entity: Entity;
+    entity_id: number;
+    action: AuditAction;
+    origin: AuditOrigin;
+    before: Transaction | Category | null; // null for creations and restores
+    after: Transaction | Category | null; // null for deletions and purges
+    created_at: string;
+}
+
+// A command whose changes can be undone as a whole
+export interface UndoStep {
+    id: number;
+    label: string;
+    created_at: string;
+}
+
+export interface UndoStack {
+    undo: UndoStep[]; // newest first
+    redo: UndoStep[]; // next to redo first
+}
+
+// A deleted row, restorable until the trash is purged
+export type Trashed<T> = T & { deleted_at: string };
+
+export interface Trash {
+    transactions: Trashed<Transaction>[];
+    categories: Trashed<Category>[];
+    retention_days: number; // 0 = only purged by hand
+}
+
+export interface PurgeSummary {
+    transactions: number;
+    categories: number;
+}
+
+export interface Attachment {
+    id: number;
+    transaction_id: number;
+    file_name: string;
+    mime_type: string;
+    size: number; // bytes
+    hash: string; // sha-256
+    created_at: string;
+}
+
+export interface TransactionSplit {
+    id: number;
+    category_id: number;
+    amount: Money;
+    amount_in_ars: Money;
+    note: string | null;
+}
+
+export interface DuplicateMatch {
+    transaction: Transaction;
+    similarity: number; // 0..1
+}
+
+// How an update or delete sent with a version fails; on a conflict nothing
+// was written and `current` is the row as stored now
+export type WriteError<T> =
+    | { kind: 'conflict'; message: string; current: T }
+    | { kind: 'failed'; message: string };
+
+export type AddTransactionResult =
+    | { status: 'created'; transaction: Transaction }
+    | { status: 'possible_duplicates'; duplicates: DuplicateMatch[] };
+
+// Nothing is stored unless every input is valid
+export type AddTransactionsResult =
+    | { status: 'created'; transactions: Transaction[] }
+    | { status: 'invalid'; errors: { index: number; error: string }[] };
+
 export interface DashboardStats {
     totalBalance: number;
     totalIncome: number;
@@ -1,4 +1,4 @@
-import { Category } from '../types';
+import { Category, Money, Transaction } from '../types';
 
 export function getCategoryName(categories: Category[], id: number): string {
     return categories.find(c => c.id === id)?.name ?? 'Desconocido';
@@ -11,3 +11,8 @@ export function getCategoryColor(categories: Category[], id: number): string {
 export function getCategoryById(categories: Category[], id: number): Category | undefined {
     return categories.find(c => c.id === id);
 }
+
+// Per-category shares of a transaction: its split lines, or the whole amount
+export function getCategoryShares(t: Transaction): { category_id: number; amount_in_ars: Money }[] {
+    return t.splits?.length ? t.splits : [{ category_id: t.category_id, amount_in_ars: t.amount_in_ars }];
+}
@@ -1,3 +1,4 @@
+import { parseISO } from 'date-fns';
 import { Transaction } from '../types';
 
 /**
@@ -5,7 +6,7 @@ import { Transaction } from '../types';
  * Sorted descending (newest first).
  */
 export function getAvailableYears(transactions: Transaction[]): number[] {
-    const years = Array.from(new Set(transactions.map(t => new Date(t.date).getFullYear())));
+    const years = Array.from(new Set(transactions.map(t => parseISO(t.date).getFullYear())));
     const currentYear = new Date().getFullYear();
     if (!years.includes(currentYear)) {
         years.push(currentYear);